@@ -0,0 +1,171 @@
+//! Storage for a parsed program.
+//!
+//! Every expression and statement lives in one [`Ast`] and refers to its
+//! children by [`NodeId`], so a tree is a few flat vectors rather than a
+//! box per node, and a node's id stays valid for as long as the `Ast` does.
+//! Passes that compute something per node keep it in a [`SideTable`]
+//! instead of adding fields to the tree.
+
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::ops::{Index, IndexMut};
+
+use crate::ast::{Expr, ExprKind, Span, Stmt, StmtKind};
+
+/// Index of a node of type `T` in its [`Ast`].
+pub struct NodeId<T> {
+    index: u32,
+    node: PhantomData<fn() -> T>,
+}
+
+pub type ExprId = NodeId<Expr>;
+pub type StmtId = NodeId<Stmt>;
+
+impl<T> NodeId<T> {
+    fn new(index: usize) -> Self {
+        let index = u32::try_from(index).expect("more AST nodes than fit in a NodeId");
+        Self { index, node: PhantomData }
+    }
+
+    pub fn index(self) -> usize {
+        self.index as usize
+    }
+}
+
+// Written out rather than derived so they don't require `T: Clone` etc.
+impl<T> Clone for NodeId<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for NodeId<T> {}
+
+impl<T> PartialEq for NodeId<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+    }
+}
+
+impl<T> Eq for NodeId<T> {}
+
+impl<T> Hash for NodeId<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+    }
+}
+
+impl<T> fmt::Debug for NodeId<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.index)
+    }
+}
+
+/// The nodes of one file, plus its top-level statements.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Ast {
+    exprs: Vec<Expr>,
+    stmts: Vec<Stmt>,
+    /// The file's statements, in order
+    pub body: Vec<StmtId>,
+}
+
+impl Ast {
+    pub fn add_expr(&mut self, kind: ExprKind, span: Span) -> ExprId {
+        self.exprs.push(Expr::new(kind, span));
+        NodeId::new(self.exprs.len() - 1)
+    }
+
+    pub fn add_stmt(&mut self, kind: StmtKind, span: Span) -> StmtId {
+        self.stmts.push(Stmt::new(kind, span));
+        NodeId::new(self.stmts.len() - 1)
+    }
+
+    /// Every expression id, including nodes no longer reachable from `body`.
+    pub fn expr_ids(&self) -> impl Iterator<Item = ExprId> {
+        (0..self.exprs.len()).map(NodeId::new)
+    }
+
+    /// Every statement id, including nodes no longer reachable from `body`.
+    pub fn stmt_ids(&self) -> impl Iterator<Item = StmtId> {
+        (0..self.stmts.len()).map(NodeId::new)
+    }
+}
+
+impl Index<ExprId> for Ast {
+    type Output = Expr;
+
+    fn index(&self, id: ExprId) -> &Expr {
+        &self.exprs[id.index()]
+    }
+}
+
+impl IndexMut<ExprId> for Ast {
+    fn index_mut(&mut self, id: ExprId) -> &mut Expr {
+        &mut self.exprs[id.index()]
+    }
+}
+
+impl Index<StmtId> for Ast {
+    type Output = Stmt;
+
+    fn index(&self, id: StmtId) -> &Stmt {
+        &self.stmts[id.index()]
+    }
+}
+
+impl IndexMut<StmtId> for Ast {
+    fn index_mut(&mut self, id: StmtId) -> &mut Stmt {
+        &mut self.stmts[id.index()]
+    }
+}
+
+/// A value of type `V` for some of the `T` nodes of one [`Ast`].
+#[derive(Debug, Clone)]
+pub struct SideTable<T, V> {
+    values: Vec<Option<V>>,
+    node: PhantomData<fn() -> T>,
+}
+
+impl<T, V> Default for SideTable<T, V> {
+    fn default() -> Self {
+        Self { values: Vec::new(), node: PhantomData }
+    }
+}
+
+impl<T, V> SideTable<T, V> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store `value` for `id`, returning what was there before.
+    pub fn insert(&mut self, id: NodeId<T>, value: V) -> Option<V> {
+        let index = id.index();
+        if index >= self.values.len() {
+            self.values.resize_with(index + 1, || None);
+        }
+        self.values[index].replace(value)
+    }
+
+    pub fn get(&self, id: NodeId<T>) -> Option<&V> {
+        self.values.get(id.index()).and_then(Option::as_ref)
+    }
+
+    pub fn get_mut(&mut self, id: NodeId<T>) -> Option<&mut V> {
+        self.values.get_mut(id.index()).and_then(Option::as_mut)
+    }
+
+    pub fn remove(&mut self, id: NodeId<T>) -> Option<V> {
+        self.values.get_mut(id.index()).and_then(Option::take)
+    }
+
+    pub fn contains(&self, id: NodeId<T>) -> bool {
+        self.get(id).is_some()
+    }
+
+    /// Entries in id order.
+    pub fn iter(&self) -> impl Iterator<Item = (NodeId<T>, &V)> {
+        self.values.iter().enumerate().filter_map(|(i, v)| v.as_ref().map(|v| (NodeId::new(i), v)))
+    }
+}
@@ -0,0 +1,396 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::ast::arena::SideTable;
+use crate::ast::visit::{walk_expr, walk_stmt, Visitor};
+use crate::ast::{Ast, ExprId, ExprKind, FunctionDecl, Pattern, Span, Stmt, StmtId, StmtKind};
+
+/// Attributes the language knows about. Anything else gets a warning.
+pub const KNOWN_ATTRIBUTES: &[&str] = &["test", "deprecated", "inline"];
+
+/// `@name` or `@name(args)` written before a declaration
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attribute {
+    pub name: String,
+    pub args: Vec<ExprId>,
+    pub span: Span,
+}
+
+/// Attributes by the declaration they are attached to, so passes can ask
+/// e.g. "is the `x` this name refers to deprecated?"
+#[derive(Debug, Default)]
+pub struct AttributeRegistry {
+    by_declaration: SideTable<Stmt, Vec<Attribute>>,
+}
+
+impl AttributeRegistry {
+    /// Collect the attributes of every declaration in `ast`, including nested blocks.
+    pub fn build(ast: &Ast) -> Self {
+        let mut registry = Self::default();
+        registry.visit_ast(ast);
+        registry
+    }
+
+    /// All attributes on the declaration `decl`.
+    pub fn get(&self, decl: StmtId) -> &[Attribute] {
+        self.by_declaration.get(decl).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// The attribute `attr` on the declaration `decl`, if present.
+    pub fn find(&self, decl: StmtId, attr: &str) -> Option<&Attribute> {
+        self.get(decl).iter().find(|a| a.name == attr)
+    }
+}
+
+impl Visitor for AttributeRegistry {
+    fn visit_stmt(&mut self, ast: &Ast, id: StmtId) {
+        if let StmtKind::Attributed { attributes, stmt } = &ast[id].kind {
+            if let Some(decl) = declaration(ast, *stmt) {
+                match self.by_declaration.get_mut(decl) {
+                    Some(existing) => existing.extend(attributes.iter().cloned()),
+                    None => {
+                        self.by_declaration.insert(decl, attributes.clone());
+                    }
+                }
+            }
+        }
+        walk_stmt(self, ast, id);
+    }
+}
+
+/// The declaration under any attributes and `pub` on `stmt`, or `None` if
+/// it declares nothing.
+pub fn declaration(ast: &Ast, stmt: StmtId) -> Option<StmtId> {
+    let stmt = unwrapped(ast, stmt);
+    match &ast[stmt].kind {
+        StmtKind::VarDeclaration { .. }
+        | StmtKind::Import { .. }
+        | StmtKind::Function(_)
+        | StmtKind::Interface(_)
+        | StmtKind::Class(_)
+        | StmtKind::TypeDecl { .. } => Some(stmt),
+        _ => None,
+    }
+}
+
+/// The statement inside any `@attr` and `pub` around `stmt`. Neither
+/// changes what the statement does, and a loaded AST may put either on any.
+pub fn unwrapped(ast: &Ast, stmt: StmtId) -> StmtId {
+    match &ast[stmt].kind {
+        StmtKind::Attributed { stmt, .. } | StmtKind::Public { stmt } => unwrapped(ast, *stmt),
+        _ => stmt,
+    }
+}
+
+/// Names a statement declares, for attaching attributes or exporting with `pub`.
+pub fn declared_names(ast: &Ast, stmt: StmtId) -> Vec<String> {
+    let mut names = Vec::new();
+    match &ast[stmt].kind {
+        StmtKind::VarDeclaration { pattern, .. } => pattern_names(pattern, &mut names),
+        StmtKind::Import { alias: Some(alias), .. } => names.push(alias.clone()),
+        StmtKind::Function(decl) => names.push(decl.name.clone()),
+        StmtKind::Interface(decl) => names.push(decl.name.clone()),
+        StmtKind::Class(class) => names.push(class.name.clone()),
+        StmtKind::TypeDecl { name, .. } => names.push(name.clone()),
+        StmtKind::Attributed { stmt, .. } | StmtKind::Public { stmt } => names.extend(declared_names(ast, *stmt)),
+        _ => {}
+    }
+    names
+}
+
+/// Add the names `pattern` binds to `out`.
+pub fn pattern_names(pattern: &Pattern, out: &mut Vec<String>) {
+    match pattern {
+        Pattern::Identifier(name) => out.push(name.clone()),
+        Pattern::Tuple(items) => items.iter().for_each(|p| pattern_names(p, out)),
+    }
+}
+/// The top-level functions marked `@test`, in order, which `--test` calls
+/// once the file has run.
+pub fn tests(ast: &Ast) -> Vec<String> {
+    let registry = AttributeRegistry::build(ast);
+    ast.body.iter()
+        .filter_map(|&stmt| declaration(ast, stmt))
+        .filter(|&decl| registry.find(decl, "test").is_some())
+        .filter_map(|decl| match &ast[decl].kind {
+            StmtKind::Function(function) => Some(function.name.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The warning for `attr` written on `stmt`, if it cannot apply there.
+fn misplaced(ast: &Ast, attr: &Attribute, stmt: StmtId) -> Option<String> {
+    let function = declaration(ast, stmt).and_then(|decl| match &ast[decl].kind {
+        StmtKind::Function(function) => Some(function),
+        _ => None,
+    });
+    match (attr.name.as_str(), function) {
+        ("test", Some(function)) if function.params.is_empty() => None,
+        ("test", Some(function)) => Some(format!("test '{}' cannot take parameters", function.name)),
+        ("test" | "inline", None) => Some(format!("@{} only applies to functions", attr.name)),
+        _ => None,
+    }
+}
+
+/// Warn about unknown attributes and about every use of a `@deprecated`
+/// declaration, whether by name or as `module.name` of an imported module.
+/// `load` gives the AST of an import path, if it can be read. Each warning
+/// comes with the span it points at.
+pub fn lint(ast: &Ast, load: &mut dyn FnMut(&str) -> Option<Ast>) -> Vec<(Span, String)> {
+    let registry = AttributeRegistry::build(ast);
+    let mut lint = Lint {
+        registry: &registry,
+        load,
+        modules: SideTable::new(),
+        scopes: vec![HashMap::new()],
+        deferred: Vec::new(),
+        warnings: Vec::new(),
+    };
+    lint.visit_ast(ast);
+
+    // function and method bodies run later, in frames that see the
+    // module's globals and nothing else; classes declared in them add more
+    while !lint.deferred.is_empty() {
+        for (frame, stmts, exprs) in std::mem::take(&mut lint.deferred) {
+            lint.scopes.push(frame.into_iter().map(|name| (name, None)).collect());
+            stmts.iter().for_each(|&s| lint.visit_stmt(ast, s));
+            exprs.iter().for_each(|&e| lint.visit_expr(ast, e));
+            lint.scopes.pop();
+        }
+    }
+    lint.warnings.sort_by_key(|(span, _)| (span.line, span.column));
+    lint.warnings
+}
+
+/// An imported module's top-level declarations and their attributes.
+struct Module {
+    ast: Ast,
+    registry: AttributeRegistry,
+    globals: HashMap<String, StmtId>,
+}
+
+struct Lint<'a> {
+    registry: &'a AttributeRegistry,
+    load: &'a mut dyn FnMut(&str) -> Option<Ast>,
+    /// The module each `import` brings in, if it could be loaded
+    modules: SideTable<Stmt, Module>,
+    /// The declaration each name in scope refers to, globals first; `None`
+    /// for names bound by no statement, such as parameters
+    scopes: Vec<HashMap<String, Option<StmtId>>>,
+    /// Function and method bodies and `extends` arguments, with the names
+    /// their frame binds
+    deferred: Vec<(Vec<String>, Vec<StmtId>, Vec<ExprId>)>,
+    warnings: Vec<(Span, String)>,
+}
+
+impl Lint<'_> {
+    fn lookup(&self, name: &str) -> Option<StmtId> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name)).copied().flatten()
+    }
+
+    fn declare(&mut self, name: String, decl: Option<StmtId>) {
+        self.scopes.last_mut().expect("a scope").insert(name, decl);
+    }
+
+    fn scoped(&mut self, f: impl FnOnce(&mut Self)) {
+        self.scopes.push(HashMap::new());
+        f(self);
+        self.scopes.pop();
+    }
+
+    fn defer_function(&mut self, decl: &FunctionDecl) {
+        let frame = decl.params.iter().map(|p| p.name.clone()).collect();
+        self.deferred.push((frame, decl.body.clone(), decl.value.into_iter().collect()));
+    }
+}
+
+/// The warning for a use of `name`, if `attributes` (written in `ast`)
+/// deprecate it.
+fn deprecation(ast: &Ast, attributes: &[Attribute], name: &str) -> Option<String> {
+    let attr = attributes.iter().find(|a| a.name == "deprecated")?;
+    Some(match attr.args.first().map(|&a| &ast[a].kind) {
+        Some(ExprKind::StringLiteral(note)) => format!("use of deprecated '{}': {}", name, note),
+        _ => format!("use of deprecated '{}'", name),
+    })
+}
+
+impl Visitor for Lint<'_> {
+    fn visit_attribute(&mut self, ast: &Ast, attr: &Attribute) {
+        if !KNOWN_ATTRIBUTES.contains(&attr.name.as_str()) {
+            self.warnings.push((attr.span, format!("unknown attribute '@{}'", attr.name)));
+        }
+        attr.args.iter().for_each(|&e| self.visit_expr(ast, e));
+    }
+
+    fn visit_stmt(&mut self, ast: &Ast, id: StmtId) {
+        let visit_all = |lint: &mut Self, stmts: &[StmtId]| stmts.iter().for_each(|&s| lint.visit_stmt(ast, s));
+
+        match &ast[id].kind {
+            StmtKind::VarDeclaration { pattern, value, .. } => {
+                self.visit_expr(ast, *value);
+                let mut names = Vec::new();
+                pattern_names(pattern, &mut names);
+                names.into_iter().for_each(|name| self.declare(name, Some(id)));
+            }
+            StmtKind::Class(decl) => {
+                self.declare(decl.name.clone(), Some(id));
+                if let Some(parent) = &decl.parent {
+                    let fields = decl.params.iter().map(|p| p.name.clone()).collect();
+                    self.deferred.push((fields, Vec::new(), parent.args.clone()));
+                }
+                for method in &decl.methods {
+                    let frame = std::iter::once("self".to_string()).chain(method.params.iter().map(|p| p.name.clone()));
+                    self.deferred.push((frame.collect(), Vec::new(), vec![method.body]));
+                }
+            }
+            StmtKind::Attributed { attributes, stmt } => {
+                for attr in attributes {
+                    if let Some(message) = misplaced(ast, attr, *stmt) {
+                        self.warnings.push((attr.span, message));
+                    }
+                }
+                walk_stmt(self, ast, id);
+            }
+            StmtKind::Function(decl) => {
+                self.declare(decl.name.clone(), Some(id));
+                self.defer_function(decl);
+            }
+            StmtKind::Interface(decl) => self.declare(decl.name.clone(), Some(id)),
+            StmtKind::Impl(decl) => decl.methods.iter().for_each(|method| self.defer_function(method)),
+            StmtKind::TypeDecl { name, .. } => self.declare(name.clone(), Some(id)),
+            StmtKind::Import { path, alias } => {
+                // `import "lib/math.sst"` is bound as `math`
+                let stem = || Path::new(path).file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+                self.declare(alias.clone().unwrap_or_else(stem), Some(id));
+                if let Some(ast) = (self.load)(path) {
+                    let globals = ast.body.iter()
+                        .flat_map(|&s| declared_names(&ast, s).into_iter().zip(declaration(&ast, s)))
+                        .collect();
+                    let registry = AttributeRegistry::build(&ast);
+                    self.modules.insert(id, Module { ast, registry, globals });
+                }
+            }
+            StmtKind::Defer { body } => self.scoped(|lint| visit_all(lint, body)),
+            StmtKind::For { pattern, iterable, body } => {
+                self.visit_expr(ast, *iterable);
+                self.scoped(|lint| {
+                    let mut names = Vec::new();
+                    pattern_names(pattern, &mut names);
+                    names.into_iter().for_each(|name| lint.declare(name, None));
+                    visit_all(lint, body);
+                });
+            }
+            StmtKind::Try { body, catch, finally } => {
+                self.scoped(|lint| visit_all(lint, body));
+                if let Some(clause) = catch {
+                    self.scoped(|lint| {
+                        if let Some(name) = &clause.name {
+                            lint.declare(name.clone(), None);
+                        }
+                        visit_all(lint, &clause.body);
+                    });
+                }
+                if let Some(finally) = finally {
+                    self.scoped(|lint| visit_all(lint, finally));
+                }
+            }
+            _ => walk_stmt(self, ast, id),
+        }
+    }
+
+    fn visit_expr(&mut self, ast: &Ast, id: ExprId) {
+        let expr = &ast[id];
+        match &expr.kind {
+            ExprKind::Identifier(name) => {
+                let decl = self.lookup(name);
+                if let Some(message) = decl.and_then(|decl| deprecation(ast, self.registry.get(decl), name)) {
+                    self.warnings.push((expr.span, message));
+                }
+            }
+            // `lib.old`, where `old` is deprecated in the module imported as `lib`
+            ExprKind::Member { object, name, .. } => {
+                if let ExprKind::Identifier(alias) = &ast[*object].kind {
+                    let module = self.lookup(alias).and_then(|import| self.modules.get(import));
+                    let message = module.and_then(|module| {
+                        let decl = *module.globals.get(name)?;
+                        deprecation(&module.ast, module.registry.get(decl), &format!("{}.{}", alias, name))
+                    });
+                    if let Some(message) = message {
+                        self.warnings.push((expr.span, message));
+                    }
+                }
+                walk_expr(self, ast, id);
+            }
+            ExprKind::Block { .. } => self.scoped(|lint| walk_expr(lint, ast, id)),
+            _ => walk_expr(self, ast, id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lexer as lex;
+    use crate::parser::Parser;
+
+    fn parse(source: &str) -> Ast {
+        Parser::new(lex(source)).parse()
+    }
+
+    /// The warnings for `source`, which can import `lib.sst`.
+    fn warnings(source: &str, lib: &str) -> Vec<String> {
+        let ast = parse(source);
+        lint(&ast, &mut |path| (path == "lib.sst").then(|| parse(lib)))
+            .into_iter()
+            .map(|(span, message)| format!("{}: {}", span, message))
+            .collect()
+    }
+
+    #[test]
+    fn uses_are_matched_to_their_declaration() {
+        assert_eq!(warnings(r#"
+@deprecated let x = 1;
+let w = { let x = 2; x };
+class C(x) {
+    fn f() { x }
+    fn g(y) { y + self.x }
+}
+for x in 0..3 { println(x); }
+println(x);
+"#, ""), ["5:14-15: use of deprecated 'x'", "9:9-10: use of deprecated 'x'"]);
+        assert_eq!(warnings("@odd(1) let y = 1;", ""), ["1:1-8: unknown attribute '@odd'"]);
+        assert_eq!(warnings(r#"
+@deprecated("use g") fn f(x) { x }
+fn h(f) { f(1) }
+fn k() { f(2) }
+"#, ""), ["4:10-11: use of deprecated 'f': use g"]);
+    }
+
+    #[test]
+    fn tests_are_top_level_functions_without_parameters() {
+        let ast = parse("@test fn a() {} fn b() {} @test pub fn c() {} @test fn d(x) {} @test let e = 1;");
+        assert_eq!(tests(&ast), ["a", "c", "d"]);
+        assert_eq!(warnings("@test fn d(x) {}\n@test let e = 1;", ""), [
+            "1:1-6: test 'd' cannot take parameters",
+            "2:1-6: @test only applies to functions",
+        ]);
+    }
+
+    #[test]
+    fn inline_only_applies_to_functions() {
+        assert_eq!(warnings("@inline fn f(x) { x }\n@inline let g = 1;", ""), ["2:1-8: @inline only applies to functions"]);
+    }
+
+    #[test]
+    fn members_of_imported_modules_are_checked() {
+        let lib = r#"@deprecated("use new") pub const old = 1; pub const new = 2;"#;
+        assert_eq!(
+            warnings("import \"lib.sst\";\nprintln(lib.old + lib.new);", lib),
+            ["2:9-16: use of deprecated 'lib.old': use new"],
+        );
+        assert_eq!(warnings("import m from \"lib.sst\";\nlet lib = 1;\nprintln(m.old + lib.old);", lib), [
+            "3:9-14: use of deprecated 'm.old': use new",
+        ]);
+    }
+}
@@ -0,0 +1,752 @@
+//! Tokens and ASTs as [`Json`] documents, and JSON back into an AST.
+//!
+//! Every node is an object whose `"type"` is the variant name and whose
+//! other keys are the variant's fields, plus a `"span"` of
+//! `[line, column, end_line, end_column]`:
+//!
+//! ```text
+//! {"type": "Binary", "left": {...}, "op": "+", "right": {...}, "span": [1, 9, 1, 14]}
+//! ```
+//!
+//! A program is an array of statements. When loading, `span` may be left
+//! out and optional fields may be `null` or missing, so tools generating
+//! programs only need to write what they mean. A node without a span takes
+//! the span of the node it is part of, so errors still point somewhere.
+
+use crate::ast::attributes::declaration;
+use crate::ast::json::Json;
+use crate::ast::visit::{walk_expr_mut, walk_stmt_mut, VisitorMut};
+use crate::ast::{
+    Ast, Attribute, CatchClause, ClassDecl, ExprId, ExprKind, FunctionDecl, ImplDecl, InterfaceDecl, MethodDecl,
+    MethodSig, Param, ParentClass, Pattern, Span, StmtId, StmtKind, TypeExpr, TypeParam,
+};
+use crate::lexer::{Operator, Token, TokenKind};
+
+// --------------------------
+// TOKENS
+// --------------------------
+
+/// The tokens as an array of `{"type": "Keyword", "value": "Let", "span": [...]}`.
+pub fn tokens(tokens: &[Token]) -> Json {
+    Json::Array(tokens.iter().map(token).collect())
+}
+
+fn token(tok: &Token) -> Json {
+    let (kind, value) = match &tok.kind {
+        TokenKind::Keyword(k) => ("Keyword", Json::Str(format!("{:?}", k))),
+        TokenKind::Identifier(name) => ("Identifier", Json::str(name)),
+        TokenKind::Number(n) => ("Number", Json::Int(*n)),
+        TokenKind::Str(s) => ("Str", Json::str(s)),
+        TokenKind::Operator(op) => ("Operator", Json::str(op.as_str())),
+        TokenKind::Symbol(s) => ("Symbol", Json::Str(format!("{:?}", s))),
+    };
+    let span = Span::of(tok);
+    let mut fields = vec![("value", value), ("span", span_json(&span))];
+    if let Some(line) = tok.expanded_at {
+        fields.push(("expanded_at", Json::Int(line as i64)));
+    }
+    Json::node(kind, fields)
+}
+
+// --------------------------
+// AST -> JSON
+// --------------------------
+
+/// A whole program: an array of statement nodes.
+pub fn program(ast: &Ast) -> Json {
+    stmts(ast, &ast.body)
+}
+
+fn stmts(ast: &Ast, statements: &[StmtId]) -> Json {
+    Json::Array(statements.iter().map(|&s| stmt(ast, s)).collect())
+}
+
+fn exprs(ast: &Ast, exprs: &[ExprId]) -> Json {
+    Json::Array(exprs.iter().map(|&e| expr(ast, e)).collect())
+}
+
+fn strings(names: &[String]) -> Json {
+    Json::Array(names.iter().map(|n| Json::str(n)).collect())
+}
+
+/// `[line, column, end_line, end_column]`, plus the macro call's line for
+/// text from a macro definition
+fn span_json(span: &Span) -> Json {
+    let numbers = [span.line, span.column, span.end_line, span.end_column].into_iter().chain(span.expanded_at);
+    Json::Array(numbers.map(|n| Json::Int(n as i64)).collect())
+}
+
+fn with_span(kind: &str, mut fields: Vec<(&str, Json)>, span: &Span) -> Json {
+    fields.push(("span", span_json(span)));
+    Json::node(kind, fields)
+}
+
+fn stmt(ast: &Ast, id: StmtId) -> Json {
+    let stmt = &ast[id];
+    let (kind, fields) = match &stmt.kind {
+        StmtKind::Attributed { attributes, stmt: inner } => ("Attributed", vec![
+            ("attributes", Json::Array(attributes.iter().map(|a| attribute(ast, a)).collect())),
+            ("stmt", self::stmt(ast, *inner)),
+        ]),
+        StmtKind::Public { stmt: inner } => ("Public", vec![("stmt", self::stmt(ast, *inner))]),
+        StmtKind::VarDeclaration { constant, ty, pattern: p, value } => ("VarDeclaration", vec![
+            ("constant", Json::Bool(*constant)),
+            ("ty", Json::opt(ty.as_ref(), type_expr)),
+            ("pattern", pattern(p)),
+            ("value", expr(ast, *value)),
+        ]),
+        StmtKind::Function(decl) => ("Function", function(ast, decl)),
+        StmtKind::Interface(decl) => ("Interface", vec![
+            ("name", Json::str(&decl.name)),
+            ("methods", Json::Array(decl.methods.iter().map(|m| Json::Object(vec![
+                ("name".to_string(), Json::str(&m.name)),
+                ("params".to_string(), params(&m.params)),
+                ("returns".to_string(), Json::opt(m.returns.as_ref(), type_expr)),
+            ])).collect())),
+        ]),
+        StmtKind::Impl(decl) => ("Impl", vec![
+            ("interface", Json::str(&decl.interface)),
+            ("target", Json::str(&decl.target)),
+            ("methods", Json::Array(decl.methods.iter().map(|m| Json::node("Function", function(ast, m))).collect())),
+        ]),
+        StmtKind::Class(decl) => ("Class", class(ast, decl)),
+        StmtKind::TypeDecl { name, target, nominal } => ("TypeDecl", vec![
+            ("name", Json::str(name)),
+            ("target", Json::str(target)),
+            ("nominal", Json::Bool(*nominal)),
+        ]),
+        StmtKind::Import { path, alias } => ("Import", vec![
+            ("path", Json::str(path)),
+            ("alias", Json::opt(alias.as_deref(), Json::str)),
+        ]),
+        StmtKind::Expression { expr: e } => ("Expression", vec![("expr", expr(ast, *e))]),
+        StmtKind::Print { newline, expr: e } => ("Print", vec![("newline", Json::Bool(*newline)), ("expr", expr(ast, *e))]),
+        StmtKind::Throw { expr: e } => ("Throw", vec![("expr", expr(ast, *e))]),
+        StmtKind::Defer { body } => ("Defer", vec![("body", stmts(ast, body))]),
+        StmtKind::For { pattern: p, iterable, body } => ("For", vec![
+            ("pattern", pattern(p)),
+            ("iterable", expr(ast, *iterable)),
+            ("body", stmts(ast, body)),
+        ]),
+        StmtKind::Try { body, catch, finally } => ("Try", vec![
+            ("body", stmts(ast, body)),
+            ("catch", Json::opt(catch.as_ref(), |c| Json::Object(vec![
+                ("name".to_string(), Json::opt(c.name.as_deref(), Json::str)),
+                ("body".to_string(), stmts(ast, &c.body)),
+            ]))),
+            ("finally", Json::opt(finally.as_deref(), |s| stmts(ast, s))),
+        ]),
+    };
+    with_span(kind, fields, &stmt.span)
+}
+
+fn function(ast: &Ast, decl: &FunctionDecl) -> Vec<(&'static str, Json)> {
+    vec![
+        ("name", Json::str(&decl.name)),
+        ("type_params", type_params(&decl.type_params)),
+        ("params", params(&decl.params)),
+        ("returns", Json::opt(decl.returns.as_ref(), type_expr)),
+        ("body", stmts(ast, &decl.body)),
+        ("value", Json::opt(decl.value, |e| expr(ast, e))),
+    ]
+}
+
+fn class(ast: &Ast, decl: &ClassDecl) -> Vec<(&'static str, Json)> {
+    vec![
+        ("name", Json::str(&decl.name)),
+        ("type_params", type_params(&decl.type_params)),
+        ("params", params(&decl.params)),
+        ("public_fields", strings(&decl.public_fields)),
+        ("parent", Json::opt(decl.parent.as_ref(), |p| Json::Object(vec![
+            ("name".to_string(), Json::str(&p.name)),
+            ("args".to_string(), exprs(ast, &p.args)),
+        ]))),
+        ("methods", Json::Array(decl.methods.iter().map(|m| Json::Object(vec![
+            ("name".to_string(), Json::str(&m.name)),
+            ("public".to_string(), Json::Bool(m.public)),
+            ("type_params".to_string(), type_params(&m.type_params)),
+            ("params".to_string(), params(&m.params)),
+            ("returns".to_string(), Json::opt(m.returns.as_ref(), type_expr)),
+            ("body".to_string(), expr(ast, m.body)),
+        ])).collect())),
+    ]
+}
+
+/// `[{"name": "a", "ty": {"type": "Named", "name": "int"}}, {"name": "b", "ty": null}]`
+fn params(params: &[Param]) -> Json {
+    Json::Array(params.iter().map(|p| Json::Object(vec![
+        ("name".to_string(), Json::str(&p.name)),
+        ("ty".to_string(), Json::opt(p.ty.as_ref(), type_expr)),
+    ])).collect())
+}
+
+fn type_params(params: &[TypeParam]) -> Json {
+    Json::Array(params.iter().map(|p| Json::Object(vec![
+        ("name".to_string(), Json::str(&p.name)),
+        ("bound".to_string(), Json::opt(p.bound.as_deref(), Json::str)),
+    ])).collect())
+}
+
+fn type_expr(ty: &TypeExpr) -> Json {
+    match ty {
+        TypeExpr::Named(name) => Json::node("Named", vec![("name", Json::str(name))]),
+        TypeExpr::Tuple(items) => Json::node("Tuple", vec![("items", Json::Array(items.iter().map(type_expr).collect()))]),
+        TypeExpr::Generic(name, args) => Json::node("Generic", vec![
+            ("name", Json::str(name)),
+            ("args", Json::Array(args.iter().map(type_expr).collect())),
+        ]),
+        TypeExpr::Optional(inner) => Json::node("Optional", vec![("inner", type_expr(inner))]),
+    }
+}
+
+fn attribute(ast: &Ast, attr: &Attribute) -> Json {
+    Json::Object(vec![
+        ("name".to_string(), Json::str(&attr.name)),
+        ("args".to_string(), exprs(ast, &attr.args)),
+        ("span".to_string(), span_json(&attr.span)),
+    ])
+}
+
+fn pattern(pattern: &Pattern) -> Json {
+    match pattern {
+        Pattern::Identifier(name) => Json::node("Identifier", vec![("name", Json::str(name))]),
+        Pattern::Tuple(items) => Json::node("Tuple", vec![("items", Json::Array(items.iter().map(self::pattern).collect()))]),
+    }
+}
+
+fn expr(ast: &Ast, id: ExprId) -> Json {
+    let e = &ast[id];
+    let (kind, fields) = match &e.kind {
+        ExprKind::Identifier(name) => ("Identifier", vec![("name", Json::str(name))]),
+        ExprKind::StringLiteral(s) => ("StringLiteral", vec![("value", Json::str(s))]),
+        ExprKind::IntLiteral(i) => ("IntLiteral", vec![("value", Json::Int(*i))]),
+        ExprKind::BoolLiteral(b) => ("BoolLiteral", vec![("value", Json::Bool(*b))]),
+        ExprKind::NoneLiteral => ("NoneLiteral", vec![]),
+        ExprKind::Ok(inner) => ("Ok", vec![("expr", expr(ast, *inner))]),
+        ExprKind::Err(inner) => ("Err", vec![("expr", expr(ast, *inner))]),
+        ExprKind::Some(inner) => ("Some", vec![("expr", expr(ast, *inner))]),
+        ExprKind::Propagate(inner) => ("Propagate", vec![("expr", expr(ast, *inner))]),
+        ExprKind::Tuple(items) => ("Tuple", vec![("items", exprs(ast, items))]),
+        ExprKind::TupleIndex { tuple, index, optional } => ("TupleIndex", vec![
+            ("tuple", expr(ast, *tuple)),
+            ("index", Json::Int(*index as i64)),
+            ("optional", Json::Bool(*optional)),
+        ]),
+        ExprKind::Member { object, name, optional } => ("Member", vec![
+            ("object", expr(ast, *object)),
+            ("name", Json::str(name)),
+            ("optional", Json::Bool(*optional)),
+        ]),
+        ExprKind::Range { start, end, inclusive, step } => ("Range", vec![
+            ("start", expr(ast, *start)),
+            ("end", expr(ast, *end)),
+            ("inclusive", Json::Bool(*inclusive)),
+            ("step", Json::opt(*step, |e| expr(ast, e))),
+        ]),
+        ExprKind::Slice { target, range } => ("Slice", vec![("target", expr(ast, *target)), ("range", expr(ast, *range))]),
+        ExprKind::Block { statements, value } => ("Block", vec![
+            ("statements", stmts(ast, statements)),
+            ("value", Json::opt(*value, |e| expr(ast, e))),
+        ]),
+        ExprKind::If { condition, then_branch, else_branch } => ("If", vec![
+            ("condition", expr(ast, *condition)),
+            ("then_branch", expr(ast, *then_branch)),
+            ("else_branch", Json::opt(*else_branch, |e| expr(ast, e))),
+        ]),
+        ExprKind::Ternary { condition, then_expr, else_expr } => ("Ternary", vec![
+            ("condition", expr(ast, *condition)),
+            ("then_expr", expr(ast, *then_expr)),
+            ("else_expr", expr(ast, *else_expr)),
+        ]),
+        ExprKind::Call { callee, args } => ("Call", vec![("callee", expr(ast, *callee)), ("args", exprs(ast, args))]),
+        ExprKind::Super { method } => ("Super", vec![("method", Json::str(method))]),
+        ExprKind::Binary { left, op, right } => ("Binary", vec![
+            ("left", expr(ast, *left)),
+            ("op", Json::str(op.as_str())),
+            ("right", expr(ast, *right)),
+        ]),
+    };
+    with_span(kind, fields, &e.span)
+}
+
+// --------------------------
+// JSON -> AST
+// --------------------------
+
+/// Rebuild a program from the output of [`program`] (or anything shaped like it).
+/// Errors name the node and field that didn't fit.
+pub fn load(json: &Json) -> Result<Ast, String> {
+    let mut ast = Ast::default();
+    ast.body = load_stmts(&mut ast, json, "program")?;
+    InheritSpans(Span::default()).visit_ast_mut(&mut ast);
+    Ok(ast)
+}
+
+/// Gives each node loaded without a span the span of its parent.
+struct InheritSpans(Span);
+
+impl InheritSpans {
+    /// `span`, or the parent's if it was left out, which the node's
+    /// children then inherit while `walk` runs.
+    fn inherit(&mut self, span: &mut Span, walk: impl FnOnce(&mut Self)) {
+        if *span == Span::default() {
+            *span = self.0;
+        }
+        let parent = std::mem::replace(&mut self.0, *span);
+        walk(self);
+        self.0 = parent;
+    }
+}
+
+impl VisitorMut for InheritSpans {
+    fn visit_stmt_mut(&mut self, ast: &mut Ast, id: StmtId) {
+        let mut span = ast[id].span;
+        self.inherit(&mut span, |this| {
+            if let StmtKind::Attributed { attributes, .. } = &mut ast[id].kind {
+                for attr in attributes.iter_mut().filter(|attr| attr.span == Span::default()) {
+                    attr.span = this.0;
+                }
+            }
+            walk_stmt_mut(this, ast, id)
+        });
+        ast[id].span = span;
+    }
+
+    fn visit_expr_mut(&mut self, ast: &mut Ast, id: ExprId) {
+        let mut span = ast[id].span;
+        self.inherit(&mut span, |this| walk_expr_mut(this, ast, id));
+        ast[id].span = span;
+    }
+}
+
+/// The fields of one JSON object, read with a node name for error messages.
+struct Fields<'a> {
+    node: String,
+    json: &'a Json,
+}
+
+impl<'a> Fields<'a> {
+    fn of(json: &'a Json, what: &str) -> Result<Self, String> {
+        match json {
+            Json::Object(_) => {
+                let node = match json.get("type") {
+                    Some(Json::Str(kind)) => kind.clone(),
+                    _ => what.to_string(),
+                };
+                Ok(Self { node, json })
+            }
+            other => Err(format!("expected {} object, got {}", what, other.describe())),
+        }
+    }
+
+    fn kind(&self) -> Result<&'a str, String> {
+        match self.json.get("type") {
+            Some(Json::Str(kind)) => Ok(kind),
+            Some(other) => Err(format!("`type` must be a string, got {}", other.describe())),
+            None => Err(format!("{} object has no `type`", self.node)),
+        }
+    }
+
+    fn field(&self, key: &str) -> Result<&'a Json, String> {
+        self.json.get(key).ok_or_else(|| format!("{} is missing `{}`", self.node, key))
+    }
+
+    /// A field that may be `null` or left out
+    fn optional(&self, key: &str) -> Option<&'a Json> {
+        self.json.get(key).filter(|v| **v != Json::Null)
+    }
+
+    fn mismatch(&self, key: &str, expected: &str, got: &Json) -> String {
+        format!("{}.{} should be {}, got {}", self.node, key, expected, got.describe())
+    }
+
+    fn string(&self, key: &str) -> Result<String, String> {
+        match self.field(key)? {
+            Json::Str(s) => Ok(s.clone()),
+            other => Err(self.mismatch(key, "a string", other)),
+        }
+    }
+
+    fn opt_string(&self, key: &str) -> Result<Option<String>, String> {
+        match self.optional(key) {
+            None => Ok(None),
+            Some(Json::Str(s)) => Ok(Some(s.clone())),
+            Some(other) => Err(self.mismatch(key, "a string", other)),
+        }
+    }
+
+    fn strings(&self, key: &str) -> Result<Vec<String>, String> {
+        match self.field(key)? {
+            Json::Array(items) => items.iter()
+                .map(|item| match item {
+                    Json::Str(s) => Ok(s.clone()),
+                    other => Err(self.mismatch(key, "an array of strings", other)),
+                })
+                .collect(),
+            other => Err(self.mismatch(key, "an array of strings", other)),
+        }
+    }
+
+    fn bool(&self, key: &str) -> Result<bool, String> {
+        match self.field(key)? {
+            Json::Bool(b) => Ok(*b),
+            other => Err(self.mismatch(key, "true or false", other)),
+        }
+    }
+
+    fn int(&self, key: &str) -> Result<i64, String> {
+        match self.field(key)? {
+            Json::Int(i) => Ok(*i),
+            other => Err(self.mismatch(key, "a number", other)),
+        }
+    }
+
+    fn index(&self, key: &str) -> Result<usize, String> {
+        let i = self.int(key)?;
+        usize::try_from(i).map_err(|_| format!("{}.{} should not be negative, got {}", self.node, key, i))
+    }
+
+    fn span(&self) -> Result<Span, String> {
+        let parts = match self.optional("span") {
+            None => return Ok(Span::default()),
+            Some(Json::Array(parts)) if parts.len() == 4 || parts.len() == 5 => parts,
+            Some(other) => return Err(self.mismatch("span", "[line, column, end_line, end_column, expanded_at?]", other)),
+        };
+        let mut numbers = [0; 5];
+        for (n, part) in numbers.iter_mut().zip(parts) {
+            *n = match part {
+                Json::Int(i) if *i >= 0 => *i as usize,
+                other => return Err(self.mismatch("span", "four or five non-negative numbers", other)),
+            };
+        }
+        let [line, column, end_line, end_column, call] = numbers;
+        let expanded_at = (parts.len() == 5).then_some(call);
+        Ok(Span { line, column, end_line, end_column, expanded_at })
+    }
+
+    fn expr(&self, ast: &mut Ast, key: &str) -> Result<ExprId, String> {
+        load_expr(ast, self.field(key)?)
+    }
+
+    fn opt_expr(&self, ast: &mut Ast, key: &str) -> Result<Option<ExprId>, String> {
+        self.optional(key).map(|v| load_expr(ast, v)).transpose()
+    }
+
+    fn exprs(&self, ast: &mut Ast, key: &str) -> Result<Vec<ExprId>, String> {
+        match self.field(key)? {
+            Json::Array(items) => items.iter().map(|item| load_expr(ast, item)).collect(),
+            other => Err(self.mismatch(key, "an array of expressions", other)),
+        }
+    }
+
+    fn stmts(&self, ast: &mut Ast, key: &str) -> Result<Vec<StmtId>, String> {
+        load_stmts(ast, self.field(key)?, &format!("{}.{}", self.node, key))
+    }
+
+    fn pattern(&self, key: &str) -> Result<Pattern, String> {
+        load_pattern(self.field(key)?)
+    }
+
+    fn opt_type(&self, key: &str) -> Result<Option<TypeExpr>, String> {
+        self.optional(key).map(load_type).transpose()
+    }
+
+    fn params(&self, key: &str) -> Result<Vec<Param>, String> {
+        self.array(key, "parameters")?.iter()
+            .map(|json| {
+                let p = Fields::of(json, "parameter")?;
+                Ok(Param { name: p.string("name")?, ty: p.opt_type("ty")? })
+            })
+            .collect()
+    }
+
+    /// Type parameters; a declaration without any may leave the field out
+    fn type_params(&self, key: &str) -> Result<Vec<TypeParam>, String> {
+        if self.optional(key).is_none() {
+            return Ok(Vec::new());
+        }
+        self.array(key, "type parameters")?.iter()
+            .map(|json| {
+                let p = Fields::of(json, "type parameter")?;
+                Ok(TypeParam { name: p.string("name")?, bound: p.opt_string("bound")? })
+            })
+            .collect()
+    }
+
+    fn array(&self, key: &str, what: &str) -> Result<&'a [Json], String> {
+        match self.field(key)? {
+            Json::Array(items) => Ok(items),
+            other => Err(self.mismatch(key, &format!("an array of {}", what), other)),
+        }
+    }
+}
+
+fn load_stmts(ast: &mut Ast, json: &Json, what: &str) -> Result<Vec<StmtId>, String> {
+    match json {
+        Json::Array(items) => items.iter().map(|item| load_stmt(ast, item)).collect(),
+        other => Err(format!("{} should be an array of statements, got {}", what, other.describe())),
+    }
+}
+
+fn load_stmt(ast: &mut Ast, json: &Json) -> Result<StmtId, String> {
+    let f = Fields::of(json, "statement")?;
+    let kind = match f.kind()? {
+        "Attributed" => StmtKind::Attributed {
+            attributes: match f.field("attributes")? {
+                Json::Array(items) => items.iter().map(|item| load_attribute(ast, item)).collect::<Result<_, _>>()?,
+                other => return Err(f.mismatch("attributes", "an array", other)),
+            },
+            stmt: match load_stmt(ast, f.field("stmt")?)? {
+                stmt if declaration(ast, stmt).is_some() => stmt,
+                _ => return Err(f.mismatch("stmt", "a declaration", f.field("stmt")?)),
+            },
+        },
+        "Public" => StmtKind::Public { stmt: load_stmt(ast, f.field("stmt")?)? },
+        "VarDeclaration" => StmtKind::VarDeclaration {
+            constant: f.bool("constant")?,
+            ty: f.opt_type("ty")?,
+            pattern: f.pattern("pattern")?,
+            value: f.expr(ast, "value")?,
+        },
+        "Function" => StmtKind::Function(load_function(ast, &f)?),
+        "Interface" => StmtKind::Interface(InterfaceDecl {
+            name: f.string("name")?,
+            methods: f.array("methods", "method signatures")?.iter()
+                .map(|json| {
+                    let m = Fields::of(json, "method signature")?;
+                    Ok(MethodSig { name: m.string("name")?, params: m.params("params")?, returns: m.opt_type("returns")? })
+                })
+                .collect::<Result<_, String>>()?,
+        }),
+        "Impl" => StmtKind::Impl(ImplDecl {
+            interface: f.string("interface")?,
+            target: f.string("target")?,
+            methods: f.array("methods", "methods")?.iter()
+                .map(|json| load_function(ast, &Fields::of(json, "method")?))
+                .collect::<Result<_, String>>()?,
+        }),
+        "Class" => StmtKind::Class(load_class(ast, &f)?),
+        "TypeDecl" => StmtKind::TypeDecl {
+            name: f.string("name")?,
+            target: f.string("target")?,
+            nominal: f.bool("nominal")?,
+        },
+        "Import" => StmtKind::Import { path: f.string("path")?, alias: f.opt_string("alias")? },
+        "Expression" => StmtKind::Expression { expr: f.expr(ast, "expr")? },
+        "Print" => StmtKind::Print { newline: f.bool("newline")?, expr: f.expr(ast, "expr")? },
+        "Throw" => StmtKind::Throw { expr: f.expr(ast, "expr")? },
+        "Defer" => StmtKind::Defer { body: f.stmts(ast, "body")? },
+        "For" => StmtKind::For {
+            pattern: f.pattern("pattern")?,
+            iterable: f.expr(ast, "iterable")?,
+            body: f.stmts(ast, "body")?,
+        },
+        "Try" => {
+            let catch = match f.optional("catch") {
+                None => None,
+                Some(json) => {
+                    let c = Fields::of(json, "catch clause")?;
+                    Some(CatchClause { name: c.opt_string("name")?, body: c.stmts(ast, "body")? })
+                }
+            };
+            let finally = match f.optional("finally") {
+                None => None,
+                Some(json) => Some(load_stmts(ast, json, "Try.finally")?),
+            };
+            if catch.is_none() && finally.is_none() {
+                return Err("Try needs a `catch` or a `finally`".to_string());
+            }
+            StmtKind::Try { body: f.stmts(ast, "body")?, catch, finally }
+        }
+        other => return Err(format!("unknown statement type '{}'", other)),
+    };
+    Ok(ast.add_stmt(kind, f.span()?))
+}
+
+fn load_function(ast: &mut Ast, f: &Fields) -> Result<FunctionDecl, String> {
+    Ok(FunctionDecl {
+        name: f.string("name")?,
+        type_params: f.type_params("type_params")?,
+        params: f.params("params")?,
+        returns: f.opt_type("returns")?,
+        body: f.stmts(ast, "body")?,
+        value: f.opt_expr(ast, "value")?,
+    })
+}
+
+fn load_class(ast: &mut Ast, f: &Fields) -> Result<ClassDecl, String> {
+    let parent = match f.optional("parent") {
+        None => None,
+        Some(json) => {
+            let p = Fields::of(json, "parent class")?;
+            Some(ParentClass { name: p.string("name")?, args: p.exprs(ast, "args")? })
+        }
+    };
+    let methods = match f.field("methods")? {
+        Json::Array(items) => items.iter()
+            .map(|json| {
+                let m = Fields::of(json, "method")?;
+                Ok(MethodDecl {
+                    name: m.string("name")?,
+                    public: m.bool("public")?,
+                    type_params: m.type_params("type_params")?,
+                    params: m.params("params")?,
+                    returns: m.opt_type("returns")?,
+                    body: m.expr(ast, "body")?,
+                })
+            })
+            .collect::<Result<_, String>>()?,
+        other => return Err(f.mismatch("methods", "an array", other)),
+    };
+    Ok(ClassDecl {
+        name: f.string("name")?,
+        type_params: f.type_params("type_params")?,
+        params: f.params("params")?,
+        public_fields: f.strings("public_fields")?,
+        parent,
+        methods,
+    })
+}
+
+fn load_attribute(ast: &mut Ast, json: &Json) -> Result<Attribute, String> {
+    let f = Fields::of(json, "attribute")?;
+    Ok(Attribute { name: f.string("name")?, args: f.exprs(ast, "args")?, span: f.span()? })
+}
+
+fn load_pattern(json: &Json) -> Result<Pattern, String> {
+    let f = Fields::of(json, "pattern")?;
+    match f.kind()? {
+        "Identifier" => Ok(Pattern::Identifier(f.string("name")?)),
+        "Tuple" => match f.field("items")? {
+            Json::Array(items) => Ok(Pattern::Tuple(items.iter().map(load_pattern).collect::<Result<_, _>>()?)),
+            other => Err(f.mismatch("items", "an array of patterns", other)),
+        },
+        other => Err(format!("unknown pattern type '{}'", other)),
+    }
+}
+
+fn load_type(json: &Json) -> Result<TypeExpr, String> {
+    let f = Fields::of(json, "type")?;
+    let types = |key| -> Result<Vec<TypeExpr>, String> { f.array(key, "types")?.iter().map(load_type).collect() };
+    match f.kind()? {
+        "Named" => Ok(TypeExpr::Named(f.string("name")?)),
+        "Tuple" => Ok(TypeExpr::Tuple(types("items")?)),
+        "Generic" => Ok(TypeExpr::Generic(f.string("name")?, types("args")?)),
+        "Optional" => Ok(TypeExpr::Optional(Box::new(load_type(f.field("inner")?)?))),
+        other => Err(format!("unknown type expression '{}'", other)),
+    }
+}
+
+fn load_expr(ast: &mut Ast, json: &Json) -> Result<ExprId, String> {
+    let f = Fields::of(json, "expression")?;
+    let kind = match f.kind()? {
+        "Identifier" => ExprKind::Identifier(f.string("name")?),
+        "StringLiteral" => ExprKind::StringLiteral(f.string("value")?),
+        "IntLiteral" => ExprKind::IntLiteral(f.int("value")?),
+        "BoolLiteral" => ExprKind::BoolLiteral(f.bool("value")?),
+        "NoneLiteral" => ExprKind::NoneLiteral,
+        "Ok" => ExprKind::Ok(f.expr(ast, "expr")?),
+        "Err" => ExprKind::Err(f.expr(ast, "expr")?),
+        "Some" => ExprKind::Some(f.expr(ast, "expr")?),
+        "Propagate" => ExprKind::Propagate(f.expr(ast, "expr")?),
+        "Tuple" => ExprKind::Tuple(f.exprs(ast, "items")?),
+        "TupleIndex" => ExprKind::TupleIndex {
+            tuple: f.expr(ast, "tuple")?,
+            index: f.index("index")?,
+            optional: f.bool("optional")?,
+        },
+        "Member" => ExprKind::Member {
+            object: f.expr(ast, "object")?,
+            name: f.string("name")?,
+            optional: f.bool("optional")?,
+        },
+        "Range" => ExprKind::Range {
+            start: f.expr(ast, "start")?,
+            end: f.expr(ast, "end")?,
+            inclusive: f.bool("inclusive")?,
+            step: f.opt_expr(ast, "step")?,
+        },
+        "Slice" => ExprKind::Slice { target: f.expr(ast, "target")?, range: f.expr(ast, "range")? },
+        "Block" => ExprKind::Block { statements: f.stmts(ast, "statements")?, value: f.opt_expr(ast, "value")? },
+        "If" => ExprKind::If {
+            condition: f.expr(ast, "condition")?,
+            then_branch: f.expr(ast, "then_branch")?,
+            else_branch: f.opt_expr(ast, "else_branch")?,
+        },
+        "Ternary" => ExprKind::Ternary {
+            condition: f.expr(ast, "condition")?,
+            then_expr: f.expr(ast, "then_expr")?,
+            else_expr: f.expr(ast, "else_expr")?,
+        },
+        "Call" => ExprKind::Call { callee: f.expr(ast, "callee")?, args: f.exprs(ast, "args")? },
+        "Super" => ExprKind::Super { method: f.string("method")? },
+        "Binary" => {
+            let op = f.string("op")?;
+            ExprKind::Binary {
+                left: f.expr(ast, "left")?,
+                op: Operator::ALL.into_iter().find(|o| o.as_str() == op)
+                    .ok_or_else(|| format!("unknown operator '{}' in Binary", op))?,
+                right: f.expr(ast, "right")?,
+            }
+        }
+        other => return Err(format!("unknown expression type '{}'", other)),
+    };
+    Ok(ast.add_expr(kind, f.span()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    #[test]
+    fn json_round_trip() {
+        let source = r#"
+            @deprecated("old") pub const (a, b) = (1, "two\n");
+            import m from "m.sst";
+            let int? n = none;
+            fn max<T: Ord>(T a, Pair<T, int>? b, (int, string) c) -> T? { let t = 1; a }
+            interface Shape { fn area(self) -> int; }
+            impl Shape for int { fn area(self) -> int { self * self } }
+            class Dog<T>(pub name, T tag) extends Animal(name) { pub fn speak() -> string { super.speak() + "!" } }
+            for i in 0..=10 step 2 { println(t?.0 ?? m.x[1..2]); }
+            try { throw err(none); } catch (e) { defer print(some(e)); } finally { }
+            let v = if (a instanceof B) { ok(1)? } else { c ? d : (e,) };
+        "#;
+        let ast = Parser::new(crate::lexer::lexer(source)).parse();
+        let text = program(&ast).to_json();
+        assert_eq!(program(&load(&Json::parse(&text).unwrap()).unwrap()).to_json(), text);
+        assert!(program(&ast).to_sexp().starts_with("((Attributed\n    :attributes ((:name \"deprecated\""));
+    }
+
+    #[test]
+    fn load_fills_in_optional_fields() {
+        let json = Json::parse(r#"[{"type": "Print", "newline": true, "expr": {"type": "NoneLiteral"}}]"#).unwrap();
+        let ast = load(&json).unwrap();
+        let print = &ast[ast.body[0]];
+        assert_eq!(print.span, Span::default());
+        assert!(matches!(&print.kind, StmtKind::Print { expr, .. } if ast[*expr].kind == ExprKind::NoneLiteral));
+
+        let json = Json::parse(r#"[{"type": "Print", "newline": true, "expr": {"type": "Identifier", "name": "x"}}]"#).unwrap();
+        let ast = load(&json).unwrap();
+        assert!(matches!(&ast[ast.body[0]].kind, StmtKind::Print { expr, .. } if ast[*expr].kind == ExprKind::Identifier("x".into())));
+    }
+
+    #[test]
+    fn nodes_without_a_span_take_their_parents() {
+        let json = Json::parse(r#"[{"type": "Print", "newline": true, "span": [3, 1, 3, 12],
+            "expr": {"type": "Ok", "expr": {"type": "Identifier", "name": "x", "span": [3, 10, 3, 11]}}}]"#).unwrap();
+        let ast = load(&json).unwrap();
+        let StmtKind::Print { expr, .. } = &ast[ast.body[0]].kind else { panic!("not a print") };
+        let ExprKind::Ok(inner) = &ast[*expr].kind else { panic!("not an ok") };
+        assert_eq!(ast[*expr].span, ast[ast.body[0]].span);
+        assert_eq!((ast[*inner].span.line, ast[*inner].span.column), (3, 10));
+    }
+
+    #[test]
+    fn load_reports_bad_nodes() {
+        let json = Json::parse(r#"[{"type": "Print", "newline": 1, "expr": {"type": "NoneLiteral"}}]"#).unwrap();
+        assert_eq!(load(&json).unwrap_err(), "Print.newline should be true or false, got number 1");
+        assert!(Json::parse("[1,]").unwrap_err().ends_with("at 1:4"));
+    }
+}
@@ -1,8 +1,22 @@
-use crate::lexer::{Operator, Token};
+use crate::ast::{Ast, ExprId, Span, StmtId};
+use crate::lexer::Operator;
+
+/// An expression and the source text it was parsed from
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+}
 
 /// Represents all possible expressions in Stupid Script
-#[derive(Debug, Clone)]
-pub enum Expr {
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind {
     Identifier(String),
 
     /// String literal: "hello world"
@@ -11,10 +25,112 @@ pub enum Expr {
     /// Number literal (integer only for now)
     IntLiteral(i64),
 
+    /// `true` / `false`
+    BoolLiteral(bool),
+
+    /// The `none` literal
+    NoneLiteral,
+
+    /// `ok(expr)` — successful Result
+    Ok(ExprId),
+
+    /// `err(expr)` — failed Result
+    Err(ExprId),
+
+    /// `some(expr)` — present Option (`none` is the empty one)
+    Some(ExprId),
+
+    /// Postfix `expr?`: unwraps `ok`/`some`, or returns `err`/`none` early
+    Propagate(ExprId),
+
+    /// Tuple expression: `(a, b)`
+    Tuple(Vec<ExprId>),
+
+    /// Tuple element access: `t.0`, or `t?.0` when `optional`
+    /// (`?.` yields `none` on `none` and looks through `some(v)`)
+    TupleIndex {
+        tuple: ExprId,
+        index: usize,
+        optional: bool,
+    },
+
+    /// Qualified access into a module namespace: `math.sqrt`, or `m?.sqrt` when `optional`
+    Member {
+        object: ExprId,
+        name: String,
+        optional: bool,
+    },
+
+    /// Call: `max(a, b)`; `Dog("Rex")` constructs, `d.speak()` / `super.speak()` call methods
+    Call {
+        callee: ExprId,
+        args: Vec<ExprId>,
+    },
+
+    /// Range: `a..b`, `a..=b`, optionally `a..b step s`
+    Range {
+        start: ExprId,
+        end: ExprId,
+        inclusive: bool,
+        step: Option<ExprId>,
+    },
+
+    /// Slice of a string or tuple by a range: `s[1..3]`
+    Slice {
+        target: ExprId,
+        range: ExprId,
+    },
+
+    /// Block used as a value: `{ stmt; stmt; tail }`.
+    /// The block evaluates to `value`, or `none` if there is no tail expression.
+    Block {
+        statements: Vec<StmtId>,
+        value: Option<ExprId>,
+    },
+
+    /// `if (cond) { ... } else { ... }` as an expression.
+    /// Branches are `Block`s; `else_branch` may also be another `If` (`else if`).
+    /// Without an `else`, a false condition gives `none`.
+    If {
+        condition: ExprId,
+        then_branch: ExprId,
+        else_branch: Option<ExprId>,
+    },
+
+    /// `cond ? a : b`
+    Ternary {
+        condition: ExprId,
+        then_expr: ExprId,
+        else_expr: ExprId,
+    },
+
+    /// `super.name` — the parent class's version of a method, on the current `self`
+    Super {
+        method: String,
+    },
+
     /// Binary operators such as `a + b`
     Binary {
-        left: Box<Expr>,
+        left: ExprId,
         op: Operator,
-        right: Box<Expr>,
+        right: ExprId,
     },
 }
+
+/// The variable a condition compares with `none`, as in `x != none` or
+/// `none == x`, and whether the condition holds when it is not `none`.
+/// The branch taken then sees the variable narrowed to what is inside it.
+pub fn none_check(ast: &Ast, condition: ExprId) -> Option<(&str, bool)> {
+    let ExprKind::Binary { left, op, right } = &ast[condition].kind else { return None };
+    let present = match op {
+        Operator::NotEqual => true,
+        Operator::Equal => false,
+        _ => return None,
+    };
+    match (&ast[*left].kind, &ast[*right].kind) {
+        (ExprKind::Identifier(name), ExprKind::NoneLiteral) | (ExprKind::NoneLiteral, ExprKind::Identifier(name)) => {
+            Some((name, present))
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,414 @@
+//! A minimal JSON document model for the token and AST dumps.
+//!
+//! The same tree renders as JSON or as an S-expression, and JSON text
+//! can be read back into it. Numbers are integers only, like the language.
+
+use std::fmt::Write;
+
+/// Output lines longer than this are broken up, one child per line.
+const WIDTH: usize = 80;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Str(String),
+    Array(Vec<Json>),
+    /// Keys keep their insertion order so dumps read predictably
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// An object whose first key is `"type": kind`.
+    pub fn node(kind: &str, fields: Vec<(&str, Json)>) -> Self {
+        let mut entries = vec![("type".to_string(), Json::Str(kind.to_string()))];
+        entries.extend(fields.into_iter().map(|(k, v)| (k.to_string(), v)));
+        Json::Object(entries)
+    }
+
+    pub fn str(s: &str) -> Self {
+        Json::Str(s.to_string())
+    }
+
+    /// `None` becomes `null`.
+    pub fn opt<T>(value: Option<T>, f: impl FnOnce(T) -> Json) -> Self {
+        value.map(f).unwrap_or(Json::Null)
+    }
+
+    /// The value under `key`, if this is an object that has it.
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// A short description for error messages: `object`, `string "x"`, ...
+    pub fn describe(&self) -> String {
+        match self {
+            Json::Null => "null".to_string(),
+            Json::Bool(b) => b.to_string(),
+            Json::Int(i) => format!("number {}", i),
+            Json::Str(s) => format!("string {:?}", s),
+            Json::Array(_) => "array".to_string(),
+            Json::Object(_) => "object".to_string(),
+        }
+    }
+
+    // --------------------------
+    // WRITING
+    // --------------------------
+
+    /// Indented JSON; short arrays and objects stay on one line.
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        self.write_json(&mut out, 0);
+        out
+    }
+
+    fn write_json(&self, out: &mut String, depth: usize) {
+        let compact = self.compact_json();
+        let (open, close, items): (char, char, Vec<String>) = match self {
+            Json::Array(items) if depth * 2 + compact.len() > WIDTH => {
+                ('[', ']', items.iter().map(|v| v.nested_json(depth + 1)).collect())
+            }
+            Json::Object(entries) if depth * 2 + compact.len() > WIDTH => {
+                let items = entries.iter()
+                    .map(|(k, v)| format!("{}: {}", quote(k), v.nested_json(depth + 1)))
+                    .collect();
+                ('{', '}', items)
+            }
+            _ => {
+                out.push_str(&compact);
+                return;
+            }
+        };
+
+        let indent = "  ".repeat(depth + 1);
+        out.push(open);
+        for (i, item) in items.iter().enumerate() {
+            let _ = write!(out, "\n{}{}{}", indent, item, if i + 1 < items.len() { "," } else { "" });
+        }
+        let _ = write!(out, "\n{}{}", "  ".repeat(depth), close);
+    }
+
+    fn nested_json(&self, depth: usize) -> String {
+        let mut out = String::new();
+        self.write_json(&mut out, depth);
+        out
+    }
+
+    fn compact_json(&self) -> String {
+        match self {
+            Json::Null => "null".to_string(),
+            Json::Bool(b) => b.to_string(),
+            Json::Int(i) => i.to_string(),
+            Json::Str(s) => quote(s),
+            Json::Array(items) => {
+                let items: Vec<String> = items.iter().map(Json::compact_json).collect();
+                format!("[{}]", items.join(", "))
+            }
+            Json::Object(entries) => {
+                let entries: Vec<String> = entries.iter()
+                    .map(|(k, v)| format!("{}: {}", quote(k), v.compact_json()))
+                    .collect();
+                format!("{{{}}}", entries.join(", "))
+            }
+        }
+    }
+
+    /// S-expression: `{"type": "Binary", "op": "+"}` is `(Binary :op "+")`,
+    /// other objects are `(:key value ...)`, arrays are lists and `null` is `nil`.
+    pub fn to_sexp(&self) -> String {
+        let mut out = String::new();
+        self.write_sexp(&mut out, 0);
+        out
+    }
+
+    fn write_sexp(&self, out: &mut String, depth: usize) {
+        let compact = self.compact_sexp();
+        if depth * 2 + compact.len() <= WIDTH {
+            out.push_str(&compact);
+            return;
+        }
+
+        let indent = "  ".repeat(depth + 1);
+        let (head, items) = match self {
+            Json::Array(items) => (String::new(), items.iter().map(|v| v.nested_sexp(depth + 1)).collect()),
+            Json::Object(entries) => {
+                let (head, rest) = split_type(entries);
+                let items: Vec<String> = rest.iter()
+                    .map(|(k, v)| format!(":{} {}", k, v.nested_sexp(depth + 1)))
+                    .collect();
+                (head.unwrap_or_default(), items)
+            }
+            _ => {
+                out.push_str(&compact);
+                return;
+            }
+        };
+
+        out.push('(');
+        out.push_str(&head);
+        for (i, item) in items.iter().enumerate() {
+            if i == 0 && head.is_empty() {
+                out.push_str(item);
+            } else {
+                let _ = write!(out, "\n{}{}", indent, item);
+            }
+        }
+        out.push(')');
+    }
+
+    fn nested_sexp(&self, depth: usize) -> String {
+        let mut out = String::new();
+        self.write_sexp(&mut out, depth);
+        out
+    }
+
+    fn compact_sexp(&self) -> String {
+        match self {
+            Json::Null => "nil".to_string(),
+            Json::Bool(b) => b.to_string(),
+            Json::Int(i) => i.to_string(),
+            Json::Str(s) => quote(s),
+            Json::Array(items) => {
+                let items: Vec<String> = items.iter().map(Json::compact_sexp).collect();
+                format!("({})", items.join(" "))
+            }
+            Json::Object(entries) => {
+                let (head, rest) = split_type(entries);
+                let mut parts: Vec<String> = head.into_iter().collect();
+                parts.extend(rest.iter().map(|(k, v)| format!(":{} {}", k, v.compact_sexp())));
+                format!("({})", parts.join(" "))
+            }
+        }
+    }
+
+    // --------------------------
+    // READING
+    // --------------------------
+
+    /// Parse JSON text. Errors name the line and column they were found at.
+    pub fn parse(text: &str) -> Result<Json, String> {
+        let mut reader = Reader { chars: text.chars().collect(), pos: 0 };
+        let value = reader.value()?;
+        reader.skip_whitespace();
+        if reader.pos < reader.chars.len() {
+            return Err(reader.error("trailing characters after JSON value"));
+        }
+        Ok(value)
+    }
+}
+
+/// Pull a leading `"type": "Name"` off an object's entries.
+fn split_type(entries: &[(String, Json)]) -> (Option<String>, &[(String, Json)]) {
+    match entries.first() {
+        Some((k, Json::Str(kind))) if k == "type" => (Some(kind.clone()), &entries[1..]),
+        _ => (None, entries),
+    }
+}
+
+/// A JSON string literal, which S-expressions share
+fn quote(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+struct Reader {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Reader {
+    fn error(&self, message: &str) -> String {
+        let before = &self.chars[..self.pos.min(self.chars.len())];
+        let line = before.iter().filter(|&&c| c == '\n').count() + 1;
+        let column = before.iter().rev().take_while(|&&c| c != '\n').count() + 1;
+        format!("{} at {}:{}", message, line, column)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.get(self.pos).is_some_and(|c| c.is_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.chars.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", c)))
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        match self.peek() {
+            Some('{') => self.object(),
+            Some('[') => self.array(),
+            Some('"') => self.string().map(Json::Str),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            Some(c) if c.is_ascii_alphabetic() => {
+                let start = self.pos;
+                while self.chars.get(self.pos).is_some_and(|c| c.is_ascii_alphabetic()) {
+                    self.pos += 1;
+                }
+                match self.chars[start..self.pos].iter().collect::<String>().as_str() {
+                    "null" => Ok(Json::Null),
+                    "true" => Ok(Json::Bool(true)),
+                    "false" => Ok(Json::Bool(false)),
+                    word => {
+                        self.pos = start;
+                        Err(self.error(&format!("unexpected '{}'", word)))
+                    }
+                }
+            }
+            Some(c) => Err(self.error(&format!("unexpected '{}'", c))),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, String> {
+        self.expect('{')?;
+        let mut entries = Vec::new();
+        if self.peek() == Some('}') {
+            self.pos += 1;
+            return Ok(Json::Object(entries));
+        }
+        loop {
+            if self.peek() != Some('"') {
+                return Err(self.error("expected string key"));
+            }
+            let key = self.string()?;
+            self.expect(':')?;
+            entries.push((key, self.value()?));
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some('}') => {
+                    self.pos += 1;
+                    return Ok(Json::Object(entries));
+                }
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, String> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        if self.peek() == Some(']') {
+            self.pos += 1;
+            return Ok(Json::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some(']') => {
+                    self.pos += 1;
+                    return Ok(Json::Array(items));
+                }
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Json, String> {
+        let start = self.pos;
+        if self.chars[self.pos] == '-' {
+            self.pos += 1;
+        }
+        while self.chars.get(self.pos).is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        if matches!(self.chars.get(self.pos), Some('.' | 'e' | 'E')) {
+            return Err(self.error("only integer numbers are supported"));
+        }
+        let text: String = self.chars[start..self.pos].iter().collect();
+        text.parse().map(Json::Int).map_err(|_| {
+            self.pos = start;
+            self.error(&format!("invalid number '{}'", text))
+        })
+    }
+
+    /// A string literal; the opening quote is at `pos`.
+    fn string(&mut self) -> Result<String, String> {
+        self.pos += 1;
+        let mut out = String::new();
+        loop {
+            let c = match self.chars.get(self.pos) {
+                Some(&c) => c,
+                None => return Err(self.error("unterminated string")),
+            };
+            self.pos += 1;
+            match c {
+                '"' => return Ok(out),
+                '\\' => {
+                    let escaped = self.chars.get(self.pos).copied();
+                    self.pos += 1;
+                    out.push(match escaped {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => self.unicode_escape()?,
+                        _ => return Err(self.error("invalid escape in string")),
+                    });
+                }
+                c => out.push(c),
+            }
+        }
+    }
+
+    /// The `XXXX` of `\uXXXX`, combining a surrogate pair if one follows.
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        let high = self.hex4()?;
+        let code = if (0xD800..0xDC00).contains(&high)
+            && self.chars.get(self.pos) == Some(&'\\')
+            && self.chars.get(self.pos + 1) == Some(&'u')
+        {
+            self.pos += 2;
+            let low = self.hex4()?;
+            0x10000 + ((high - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF)
+        } else {
+            high
+        };
+        char::from_u32(code).ok_or_else(|| self.error("invalid unicode escape"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let digits: String = self.chars.iter().skip(self.pos).take(4).collect();
+        let code = u32::from_str_radix(&digits, 16)
+            .ok()
+            .filter(|_| digits.len() == 4)
+            .ok_or_else(|| self.error("expected four hex digits after \\u"))?;
+        self.pos += 4;
+        Ok(code)
+    }
+}
@@ -1,5 +1,19 @@
 pub mod expressions;
 pub mod statements;
+pub mod patterns;
+pub mod types;
+pub mod attributes;
+pub mod span;
+pub mod arena;
+pub mod visit;
+pub mod printer;
+pub mod json;
+pub mod dump;
 
 pub use expressions::*;
 pub use statements::*;
+pub use patterns::*;
+pub use types::*;
+pub use attributes::Attribute;
+pub use span::Span;
+pub use arena::{Ast, ExprId, StmtId};
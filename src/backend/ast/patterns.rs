@@ -0,0 +1,9 @@
+/// Binding patterns used on the left-hand side of `let`/`const`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    /// Plain name: `let x = ...`
+    Identifier(String),
+
+    /// Destructuring: `let (q, r) = ...`
+    Tuple(Vec<Pattern>),
+}
@@ -0,0 +1,687 @@
+//! Turns an AST back into source text.
+//!
+//! Parentheses are only added where the parser's precedence would
+//! otherwise group things differently, so `parse(print(ast))` gives back
+//! the same tree (spans aside). Macros are already expanded in a parsed
+//! tree, and the names they bind were renamed to fresh identifiers
+//! (`tmp` to `tmp1`), so the printed program is the expansion.
+
+use crate::ast::{
+    Ast, Attribute, ClassDecl, ExprId, ExprKind, FunctionDecl, MethodSig, Param, Pattern, StmtId, StmtKind, TypeExpr,
+    TypeParam,
+};
+use crate::lexer::Operator;
+
+const INDENT: &str = "    ";
+
+/// Binding strength of each expression level, loosest first,
+/// mirroring the `parse_*` chain in the parser.
+const TERNARY: u8 = 0;
+const COMPARISON: u8 = 1;
+const RANGE: u8 = 2;
+const COALESCE: u8 = 3;
+const TERM: u8 = 4;
+const FACTOR: u8 = 5;
+const POSTFIX: u8 = 6;
+const PRIMARY: u8 = 7;
+
+/// Source text for a whole program, one statement per line.
+pub fn print(ast: &Ast) -> String {
+    let mut printer = Printer { ast, out: String::new(), depth: 0 };
+    for &stmt in &ast.body {
+        printer.stmt(stmt);
+    }
+    printer.out
+}
+
+struct Printer<'a> {
+    ast: &'a Ast,
+    out: String,
+    depth: usize,
+}
+
+impl Printer<'_> {
+    fn line_start(&mut self) {
+        for _ in 0..self.depth {
+            self.out.push_str(INDENT);
+        }
+    }
+
+    // --------------------------
+    // STATEMENTS
+    // --------------------------
+
+    fn stmt(&mut self, stmt: StmtId) {
+        self.line_start();
+        self.stmt_inline(stmt);
+        self.out.push('\n');
+    }
+
+    /// A statement without the leading indent or trailing newline
+    fn stmt_inline(&mut self, stmt: StmtId) {
+        match &self.ast[stmt].kind {
+            StmtKind::Attributed { attributes, stmt } => {
+                for attr in attributes {
+                    self.attribute(attr);
+                    self.out.push(' ');
+                }
+                self.stmt_inline(*stmt);
+            }
+            StmtKind::Public { stmt } => {
+                self.out.push_str("pub ");
+                self.stmt_inline(*stmt);
+            }
+            StmtKind::VarDeclaration { constant, ty, pattern, value } => {
+                self.out.push_str(if *constant { "const " } else { "let " });
+                if let Some(ty) = ty {
+                    self.out.push_str(&format!("{} ", ty));
+                }
+                self.pattern(pattern);
+                self.out.push_str(" = ");
+                self.expr(*value, TERNARY);
+                self.out.push(';');
+            }
+            StmtKind::Function(decl) => self.function(decl),
+            StmtKind::Interface(decl) => {
+                self.out.push_str(&format!("interface {} {{\n", decl.name));
+                self.depth += 1;
+                for method in &decl.methods {
+                    self.line_start();
+                    self.signature(method);
+                    self.out.push('\n');
+                }
+                self.depth -= 1;
+                self.line_start();
+                self.out.push('}');
+            }
+            StmtKind::Impl(decl) => {
+                self.out.push_str(&format!("impl {} for {} {{\n", decl.interface, decl.target));
+                self.depth += 1;
+                for method in &decl.methods {
+                    self.line_start();
+                    self.function(method);
+                    self.out.push('\n');
+                }
+                self.depth -= 1;
+                self.line_start();
+                self.out.push('}');
+            }
+            StmtKind::Class(decl) => self.class(decl),
+            StmtKind::TypeDecl { name, target, nominal } => {
+                let keyword = if *nominal { "newtype" } else { "type" };
+                self.out.push_str(&format!("{} {} = {};", keyword, name, target));
+            }
+            StmtKind::Import { path, alias } => {
+                self.out.push_str("import ");
+                if let Some(alias) = alias {
+                    self.out.push_str(alias);
+                    self.out.push_str(" from ");
+                }
+                self.string(path);
+                self.out.push(';');
+            }
+            StmtKind::Expression { expr } => {
+                // `if` or `{` at the start of a statement is parsed as a statement on its own
+                if let ExprKind::If { .. } | ExprKind::Block { .. } = self.ast[*expr].kind {
+                    self.expr(*expr, TERNARY);
+                } else {
+                    self.statement_expr(*expr);
+                    self.out.push(';');
+                }
+            }
+            StmtKind::Print { newline, expr } => {
+                self.out.push_str(if *newline { "println(" } else { "print(" });
+                self.expr(*expr, TERNARY);
+                self.out.push_str(");");
+            }
+            StmtKind::Throw { expr } => {
+                self.out.push_str("throw ");
+                self.expr(*expr, TERNARY);
+                self.out.push(';');
+            }
+            StmtKind::Defer { body } => {
+                self.out.push_str("defer ");
+                self.block(body, None);
+            }
+            StmtKind::For { pattern, iterable, body } => {
+                self.out.push_str("for ");
+                self.pattern(pattern);
+                self.out.push_str(" in ");
+                // a trailing `?` would read the body's `{` as the start of `a ? b : c`
+                if ends_with_propagate(self.ast, *iterable) {
+                    self.parenthesized(*iterable);
+                } else {
+                    self.expr(*iterable, TERNARY);
+                }
+                self.out.push(' ');
+                self.block(body, None);
+            }
+            StmtKind::Try { body, catch, finally } => {
+                self.out.push_str("try ");
+                self.block(body, None);
+                if let Some(clause) = catch {
+                    self.out.push_str(" catch ");
+                    if let Some(name) = &clause.name {
+                        self.out.push_str(&format!("({}) ", name));
+                    }
+                    self.block(&clause.body, None);
+                }
+                if let Some(finally) = finally {
+                    self.out.push_str(" finally ");
+                    self.block(finally, None);
+                }
+            }
+        }
+    }
+
+    /// An expression in statement or block-tail position, where a leading
+    /// `if` or `{` would otherwise end the statement early.
+    fn statement_expr(&mut self, expr: ExprId) {
+        let whole = matches!(self.ast[expr].kind, ExprKind::If { .. } | ExprKind::Block { .. });
+        if starts_with_if_or_block(self.ast, expr) && !whole {
+            self.parenthesized(expr);
+        } else {
+            self.expr(expr, TERNARY);
+        }
+    }
+
+    fn attribute(&mut self, attr: &Attribute) {
+        self.out.push('@');
+        self.out.push_str(&attr.name);
+        if !attr.args.is_empty() {
+            self.out.push('(');
+            self.exprs(&attr.args);
+            self.out.push(')');
+        }
+    }
+
+    /// `fn name<T>(T a) -> R { ... }`
+    fn function(&mut self, decl: &FunctionDecl) {
+        self.out.push_str("fn ");
+        self.out.push_str(&decl.name);
+        self.type_params(&decl.type_params);
+        self.params(&decl.params, &[]);
+        self.returns(&decl.returns);
+        self.out.push(' ');
+        self.block(&decl.body, decl.value);
+    }
+
+    /// `fn area(self) -> int;`
+    fn signature(&mut self, sig: &MethodSig) {
+        self.out.push_str("fn ");
+        self.out.push_str(&sig.name);
+        self.params(&sig.params, &[]);
+        self.returns(&sig.returns);
+        self.out.push(';');
+    }
+
+    fn type_params(&mut self, params: &[TypeParam]) {
+        if params.is_empty() {
+            return;
+        }
+        let params: Vec<String> = params.iter()
+            .map(|p| match &p.bound {
+                Some(bound) => format!("{}: {}", p.name, bound),
+                None => p.name.clone(),
+            })
+            .collect();
+        self.out.push_str(&format!("<{}>", params.join(", ")));
+    }
+
+    /// `(pub T a, b)`, with `pub` before the names listed in `public`
+    fn params(&mut self, params: &[Param], public: &[String]) {
+        let params: Vec<String> = params.iter()
+            .map(|p| {
+                let mut param = if public.contains(&p.name) { String::from("pub ") } else { String::new() };
+                if let Some(ty) = &p.ty {
+                    param.push_str(&format!("{} ", ty));
+                }
+                param.push_str(&p.name);
+                param
+            })
+            .collect();
+        self.out.push_str(&format!("({})", params.join(", ")));
+    }
+
+    fn returns(&mut self, returns: &Option<TypeExpr>) {
+        if let Some(ty) = returns {
+            self.out.push_str(&format!(" -> {}", ty));
+        }
+    }
+
+    fn class(&mut self, decl: &ClassDecl) {
+        self.out.push_str("class ");
+        self.out.push_str(&decl.name);
+        self.type_params(&decl.type_params);
+        if !decl.params.is_empty() {
+            self.params(&decl.params, &decl.public_fields);
+        }
+        if let Some(parent) = &decl.parent {
+            self.out.push_str(" extends ");
+            self.out.push_str(&parent.name);
+            if !parent.args.is_empty() {
+                self.out.push('(');
+                self.exprs(&parent.args);
+                self.out.push(')');
+            }
+        }
+        self.out.push_str(" {\n");
+        self.depth += 1;
+        for method in &decl.methods {
+            self.line_start();
+            if method.public {
+                self.out.push_str("pub ");
+            }
+            self.out.push_str("fn ");
+            self.out.push_str(&method.name);
+            self.type_params(&method.type_params);
+            self.params(&method.params, &[]);
+            self.returns(&method.returns);
+            self.out.push(' ');
+            self.expr(method.body, PRIMARY);
+            self.out.push('\n');
+        }
+        self.depth -= 1;
+        self.line_start();
+        self.out.push('}');
+    }
+
+    fn pattern(&mut self, pattern: &Pattern) {
+        match pattern {
+            Pattern::Identifier(name) => self.out.push_str(name),
+            Pattern::Tuple(items) => {
+                self.out.push('(');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        self.out.push_str(", ");
+                    }
+                    self.pattern(item);
+                }
+                if items.len() == 1 {
+                    self.out.push(',');
+                }
+                self.out.push(')');
+            }
+        }
+    }
+
+    /// `{ stmt* tail? }` over several lines
+    fn block(&mut self, statements: &[StmtId], value: Option<ExprId>) {
+        if statements.is_empty() && value.is_none() {
+            self.out.push_str("{}");
+            return;
+        }
+
+        self.out.push_str("{\n");
+        self.depth += 1;
+        for &stmt in statements {
+            self.stmt(stmt);
+        }
+        if let Some(value) = value {
+            self.line_start();
+            self.statement_expr(value);
+            self.out.push('\n');
+        }
+        self.depth -= 1;
+        self.line_start();
+        self.out.push('}');
+    }
+
+    // --------------------------
+    // EXPRESSIONS
+    // --------------------------
+
+    /// Print `expr`, in parentheses if it binds looser than `min`.
+    fn expr(&mut self, expr: ExprId, min: u8) {
+        if precedence(self.ast, expr) < min {
+            self.parenthesized(expr);
+            return;
+        }
+
+        match &self.ast[expr].kind {
+            ExprKind::Identifier(name) => self.out.push_str(name),
+            ExprKind::StringLiteral(s) => self.string(s),
+            ExprKind::IntLiteral(i) => self.out.push_str(&i.to_string()),
+            ExprKind::BoolLiteral(b) => self.out.push_str(&b.to_string()),
+            ExprKind::NoneLiteral => self.out.push_str("none"),
+            ExprKind::Ok(inner) => self.wrapped("ok", *inner),
+            ExprKind::Err(inner) => self.wrapped("err", *inner),
+            ExprKind::Some(inner) => self.wrapped("some", *inner),
+            ExprKind::Propagate(inner) => {
+                self.postfix_operand(*inner);
+                self.out.push('?');
+            }
+            ExprKind::Tuple(items) => {
+                self.out.push('(');
+                self.exprs(items);
+                if items.len() == 1 {
+                    self.out.push(',');
+                }
+                self.out.push(')');
+            }
+            ExprKind::TupleIndex { tuple, index, optional } => {
+                self.postfix_operand(*tuple);
+                self.out.push_str(if *optional { "?." } else { "." });
+                self.out.push_str(&index.to_string());
+            }
+            ExprKind::Member { object, name, optional } => {
+                self.postfix_operand(*object);
+                self.out.push_str(if *optional { "?." } else { "." });
+                self.out.push_str(name);
+            }
+            ExprKind::Range { start, end, inclusive, step } => {
+                // `a?..b` would lex as `a ?. .b`
+                if ends_with_propagate(self.ast, *start) {
+                    self.parenthesized(*start);
+                } else {
+                    self.expr(*start, COALESCE);
+                }
+                self.out.push_str(if *inclusive { "..=" } else { ".." });
+                self.expr(*end, COALESCE);
+                if let Some(step) = step {
+                    self.out.push_str(" step ");
+                    self.expr(*step, COALESCE);
+                }
+            }
+            ExprKind::Slice { target, range } => {
+                self.postfix_operand(*target);
+                self.out.push('[');
+                self.expr(*range, TERNARY);
+                self.out.push(']');
+            }
+            ExprKind::Block { statements, value } => self.block(statements, *value),
+            ExprKind::If { condition, then_branch, else_branch } => {
+                self.out.push_str("if (");
+                self.expr(*condition, TERNARY);
+                self.out.push_str(") ");
+                self.expr(*then_branch, PRIMARY);
+                if let Some(else_branch) = else_branch {
+                    self.out.push_str(" else ");
+                    self.expr(*else_branch, PRIMARY);
+                }
+            }
+            ExprKind::Ternary { condition, then_expr, else_expr } => {
+                self.expr(*condition, COMPARISON);
+                self.out.push_str(" ? ");
+                self.expr(*then_expr, TERNARY);
+                self.out.push_str(" : ");
+                self.expr(*else_expr, TERNARY);
+            }
+            ExprKind::Call { callee, args } => {
+                self.postfix_operand(*callee);
+                self.out.push('(');
+                self.exprs(args);
+                self.out.push(')');
+            }
+            ExprKind::Super { method } => {
+                self.out.push_str("super.");
+                self.out.push_str(method);
+            }
+            ExprKind::Binary { left, op, right } => {
+                let level = binary_precedence(op);
+                // left-associative: only the right side needs a tighter operand
+                self.expr(*left, level);
+                self.out.push_str(&format!(" {} ", op.as_str()));
+                self.expr(*right, level + 1);
+            }
+        }
+    }
+
+    /// The operand of `x?`, `x.y`, `x(...)` or `x[...]`. A `?` right before
+    /// another postfix would lex as `?.` / `??` or start a conditional.
+    fn postfix_operand(&mut self, expr: ExprId) {
+        if let ExprKind::Propagate(_) = self.ast[expr].kind {
+            self.parenthesized(expr);
+        } else {
+            self.expr(expr, POSTFIX);
+        }
+    }
+
+    fn parenthesized(&mut self, expr: ExprId) {
+        self.out.push('(');
+        // `(x)` is grouping; only a one-element tuple needs the comma
+        self.expr(expr, TERNARY);
+        self.out.push(')');
+    }
+
+    fn wrapped(&mut self, name: &str, inner: ExprId) {
+        self.out.push_str(name);
+        self.out.push('(');
+        self.expr(inner, TERNARY);
+        self.out.push(')');
+    }
+
+    fn exprs(&mut self, exprs: &[ExprId]) {
+        for (i, &expr) in exprs.iter().enumerate() {
+            if i > 0 {
+                self.out.push_str(", ");
+            }
+            self.expr(expr, TERNARY);
+        }
+    }
+
+    /// A string literal, escaped the way the lexer reads it back
+    fn string(&mut self, s: &str) {
+        self.out.push('"');
+        for c in s.chars() {
+            match c {
+                '"' => self.out.push_str("\\\""),
+                '\\' => self.out.push_str("\\\\"),
+                '\n' => self.out.push_str("\\n"),
+                '\t' => self.out.push_str("\\t"),
+                c => self.out.push(c),
+            }
+        }
+        self.out.push('"');
+    }
+}
+
+fn precedence(ast: &Ast, expr: ExprId) -> u8 {
+    match &ast[expr].kind {
+        ExprKind::Ternary { .. } => TERNARY,
+        ExprKind::Range { .. } => RANGE,
+        ExprKind::Binary { op, .. } => binary_precedence(op),
+        ExprKind::Propagate(_)
+        | ExprKind::TupleIndex { .. }
+        | ExprKind::Member { .. }
+        | ExprKind::Slice { .. }
+        | ExprKind::Call { .. } => POSTFIX,
+        _ => PRIMARY,
+    }
+}
+
+fn binary_precedence(op: &Operator) -> u8 {
+    match op {
+        Operator::Coalesce => COALESCE,
+        Operator::Plus | Operator::Minus => TERM,
+        Operator::Multiply | Operator::Division => FACTOR,
+        _ => COMPARISON,
+    }
+}
+
+/// Whether printing `expr` begins with the `if` keyword or a `{`.
+fn starts_with_if_or_block(ast: &Ast, expr: ExprId) -> bool {
+    match &ast[expr].kind {
+        ExprKind::If { .. } | ExprKind::Block { .. } => true,
+        ExprKind::Binary { left: first, .. }
+        | ExprKind::Ternary { condition: first, .. }
+        | ExprKind::Range { start: first, .. }
+        | ExprKind::Propagate(first)
+        | ExprKind::TupleIndex { tuple: first, .. }
+        | ExprKind::Member { object: first, .. }
+        | ExprKind::Slice { target: first, .. }
+        | ExprKind::Call { callee: first, .. } => starts_with_if_or_block(ast, *first),
+        _ => false,
+    }
+}
+
+/// Whether printing `expr` ends with a postfix `?`.
+fn ends_with_propagate(ast: &Ast, expr: ExprId) -> bool {
+    match &ast[expr].kind {
+        ExprKind::Propagate(_) => true,
+        ExprKind::Binary { right: last, .. } | ExprKind::Ternary { else_expr: last, .. } => ends_with_propagate(ast, *last),
+        ExprKind::Range { end, step, .. } => ends_with_propagate(ast, step.unwrap_or(*end)),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::visit::{fold_expr_children, fold_stmt_children, Folder};
+    use crate::ast::Span;
+    use crate::lexer::lexer as lex;
+    use crate::parser::Parser;
+
+    /// Resets every span so trees parsed from different text compare equal.
+    /// Folding also drops orphaned nodes and numbers the rest in walk order.
+    struct EraseSpans;
+
+    impl Folder for EraseSpans {
+        fn fold_stmt(&mut self, from: &Ast, to: &mut Ast, id: StmtId) -> StmtId {
+            let id = fold_stmt_children(self, from, to, id);
+            to[id].span = Span::default();
+            id
+        }
+
+        fn fold_expr(&mut self, from: &Ast, to: &mut Ast, id: ExprId) -> ExprId {
+            let id = fold_expr_children(self, from, to, id);
+            to[id].span = Span::default();
+            id
+        }
+
+        fn fold_attribute(&mut self, from: &Ast, to: &mut Ast, attr: &Attribute) -> Attribute {
+            Attribute { span: Span::default(), args: self.fold_exprs(from, to, &attr.args), ..attr.clone() }
+        }
+    }
+
+    fn parse(source: &str) -> Ast {
+        EraseSpans.fold_ast(&Parser::new(lex(source)).parse())
+    }
+
+    /// `parse(print(ast)) == ast`, and printing is stable.
+    fn round_trip(source: &str) {
+        let ast = parse(source);
+        let printed = print(&ast);
+        assert_eq!(parse(&printed), ast, "printed as:\n{}", printed);
+        assert_eq!(print(&parse(&printed)), printed);
+    }
+
+    #[test]
+    fn declarations() {
+        round_trip(r#"
+            let x = 1;
+            let int y = 2;
+            const (a, (b, c)) = (1, (2, 3));
+            const (int, (string, bool)?) (d, e) = (1, some(("a", true)));
+            let Pair<int, string>? p = none;
+            let one = (1,);
+            let unit = ();
+            type UserId = int;
+            newtype Meters = int;
+            import "util.sst";
+            import math from "lib/math.sst";
+            pub const answer = 42;
+            @deprecated("use y") @inline let old = 1;
+            @test fn passes() {}
+        "#);
+    }
+
+    #[test]
+    fn precedence_and_grouping() {
+        round_trip("let a = (1 + 2) * 3 - 4 / (5 - 6);");
+        round_trip("let b = 1 - (2 - 3) + (4 + 5);");
+        round_trip("let c = (a ?? b) + c ?? d;");
+        round_trip("let d = a == (b == c);");
+        round_trip("let e = (a ? b : c) ? d : e ? f : g;");
+        round_trip("let f = (0..10 step 2) in (1..=3);");
+        round_trip("let g = (a + b).0 + t?.1.2 + m?.name;");
+    }
+
+    #[test]
+    fn postfix_question_marks() {
+        round_trip("let a = r? + (r?)? + (f?)(1) + (o?).x;");
+        round_trip("let b = r? ? 1 : 2;");
+        round_trip("let c = (r?)..n;");
+        round_trip("for i in (0..n?) { println(i); }");
+    }
+
+    #[test]
+    fn statements_and_blocks() {
+        round_trip(r#"
+            println("tab\there \"quoted\" \\ done\n");
+            print(1);
+            throw "boom";
+            defer println("bye");
+            defer { println(1); println(2); }
+            for (k, v) in pairs { println(k); }
+            try { throw "x"; } catch (e) { println(e.message); } finally { println("f"); }
+            try { risky(); } catch { }
+            let v = { let t = 2; t * 3 };
+            let w = if (a < b) { a } else if (a == b) { 0 } else { b };
+            if (ready) { println(1); }
+            let z = { if (c) { 1 } else { 2 } };
+            let y = { (if (c) { 1 } else { 2 }) + 1 };
+            (if (c) { 1 } else { 2 }) + 1;
+            ({ 1 }) + 2;
+            { println(3); }
+        "#);
+    }
+
+    #[test]
+    fn functions_interfaces_and_impls() {
+        round_trip(r#"
+            fn noop() {}
+            fn max<T>(T a, T b) -> T { a > b ? a : b }
+            fn describe<S: Shape>(S shape, int? scale) -> string {
+                let a = shape.area();
+                "area " + a
+            }
+            pub fn pair(a, Pair<int, string> p) -> (int, string) { (a, p.1) }
+            interface Shape {
+                fn area(self) -> int;
+                fn scale(self, int k);
+            }
+            impl Shape for int {
+                fn area(self) -> int { self * self }
+                fn scale(self, int k) { self * k }
+            }
+        "#);
+    }
+
+    #[test]
+    fn classes() {
+        round_trip(r#"
+            class Animal(pub name) {
+                pub fn describe() { "animal " + self.name }
+                fn secret() { 1 }
+            }
+            class Dog(name, breed) extends Animal(name) {
+                fn describe() { super.describe() + " " + self.breed }
+            }
+            class Empty {}
+            class Stack<T>(pub T top, Stack<T>? rest) {
+                pub fn push(T item) -> Stack<T> { Stack(item, some(self)) }
+                fn pick<U: Shape>(U a, (int, string) b) { a }
+            }
+            println(Dog("Rex", "lab") instanceof Animal);
+            some(ok(err(none))).speak(true, false);
+        "#);
+    }
+
+    #[test]
+    fn expanded_macros() {
+        round_trip(r#"
+            macro swap!($a, $b) {
+                let tmp = $a;
+                let $a = $b;
+                let $b = tmp;
+            }
+            let tmp1 = 0;
+            let x = 1;
+            let y = 2;
+            swap!(x, y);
+            swap!(x, y);
+        "#);
+    }
+}
@@ -0,0 +1,57 @@
+use std::fmt;
+
+use crate::lexer::Token;
+
+/// A range of source text, from `line:column` up to (not including)
+/// `end_line:end_column`. Columns count characters from 1.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+    /// Line of the macro call this text was expanded from, if any.
+    /// The positions above then point into the macro definition.
+    pub expanded_at: Option<usize>,
+}
+
+impl Span {
+    /// The text covered by a single token.
+    pub fn of(token: &Token) -> Self {
+        Self {
+            line: token.line,
+            column: token.column,
+            end_line: token.end_line,
+            end_column: token.end_column,
+            expanded_at: token.expanded_at,
+        }
+    }
+
+    /// From the start of `self` to the end of `end`. If only one of them
+    /// came from a macro definition, the text between them is not one
+    /// range; the part in the definition is kept.
+    pub fn to(self, end: Span) -> Self {
+        match (self.expanded_at, end.expanded_at) {
+            (x, y) if x == y => Self { end_line: end.end_line, end_column: end.end_column, ..self },
+            (Some(_), _) => self,
+            (None, _) => end,
+        }
+    }
+}
+
+/// `3:5-12`, or `3:5-4:2` when the range spans lines, followed by
+/// `, in macro expanded at line 9` for text from a macro definition
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)?;
+        if self.end_line == self.line {
+            write!(f, "-{}", self.end_column)?;
+        } else {
+            write!(f, "-{}:{}", self.end_line, self.end_column)?;
+        }
+        match self.expanded_at {
+            Some(call) => write!(f, ", in macro expanded at line {}", call),
+            None => Ok(()),
+        }
+    }
+}
@@ -1,19 +1,201 @@
-use crate::ast::Expr;
-use crate::lexer::Keyword;
+use crate::ast::{Attribute, ExprId, Pattern, Span, StmtId, TypeExpr};
+
+/// A statement and the source text it was parsed from
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Span,
+}
+
+impl Stmt {
+    pub fn new(kind: StmtKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+}
 
 /// Top-level statement nodes
-#[derive(Debug)]
-pub enum Stmt {
+#[derive(Debug, Clone, PartialEq)]
+pub enum StmtKind {
+    /// @deprecated("use y") let x = 1;
+    /// Attributes are metadata for passes; running `stmt` ignores them.
+    Attributed {
+        attributes: Vec<Attribute>,
+        stmt: StmtId,
+    },
+
+    /// pub const answer = 42;
+    /// Exports the declared names from the module; only valid at top level.
+    Public {
+        stmt: StmtId,
+    },
+
     /// let x = 10;
+    /// let int? y = none;
+    /// let (q, r) = (7, 2);
     VarDeclaration {
         constant: bool,
+        /// The declared type, if annotated
+        ty: Option<TypeExpr>,
+        pattern: Pattern,
+        value: ExprId,
+    },
+
+    /// fn max<T>(T a, T b) -> T { ... }
+    Function(FunctionDecl),
+
+    /// interface Shape { fn area(self) -> int; }
+    Interface(InterfaceDecl),
+
+    /// impl Shape for int { fn area(self) -> int { self * self } }
+    Impl(ImplDecl),
+
+    /// class Dog(name, breed) extends Animal(name) { fn speak() { ... } }
+    Class(ClassDecl),
+
+    /// type UserId = int;      (transparent alias)
+    /// newtype Meters = int;   (distinct type wrapping an int)
+    TypeDecl {
         name: String,
-        value: Expr,
+        target: String,
+        nominal: bool,
+    },
+
+    /// import "util.sst";
+    /// import math from "lib/math.sst";
+    Import {
+        path: String,
+        alias: Option<String>,
     },
 
     /// print(expr);
     Print {
         newline: bool, // true = println
-        expr: Expr,
+        expr: ExprId,
+    },
+
+    /// throw expr;
+    Throw {
+        expr: ExprId,
+    },
+
+    /// defer println("done");
+    /// defer { ... }
+    /// Runs when the enclosing block exits, most recent first.
+    Defer {
+        body: Vec<StmtId>,
+    },
+
+    /// for i in 0..10 { ... }
+    /// for (a, b) in pairs { ... }
+    For {
+        pattern: Pattern,
+        iterable: ExprId,
+        body: Vec<StmtId>,
+    },
+
+    /// try { ... } catch (e) { ... } finally { ... }
+    /// At least one of `catch` and `finally` is present.
+    Try {
+        body: Vec<StmtId>,
+        catch: Option<CatchClause>,
+        finally: Option<Vec<StmtId>>,
+    },
+
+    /// An expression evaluated for its effect: `log(x);`, or an `if`
+    Expression {
+        expr: ExprId,
     },
 }
+
+/// A free function. The body runs with only globals and the parameters in
+/// scope; its trailing expression, if any, is the result.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionDecl {
+    pub name: String,
+    /// `<T, U>`, in declaration order
+    pub type_params: Vec<TypeParam>,
+    pub params: Vec<Param>,
+    /// `-> T`, when given
+    pub returns: Option<TypeExpr>,
+    pub body: Vec<StmtId>,
+    pub value: Option<ExprId>,
+}
+
+/// A type parameter in `<T, U: Shape>`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeParam {
+    pub name: String,
+    /// Interface an argument for this parameter must implement
+    pub bound: Option<String>,
+}
+
+/// The methods a type must provide to implement an interface.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InterfaceDecl {
+    pub name: String,
+    pub methods: Vec<MethodSig>,
+}
+
+/// A method signature without a body: `fn area(self) -> int;`
+#[derive(Debug, Clone, PartialEq)]
+pub struct MethodSig {
+    pub name: String,
+    /// Starts with `self`
+    pub params: Vec<Param>,
+    pub returns: Option<TypeExpr>,
+}
+
+/// `impl Shape for int { ... }`: methods whose first parameter is `self`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImplDecl {
+    pub interface: String,
+    /// Name of the implementing type
+    pub target: String,
+    pub methods: Vec<FunctionDecl>,
+}
+
+/// A parameter: `T a`, or just `a` when it has no annotation
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    pub name: String,
+    pub ty: Option<TypeExpr>,
+}
+
+/// A class with a primary constructor: its parameters become the instance's fields.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassDecl {
+    pub name: String,
+    /// `class Stack<T>(T top)`: names its fields and methods can use as types
+    pub type_params: Vec<TypeParam>,
+    pub params: Vec<Param>,
+    /// Fields declared `pub`, readable from other modules
+    pub public_fields: Vec<String>,
+    pub parent: Option<ParentClass>,
+    pub methods: Vec<MethodDecl>,
+}
+
+/// `extends Animal(name)` — arguments are evaluated with the constructor parameters in scope
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParentClass {
+    pub name: String,
+    pub args: Vec<ExprId>,
+}
+
+/// fn describe(prefix) -> string { ... } — `self` is bound inside `body`, a block expression
+#[derive(Debug, Clone, PartialEq)]
+pub struct MethodDecl {
+    pub name: String,
+    pub public: bool,
+    /// `fn pick<T>(T a, T b)`: type names only this method can use
+    pub type_params: Vec<TypeParam>,
+    pub params: Vec<Param>,
+    pub returns: Option<TypeExpr>,
+    pub body: ExprId,
+}
+
+/// catch (e) { ... } — `name` is bound to the error value inside `body`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CatchClause {
+    pub name: Option<String>,
+    pub body: Vec<StmtId>,
+}
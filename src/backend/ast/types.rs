@@ -0,0 +1,38 @@
+use std::fmt;
+
+/// A type annotation, written before the name it applies to: `T a`,
+/// `Pair<int, string> p`, `int? n`, or after `->` for a return type
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeExpr {
+    /// `int`, `string` or a type parameter such as `T`
+    Named(String),
+
+    /// `(int, string)`
+    Tuple(Vec<TypeExpr>),
+
+    /// `Pair<int, string>`: a generic type with its type arguments given
+    Generic(String, Vec<TypeExpr>),
+
+    /// `int?`: the type or `none`
+    Optional(Box<TypeExpr>),
+}
+
+impl fmt::Display for TypeExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeExpr::Named(name) => write!(f, "{}", name),
+            TypeExpr::Generic(name, args) => {
+                let args: Vec<String> = args.iter().map(TypeExpr::to_string).collect();
+                write!(f, "{}<{}>", name, args.join(", "))
+            }
+            TypeExpr::Optional(inner) => write!(f, "{}?", inner),
+            TypeExpr::Tuple(items) => {
+                let items: Vec<String> = items.iter().map(TypeExpr::to_string).collect();
+                match items.len() {
+                    1 => write!(f, "({},)", items[0]),
+                    _ => write!(f, "({})", items.join(", ")),
+                }
+            }
+        }
+    }
+}
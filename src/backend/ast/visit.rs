@@ -0,0 +1,471 @@
+//! Traversal of the AST.
+//!
+//! A pass implements [`Visitor`] (read-only), [`VisitorMut`] (in-place edits)
+//! or [`Folder`] (rebuild into a new [`Ast`]), overriding only the methods for
+//! the nodes it cares about. Nodes are passed by id, so a pass can key what it
+//! learns by node in a [`SideTable`](crate::ast::arena::SideTable). The `walk_*` /
+//! `fold_*_children` functions visit a node's children and are the only places
+//! that list every variant, so a new node is wired into every pass by updating them.
+
+use crate::ast::{
+    Ast, Attribute, CatchClause, ClassDecl, ExprId, ExprKind, FunctionDecl, ImplDecl, MethodDecl, ParentClass, Pattern,
+    StmtId, StmtKind,
+};
+
+/// Read-only traversal. Each method's default visits the node's children.
+pub trait Visitor: Sized {
+    fn visit_stmt(&mut self, ast: &Ast, id: StmtId) {
+        walk_stmt(self, ast, id);
+    }
+
+    fn visit_expr(&mut self, ast: &Ast, id: ExprId) {
+        walk_expr(self, ast, id);
+    }
+
+    fn visit_pattern(&mut self, _pattern: &Pattern) {}
+
+    fn visit_attribute(&mut self, ast: &Ast, attr: &Attribute) {
+        attr.args.iter().for_each(|&e| self.visit_expr(ast, e));
+    }
+
+    /// Visit a file's top-level statements.
+    fn visit_ast(&mut self, ast: &Ast) {
+        ast.body.iter().for_each(|&s| self.visit_stmt(ast, s));
+    }
+}
+
+/// In-place traversal, for passes that edit nodes without rebuilding them.
+/// Patterns and attributes are part of their statement: edit them through
+/// `ast[id]` in `visit_stmt_mut`.
+pub trait VisitorMut: Sized {
+    fn visit_stmt_mut(&mut self, ast: &mut Ast, id: StmtId) {
+        walk_stmt_mut(self, ast, id);
+    }
+
+    fn visit_expr_mut(&mut self, ast: &mut Ast, id: ExprId) {
+        walk_expr_mut(self, ast, id);
+    }
+
+    fn visit_ast_mut(&mut self, ast: &mut Ast) {
+        for s in ast.body.clone() {
+            self.visit_stmt_mut(ast, s);
+        }
+    }
+}
+
+/// Rebuilding traversal: copies nodes from `from` into `to`, returning the
+/// id of each node's replacement there.
+pub trait Folder: Sized {
+    fn fold_stmt(&mut self, from: &Ast, to: &mut Ast, id: StmtId) -> StmtId {
+        fold_stmt_children(self, from, to, id)
+    }
+
+    fn fold_expr(&mut self, from: &Ast, to: &mut Ast, id: ExprId) -> ExprId {
+        fold_expr_children(self, from, to, id)
+    }
+
+    fn fold_pattern(&mut self, pattern: Pattern) -> Pattern {
+        pattern
+    }
+
+    fn fold_attribute(&mut self, from: &Ast, to: &mut Ast, attr: &Attribute) -> Attribute {
+        Attribute { args: self.fold_exprs(from, to, &attr.args), ..attr.clone() }
+    }
+
+    fn fold_stmts(&mut self, from: &Ast, to: &mut Ast, stmts: &[StmtId]) -> Vec<StmtId> {
+        stmts.iter().map(|&s| self.fold_stmt(from, to, s)).collect()
+    }
+
+    fn fold_exprs(&mut self, from: &Ast, to: &mut Ast, exprs: &[ExprId]) -> Vec<ExprId> {
+        exprs.iter().map(|&e| self.fold_expr(from, to, e)).collect()
+    }
+
+    /// A new `Ast` holding the folded top-level statements and whatever they
+    /// reach; nodes nothing refers to are left behind.
+    fn fold_ast(&mut self, from: &Ast) -> Ast {
+        let mut to = Ast::default();
+        to.body = self.fold_stmts(from, &mut to, &from.body);
+        to
+    }
+}
+
+// --------------------------
+// Visitor
+// --------------------------
+
+pub fn walk_stmt<V: Visitor>(v: &mut V, ast: &Ast, id: StmtId) {
+    let visit_all = |v: &mut V, stmts: &[StmtId]| stmts.iter().for_each(|&s| v.visit_stmt(ast, s));
+
+    match &ast[id].kind {
+        StmtKind::Attributed { attributes, stmt } => {
+            attributes.iter().for_each(|a| v.visit_attribute(ast, a));
+            v.visit_stmt(ast, *stmt);
+        }
+        StmtKind::Public { stmt } => v.visit_stmt(ast, *stmt),
+        StmtKind::VarDeclaration { pattern, value, .. } => {
+            v.visit_pattern(pattern);
+            v.visit_expr(ast, *value);
+        }
+        StmtKind::Class(ClassDecl { parent, methods, .. }) => {
+            if let Some(ParentClass { args, .. }) = parent {
+                args.iter().for_each(|&e| v.visit_expr(ast, e));
+            }
+            methods.iter().for_each(|m| v.visit_expr(ast, m.body));
+        }
+        StmtKind::Function(decl) => walk_function(v, ast, decl),
+        StmtKind::Impl(decl) => decl.methods.iter().for_each(|m| walk_function(v, ast, m)),
+        StmtKind::Interface(_) | StmtKind::TypeDecl { .. } | StmtKind::Import { .. } => {}
+        StmtKind::Expression { expr } | StmtKind::Print { expr, .. } | StmtKind::Throw { expr } => {
+            v.visit_expr(ast, *expr)
+        }
+        StmtKind::Defer { body } => visit_all(v, body),
+        StmtKind::For { pattern, iterable, body } => {
+            v.visit_pattern(pattern);
+            v.visit_expr(ast, *iterable);
+            visit_all(v, body);
+        }
+        StmtKind::Try { body, catch, finally } => {
+            visit_all(v, body);
+            if let Some(clause) = catch {
+                visit_all(v, &clause.body);
+            }
+            if let Some(finally) = finally {
+                visit_all(v, finally);
+            }
+        }
+    }
+}
+
+fn walk_function<V: Visitor>(v: &mut V, ast: &Ast, decl: &FunctionDecl) {
+    decl.body.iter().for_each(|&s| v.visit_stmt(ast, s));
+    if let Some(value) = decl.value {
+        v.visit_expr(ast, value);
+    }
+}
+
+pub fn walk_expr<V: Visitor>(v: &mut V, ast: &Ast, id: ExprId) {
+    match &ast[id].kind {
+        ExprKind::Identifier(_)
+        | ExprKind::StringLiteral(_)
+        | ExprKind::IntLiteral(_)
+        | ExprKind::BoolLiteral(_)
+        | ExprKind::NoneLiteral
+        | ExprKind::Super { .. } => {}
+        ExprKind::Ok(inner) | ExprKind::Err(inner) | ExprKind::Some(inner) | ExprKind::Propagate(inner) => {
+            v.visit_expr(ast, *inner)
+        }
+        ExprKind::Tuple(items) => items.iter().for_each(|&e| v.visit_expr(ast, e)),
+        ExprKind::TupleIndex { tuple, .. } => v.visit_expr(ast, *tuple),
+        ExprKind::Member { object, .. } => v.visit_expr(ast, *object),
+        ExprKind::Range { start, end, step, .. } => {
+            v.visit_expr(ast, *start);
+            v.visit_expr(ast, *end);
+            if let Some(step) = step {
+                v.visit_expr(ast, *step);
+            }
+        }
+        ExprKind::Slice { target, range } => {
+            v.visit_expr(ast, *target);
+            v.visit_expr(ast, *range);
+        }
+        ExprKind::Block { statements, value } => {
+            statements.iter().for_each(|&s| v.visit_stmt(ast, s));
+            if let Some(value) = value {
+                v.visit_expr(ast, *value);
+            }
+        }
+        ExprKind::If { condition, then_branch, else_branch } => {
+            v.visit_expr(ast, *condition);
+            v.visit_expr(ast, *then_branch);
+            if let Some(else_branch) = else_branch {
+                v.visit_expr(ast, *else_branch);
+            }
+        }
+        ExprKind::Ternary { condition, then_expr, else_expr } => {
+            v.visit_expr(ast, *condition);
+            v.visit_expr(ast, *then_expr);
+            v.visit_expr(ast, *else_expr);
+        }
+        ExprKind::Call { callee, args } => {
+            v.visit_expr(ast, *callee);
+            args.iter().for_each(|&e| v.visit_expr(ast, e));
+        }
+        ExprKind::Binary { left, right, .. } => {
+            v.visit_expr(ast, *left);
+            v.visit_expr(ast, *right);
+        }
+    }
+}
+
+// --------------------------
+// VisitorMut
+// --------------------------
+
+/// A child of a node, collected before visiting so `ast` can be lent out mutably.
+enum Child {
+    Stmt(StmtId),
+    Expr(ExprId),
+}
+
+fn visit_children_mut<V: VisitorMut>(v: &mut V, ast: &mut Ast, children: Vec<Child>) {
+    for child in children {
+        match child {
+            Child::Stmt(s) => v.visit_stmt_mut(ast, s),
+            Child::Expr(e) => v.visit_expr_mut(ast, e),
+        }
+    }
+}
+
+pub fn walk_stmt_mut<V: VisitorMut>(v: &mut V, ast: &mut Ast, id: StmtId) {
+    let stmts = |stmts: &[StmtId]| stmts.iter().map(|&s| Child::Stmt(s)).collect::<Vec<_>>();
+    let exprs = |exprs: &[ExprId]| exprs.iter().map(|&e| Child::Expr(e)).collect::<Vec<_>>();
+
+    let children = match &ast[id].kind {
+        StmtKind::Attributed { attributes, stmt } => {
+            let mut children: Vec<Child> = attributes.iter().flat_map(|a| exprs(&a.args)).collect();
+            children.push(Child::Stmt(*stmt));
+            children
+        }
+        StmtKind::Public { stmt } => vec![Child::Stmt(*stmt)],
+        StmtKind::VarDeclaration { value, .. } => vec![Child::Expr(*value)],
+        StmtKind::Class(ClassDecl { parent, methods, .. }) => {
+            let mut children = parent.as_ref().map(|p| exprs(&p.args)).unwrap_or_default();
+            children.extend(methods.iter().map(|m| Child::Expr(m.body)));
+            children
+        }
+        StmtKind::Function(decl) => function_children(decl),
+        StmtKind::Impl(decl) => decl.methods.iter().flat_map(function_children).collect(),
+        StmtKind::Interface(_) | StmtKind::TypeDecl { .. } | StmtKind::Import { .. } => Vec::new(),
+        StmtKind::Expression { expr } | StmtKind::Print { expr, .. } | StmtKind::Throw { expr } => {
+            vec![Child::Expr(*expr)]
+        }
+        StmtKind::Defer { body } => stmts(body),
+        StmtKind::For { iterable, body, .. } => {
+            let mut children = vec![Child::Expr(*iterable)];
+            children.extend(stmts(body));
+            children
+        }
+        StmtKind::Try { body, catch, finally } => {
+            let mut children = stmts(body);
+            if let Some(clause) = catch {
+                children.extend(stmts(&clause.body));
+            }
+            if let Some(finally) = finally {
+                children.extend(stmts(finally));
+            }
+            children
+        }
+    };
+    visit_children_mut(v, ast, children);
+}
+
+fn function_children(decl: &FunctionDecl) -> Vec<Child> {
+    let mut children: Vec<Child> = decl.body.iter().map(|&s| Child::Stmt(s)).collect();
+    children.extend(decl.value.map(Child::Expr));
+    children
+}
+
+pub fn walk_expr_mut<V: VisitorMut>(v: &mut V, ast: &mut Ast, id: ExprId) {
+    let children = match &ast[id].kind {
+        ExprKind::Identifier(_)
+        | ExprKind::StringLiteral(_)
+        | ExprKind::IntLiteral(_)
+        | ExprKind::BoolLiteral(_)
+        | ExprKind::NoneLiteral
+        | ExprKind::Super { .. } => Vec::new(),
+        ExprKind::Ok(inner) | ExprKind::Err(inner) | ExprKind::Some(inner) | ExprKind::Propagate(inner) => {
+            vec![Child::Expr(*inner)]
+        }
+        ExprKind::Tuple(items) => items.iter().map(|&e| Child::Expr(e)).collect(),
+        ExprKind::TupleIndex { tuple, .. } => vec![Child::Expr(*tuple)],
+        ExprKind::Member { object, .. } => vec![Child::Expr(*object)],
+        ExprKind::Range { start, end, step, .. } => {
+            let mut children = vec![Child::Expr(*start), Child::Expr(*end)];
+            children.extend(step.map(Child::Expr));
+            children
+        }
+        ExprKind::Slice { target, range } => vec![Child::Expr(*target), Child::Expr(*range)],
+        ExprKind::Block { statements, value } => {
+            let mut children: Vec<Child> = statements.iter().map(|&s| Child::Stmt(s)).collect();
+            children.extend(value.map(Child::Expr));
+            children
+        }
+        ExprKind::If { condition, then_branch, else_branch } => {
+            let mut children = vec![Child::Expr(*condition), Child::Expr(*then_branch)];
+            children.extend(else_branch.map(Child::Expr));
+            children
+        }
+        ExprKind::Ternary { condition, then_expr, else_expr } => {
+            vec![Child::Expr(*condition), Child::Expr(*then_expr), Child::Expr(*else_expr)]
+        }
+        ExprKind::Call { callee, args } => {
+            let mut children = vec![Child::Expr(*callee)];
+            children.extend(args.iter().map(|&e| Child::Expr(e)));
+            children
+        }
+        ExprKind::Binary { left, right, .. } => vec![Child::Expr(*left), Child::Expr(*right)],
+    };
+    visit_children_mut(v, ast, children);
+}
+
+// --------------------------
+// Folder
+// --------------------------
+
+pub fn fold_stmt_children<F: Folder>(f: &mut F, from: &Ast, to: &mut Ast, id: StmtId) -> StmtId {
+    let stmt = &from[id];
+    let kind = match &stmt.kind {
+        StmtKind::Attributed { attributes, stmt } => StmtKind::Attributed {
+            attributes: attributes.iter().map(|a| f.fold_attribute(from, to, a)).collect(),
+            stmt: f.fold_stmt(from, to, *stmt),
+        },
+        StmtKind::Public { stmt } => StmtKind::Public { stmt: f.fold_stmt(from, to, *stmt) },
+        StmtKind::VarDeclaration { constant, ty, pattern, value } => StmtKind::VarDeclaration {
+            constant: *constant,
+            ty: ty.clone(),
+            pattern: f.fold_pattern(pattern.clone()),
+            value: f.fold_expr(from, to, *value),
+        },
+        StmtKind::Class(decl) => StmtKind::Class(ClassDecl {
+            parent: decl.parent.as_ref().map(|p| ParentClass { args: f.fold_exprs(from, to, &p.args), ..p.clone() }),
+            methods: decl.methods.iter()
+                .map(|m| MethodDecl { body: f.fold_expr(from, to, m.body), ..m.clone() })
+                .collect(),
+            ..decl.clone()
+        }),
+        StmtKind::Function(decl) => StmtKind::Function(fold_function(f, from, to, decl)),
+        StmtKind::Impl(decl) => StmtKind::Impl(ImplDecl {
+            methods: decl.methods.iter().map(|m| fold_function(f, from, to, m)).collect(),
+            ..decl.clone()
+        }),
+        kind @ (StmtKind::Interface(_) | StmtKind::TypeDecl { .. } | StmtKind::Import { .. }) => kind.clone(),
+        StmtKind::Expression { expr } => StmtKind::Expression { expr: f.fold_expr(from, to, *expr) },
+        StmtKind::Print { newline, expr } => StmtKind::Print { newline: *newline, expr: f.fold_expr(from, to, *expr) },
+        StmtKind::Throw { expr } => StmtKind::Throw { expr: f.fold_expr(from, to, *expr) },
+        StmtKind::Defer { body } => StmtKind::Defer { body: f.fold_stmts(from, to, body) },
+        StmtKind::For { pattern, iterable, body } => StmtKind::For {
+            pattern: f.fold_pattern(pattern.clone()),
+            iterable: f.fold_expr(from, to, *iterable),
+            body: f.fold_stmts(from, to, body),
+        },
+        StmtKind::Try { body, catch, finally } => StmtKind::Try {
+            body: f.fold_stmts(from, to, body),
+            catch: catch.as_ref().map(|c| CatchClause { body: f.fold_stmts(from, to, &c.body), ..c.clone() }),
+            finally: finally.as_ref().map(|s| f.fold_stmts(from, to, s)),
+        },
+    };
+    to.add_stmt(kind, stmt.span)
+}
+
+fn fold_function<F: Folder>(f: &mut F, from: &Ast, to: &mut Ast, decl: &FunctionDecl) -> FunctionDecl {
+    FunctionDecl {
+        body: f.fold_stmts(from, to, &decl.body),
+        value: decl.value.map(|v| f.fold_expr(from, to, v)),
+        ..decl.clone()
+    }
+}
+
+pub fn fold_expr_children<F: Folder>(f: &mut F, from: &Ast, to: &mut Ast, id: ExprId) -> ExprId {
+    let expr = &from[id];
+    let fold = |f: &mut F, to: &mut Ast, e: &ExprId| f.fold_expr(from, to, *e);
+
+    let kind = match &expr.kind {
+        kind @ (ExprKind::Identifier(_)
+        | ExprKind::StringLiteral(_)
+        | ExprKind::IntLiteral(_)
+        | ExprKind::BoolLiteral(_)
+        | ExprKind::NoneLiteral
+        | ExprKind::Super { .. }) => kind.clone(),
+        ExprKind::Ok(inner) => ExprKind::Ok(fold(f, to, inner)),
+        ExprKind::Err(inner) => ExprKind::Err(fold(f, to, inner)),
+        ExprKind::Some(inner) => ExprKind::Some(fold(f, to, inner)),
+        ExprKind::Propagate(inner) => ExprKind::Propagate(fold(f, to, inner)),
+        ExprKind::Tuple(items) => ExprKind::Tuple(items.iter().map(|e| fold(f, to, e)).collect()),
+        ExprKind::TupleIndex { tuple, index, optional } => {
+            ExprKind::TupleIndex { tuple: fold(f, to, tuple), index: *index, optional: *optional }
+        }
+        ExprKind::Member { object, name, optional } => {
+            ExprKind::Member { object: fold(f, to, object), name: name.clone(), optional: *optional }
+        }
+        ExprKind::Range { start, end, inclusive, step } => ExprKind::Range {
+            start: fold(f, to, start),
+            end: fold(f, to, end),
+            inclusive: *inclusive,
+            step: step.as_ref().map(|e| fold(f, to, e)),
+        },
+        ExprKind::Slice { target, range } => {
+            ExprKind::Slice { target: fold(f, to, target), range: fold(f, to, range) }
+        }
+        ExprKind::Block { statements, value } => ExprKind::Block {
+            statements: f.fold_stmts(from, to, statements),
+            value: value.map(|v| f.fold_expr(from, to, v)),
+        },
+        ExprKind::If { condition, then_branch, else_branch } => ExprKind::If {
+            condition: fold(f, to, condition),
+            then_branch: fold(f, to, then_branch),
+            else_branch: else_branch.as_ref().map(|e| fold(f, to, e)),
+        },
+        ExprKind::Ternary { condition, then_expr, else_expr } => ExprKind::Ternary {
+            condition: fold(f, to, condition),
+            then_expr: fold(f, to, then_expr),
+            else_expr: fold(f, to, else_expr),
+        },
+        ExprKind::Call { callee, args } => ExprKind::Call {
+            callee: fold(f, to, callee),
+            args: args.iter().map(|e| fold(f, to, e)).collect(),
+        },
+        ExprKind::Binary { left, op, right } => ExprKind::Binary {
+            left: fold(f, to, left),
+            op: op.clone(),
+            right: fold(f, to, right),
+        },
+    };
+    to.add_expr(kind, expr.span)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lexer as lex;
+    use crate::parser::Parser;
+
+    struct Names(Vec<String>);
+
+    impl Visitor for Names {
+        fn visit_expr(&mut self, ast: &Ast, id: ExprId) {
+            if let ExprKind::Identifier(name) = &ast[id].kind {
+                self.0.push(name.clone());
+            }
+            walk_expr(self, ast, id);
+        }
+    }
+
+    struct Rename;
+
+    impl VisitorMut for Rename {
+        fn visit_expr_mut(&mut self, ast: &mut Ast, id: ExprId) {
+            if let ExprKind::Identifier(name) = &mut ast[id].kind {
+                name.make_ascii_uppercase();
+            }
+            walk_expr_mut(self, ast, id);
+        }
+    }
+
+    #[test]
+    fn walks_reach_function_impl_and_method_bodies() {
+        let mut ast = Parser::new(lex("
+            fn f(a) { a }
+            impl Show for int { fn show(self) -> string { b; \"\" } }
+            class C(x) { fn m() { c } }
+        ")).parse();
+
+        let mut names = Names(Vec::new());
+        names.visit_ast(&ast);
+        assert_eq!(names.0, ["a", "b", "c"]);
+
+        Rename.visit_ast_mut(&mut ast);
+        let mut names = Names(Vec::new());
+        names.visit_ast(&ast);
+        assert_eq!(names.0, ["A", "B", "C"]);
+    }
+}
@@ -0,0 +1,520 @@
+//! Lowering from the surface AST to the core IR.
+//!
+//! Most syntax is rewritten in terms of a smaller set of constructs:
+//!
+//! ```text
+//! for p in e { body }   { let $it = %iter(e); while (%has_next($it)) { let p = %next($it); body } }
+//! s1; defer d; s2       s1; try { s2 } finally { d }
+//! a ?? b                { let $t = %look_through(a); if (%is_none($t)) b else $t }
+//! e?                    { let $t = e; if (%failed($t)) { return $t } else %unwrap($t) }
+//! a?.x                  { let $t = %look_through(a); if (%is_none($t)) none else $t.x }
+//! c ? a : b             if (c) a else b
+//! if (x != none) { x }  if (x != none) { %look_through(x) }
+//! println(e)            %println(e)
+//! @attr s / pub s       s / s; export <names declared by s>
+//! fn f<T: B>(T x) -> T   const f = fn f(x), remembering that x must implement B
+//! @inline fn f(x) { }   const f = inline fn f(x) { }, which `resolve` may copy into calls
+//! class C(x) { }        const C = class C(x) { }
+//! import m from "p"     const m = import "p"
+//! let int x = e         let x = e
+//! ```
+//!
+//! Type annotations are for the checker and are dropped here, except for
+//! the bounds above. Temporaries start with `$`, which no source
+//! identifier can.
+
+use std::path::Path;
+use std::rc::Rc;
+
+use crate::ast::attributes::{self, AttributeRegistry};
+use crate::ast::{self, Ast, ExprId, Param, Span, StmtId, TypeExpr, TypeParam};
+use crate::ir::{
+    Catch, ClassDecl, Expr, ExprKind, FunctionDecl, ImplDecl, InterfaceDecl, Literal, MethodDecl, Pattern, Prim, Stmt,
+    StmtKind, Var,
+};
+use crate::lexer::Operator;
+
+/// The core IR for a whole program.
+pub fn lower(ast: &Ast) -> Vec<Stmt> {
+    let attributes = AttributeRegistry::build(ast);
+    let mut lowerer = Lowerer { ast, attributes, temps: 0, narrowed: Vec::new() };
+    lowerer.stmts(&ast.body, None).0
+}
+
+struct Lowerer<'a> {
+    ast: &'a Ast,
+    attributes: AttributeRegistry,
+    /// Temporaries made so far, to keep their names unique
+    temps: usize,
+    /// Variables a surrounding `if` found not to be `none`, read through
+    /// any `some` around their value
+    narrowed: Vec<String>,
+}
+
+impl Lowerer<'_> {
+    fn temp(&mut self, hint: &str) -> String {
+        self.temps += 1;
+        format!("${}{}", hint, self.temps - 1)
+    }
+
+    // --------------------------
+    // STATEMENTS
+    // --------------------------
+
+    /// A statement list and its optional tail. A `defer` moves everything
+    /// after it, tail included, into the body of a `try` whose `finally`
+    /// is the deferred block.
+    fn stmts(&mut self, stmts: &[StmtId], tail: Option<ExprId>) -> (Vec<Stmt>, Option<Expr>) {
+        let narrowed = self.narrowed.clone();
+        let lowered = self.stmts_in_scope(stmts, tail);
+        self.narrowed = narrowed;
+        lowered
+    }
+
+    fn stmts_in_scope(&mut self, stmts: &[StmtId], tail: Option<ExprId>) -> (Vec<Stmt>, Option<Expr>) {
+        let mut out = Vec::new();
+        for (i, &id) in stmts.iter().enumerate() {
+            if let ast::StmtKind::Defer { body } = &self.ast[attributes::unwrapped(self.ast, id)].kind {
+                let span = self.ast[id].span;
+                let finally = vec![self.block_stmt(body, span)];
+                let (rest, value) = self.stmts_in_scope(&stmts[i + 1..], tail);
+                let guarded = Expr::new(ExprKind::Try {
+                    body: rest,
+                    value: value.map(Box::new),
+                    catch: None,
+                    finally: Some(finally),
+                }, span);
+                if tail.is_some() {
+                    return (out, Some(guarded));
+                }
+                out.push(Stmt::new(StmtKind::Expr(guarded), span));
+                return (out, None);
+            }
+            self.stmt(id, &mut out);
+        }
+        (out, tail.map(|tail| self.expr(tail)))
+    }
+
+    fn stmt(&mut self, id: StmtId, out: &mut Vec<Stmt>) {
+        let span = self.ast[id].span;
+        let kind = match &self.ast[id].kind {
+            ast::StmtKind::Attributed { stmt, .. } => return self.stmt(*stmt, out),
+            ast::StmtKind::Public { stmt } => {
+                self.stmt(*stmt, out);
+                StmtKind::Export(attributes::declared_names(self.ast, *stmt))
+            }
+            ast::StmtKind::VarDeclaration { constant, pattern, value, .. } => StmtKind::Let {
+                constant: *constant,
+                pattern: Pattern::new(pattern),
+                value: self.expr(*value),
+            },
+            ast::StmtKind::Function(decl) => {
+                let function = FunctionDecl {
+                    inline: self.attributes.find(id, "inline").is_some(),
+                    ..self.function(decl, span)
+                };
+                declare(&decl.name, ExprKind::Function(function), span)
+            }
+            ast::StmtKind::Interface(decl) => declare(&decl.name, ExprKind::Interface(InterfaceDecl {
+                name: decl.name.clone(),
+                methods: decl.methods.iter().map(|m| (m.name.clone(), m.params.len())).collect(),
+            }), span),
+            ast::StmtKind::Impl(decl) => StmtKind::Impl(ImplDecl {
+                interface: decl.interface.clone(),
+                target: decl.target.clone(),
+                methods: decl.methods.iter().map(|m| self.function(m, span)).collect(),
+            }),
+            ast::StmtKind::Class(decl) => declare(&decl.name, ExprKind::Class(self.class(decl)), span),
+            ast::StmtKind::TypeDecl { name, target, nominal } => declare(name, ExprKind::TypeDecl {
+                name: name.clone(),
+                target: Var::new(target.clone()),
+                nominal: *nominal,
+            }, span),
+            ast::StmtKind::Import { path, alias } => {
+                // `import "lib/math.sst"` is bound as `math`
+                let stem = || Path::new(path).file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+                let name = alias.clone().unwrap_or_else(stem);
+                declare(&name, ExprKind::Import { path: path.clone() }, span)
+            }
+            ast::StmtKind::Expression { expr } => StmtKind::Expr(self.expr(*expr)),
+            ast::StmtKind::Print { newline, expr } => {
+                let arg = self.expr(*expr);
+                StmtKind::Expr(prim(Prim::Print { newline: *newline }, vec![arg], span))
+            }
+            ast::StmtKind::Throw { expr } => StmtKind::Throw(self.expr(*expr)),
+            ast::StmtKind::Defer { .. } => unreachable!("`defer` is lowered with the statements after it"),
+            ast::StmtKind::For { pattern, iterable, body } => StmtKind::Expr(self.for_loop(pattern, *iterable, body, span)),
+            ast::StmtKind::Try { body, catch, finally } => {
+                let body = vec![self.block_stmt(body, span)];
+                let catch = catch.as_ref().map(|c| {
+                    let narrowed = self.narrowed.clone();
+                    self.unnarrow(c.name.as_slice());
+                    let body = self.stmts(&c.body, None).0;
+                    self.narrowed = narrowed;
+                    Catch { name: c.name.clone().map(Var::new), body }
+                });
+                let finally = finally.as_ref().map(|f| vec![self.block_stmt(f, span)]);
+                StmtKind::Expr(Expr::new(ExprKind::Try { body, value: None, catch, finally }, span))
+            }
+        };
+        out.push(Stmt::new(kind, span));
+        self.unnarrow(&attributes::declared_names(self.ast, id));
+    }
+
+    /// Stop narrowing `names`, declared again as new variables.
+    fn unnarrow(&mut self, names: &[String]) {
+        self.narrowed.retain(|n| !names.contains(n));
+    }
+
+    /// A block in statement position
+    fn block_stmt(&mut self, stmts: &[StmtId], span: Span) -> Stmt {
+        Stmt::new(StmtKind::Expr(self.block(stmts, None, span)), span)
+    }
+
+    fn for_loop(&mut self, pattern: &ast::Pattern, iterable: ExprId, body: &[StmtId], span: Span) -> Expr {
+        let cursor = self.temp("it");
+        let var = || Expr::new(ExprKind::Var(Var::new(cursor.clone())), span);
+
+        let iterable = self.expr(iterable);
+        let narrowed = self.narrowed.clone();
+        let mut names = Vec::new();
+        attributes::pattern_names(pattern, &mut names);
+        self.unnarrow(&names);
+        let mut each = vec![Stmt::new(StmtKind::Let {
+            constant: false,
+            pattern: Pattern::new(pattern),
+            value: prim(Prim::Next, vec![var()], span),
+        }, span)];
+        each.extend(self.stmts(body, None).0);
+        self.narrowed = narrowed;
+
+        let body = vec![
+            let_temp(&cursor, prim(Prim::Iter, vec![iterable], span)),
+            Stmt::new(StmtKind::While { condition: prim(Prim::HasNext, vec![var()], span), body: each }, span),
+        ];
+        Expr::new(ExprKind::Block { body, value: None }, span)
+    }
+
+    /// A function whose body and tail become one block. Bodies see only
+    /// globals, so nothing is narrowed in them.
+    fn function(&mut self, decl: &ast::FunctionDecl, span: Span) -> FunctionDecl {
+        let narrowed = std::mem::take(&mut self.narrowed);
+        let body = self.block(&decl.body, decl.value, span);
+        self.narrowed = narrowed;
+        FunctionDecl {
+            name: decl.name.clone(),
+            params: param_names(&decl.params),
+            bounds: param_bounds(&decl.type_params, &decl.params),
+            inline: false,
+            body: Rc::new(body),
+        }
+    }
+
+    fn class(&mut self, decl: &ast::ClassDecl) -> ClassDecl {
+        let narrowed = std::mem::take(&mut self.narrowed);
+        let class = ClassDecl {
+            name: decl.name.clone(),
+            params: param_names(&decl.params),
+            bounds: param_bounds(&decl.type_params, &decl.params),
+            public_fields: decl.public_fields.clone(),
+            parent: decl.parent.as_ref().map(|p| (Var::new(p.name.clone()), self.exprs(&p.args))),
+            methods: decl.methods.iter()
+                .map(|m| MethodDecl {
+                    name: m.name.clone(),
+                    public: m.public,
+                    params: param_names(&m.params),
+                    bounds: param_bounds(&m.type_params, &m.params),
+                    body: Rc::new(self.expr(m.body)),
+                })
+                .collect(),
+        };
+        self.narrowed = narrowed;
+        class
+    }
+
+    // --------------------------
+    // EXPRESSIONS
+    // --------------------------
+
+    fn expr(&mut self, id: ExprId) -> Expr {
+        let span = self.ast[id].span;
+        let kind = match &self.ast[id].kind {
+            ast::ExprKind::Identifier(name) if self.narrowed.contains(name) => {
+                let var = Expr::new(ExprKind::Var(Var::new(name.clone())), span);
+                return prim(Prim::LookThrough, vec![var], span);
+            }
+            ast::ExprKind::Identifier(name) => ExprKind::Var(Var::new(name.clone())),
+            ast::ExprKind::StringLiteral(s) => ExprKind::Literal(Literal::Str(s.clone())),
+            ast::ExprKind::IntLiteral(i) => ExprKind::Literal(Literal::Int(*i)),
+            ast::ExprKind::BoolLiteral(b) => ExprKind::Literal(Literal::Bool(*b)),
+            ast::ExprKind::NoneLiteral => ExprKind::Literal(Literal::None),
+            ast::ExprKind::Ok(inner) => return prim(Prim::Ok, vec![self.expr(*inner)], span),
+            ast::ExprKind::Err(inner) => return prim(Prim::Err, vec![self.expr(*inner)], span),
+            ast::ExprKind::Some(inner) => return prim(Prim::Some, vec![self.expr(*inner)], span),
+            ast::ExprKind::Propagate(inner) => return self.propagate(*inner, span),
+            ast::ExprKind::Tuple(items) => ExprKind::Tuple(self.exprs(items)),
+            ast::ExprKind::TupleIndex { tuple, index, optional } => {
+                let index = *index;
+                return self.access(*tuple, *optional, span, |tuple| ExprKind::Index { tuple: Box::new(tuple), index });
+            }
+            ast::ExprKind::Member { object, name, optional } => {
+                let name = name.clone();
+                return self.access(*object, *optional, span, |object| ExprKind::Member { object: Box::new(object), name });
+            }
+            ast::ExprKind::Range { start, end, inclusive, step } => {
+                let mut args = vec![self.expr(*start), self.expr(*end)];
+                args.extend(step.map(|step| self.expr(step)));
+                return prim(Prim::Range { inclusive: *inclusive }, args, span);
+            }
+            ast::ExprKind::Slice { target, range } => {
+                let args = vec![self.expr(*target), self.expr(*range)];
+                return prim(Prim::Slice, args, span);
+            }
+            ast::ExprKind::Block { statements, value } => return self.block(statements, *value, span),
+            ast::ExprKind::If { condition, then_branch, else_branch } => ExprKind::If {
+                condition: Box::new(self.expr(*condition)),
+                then_branch: Box::new(self.branch(*condition, true, *then_branch)),
+                else_branch: else_branch.map(|e| Box::new(self.branch(*condition, false, e))),
+            },
+            ast::ExprKind::Ternary { condition, then_expr, else_expr } => ExprKind::If {
+                condition: Box::new(self.expr(*condition)),
+                then_branch: Box::new(self.branch(*condition, true, *then_expr)),
+                else_branch: Some(Box::new(self.branch(*condition, false, *else_expr))),
+            },
+            ast::ExprKind::Call { callee, args } => {
+                // `a?.m(x)` skips the whole call, arguments included, when `a` is none
+                if let ast::ExprKind::Member { object, name, optional: true } = &self.ast[*callee].kind {
+                    let (callee_span, name) = (self.ast[*callee].span, name.clone());
+                    let args = self.exprs(args);
+                    return self.access(*object, true, span, |object| ExprKind::Call {
+                        callee: Box::new(Expr::new(ExprKind::Member { object: Box::new(object), name }, callee_span)),
+                        args,
+                    });
+                }
+                ExprKind::Call { callee: Box::new(self.expr(*callee)), args: self.exprs(args) }
+            }
+            ast::ExprKind::Super { method } => ExprKind::Super { method: method.clone(), this: Var::new("self") },
+            ast::ExprKind::Binary { left, op: Operator::Coalesce, right } => return self.coalesce(*left, *right, span),
+            ast::ExprKind::Binary { left, op, right } => {
+                let args = vec![self.expr(*left), self.expr(*right)];
+                return prim(Prim::Binary(op.clone()), args, span);
+            }
+        };
+        Expr::new(kind, span)
+    }
+
+    /// The branch of an `if` or `?:` taken when `condition` is `taken`,
+    /// with the variable it found not to be `none` narrowed.
+    fn branch(&mut self, condition: ExprId, taken: bool, branch: ExprId) -> Expr {
+        match ast::none_check(self.ast, condition) {
+            Some((name, present)) if present == taken => {
+                self.narrowed.push(name.to_string());
+                let branch = self.expr(branch);
+                self.narrowed.pop();
+                branch
+            }
+            _ => self.expr(branch),
+        }
+    }
+
+    fn exprs(&mut self, exprs: &[ExprId]) -> Vec<Expr> {
+        exprs.iter().map(|&e| self.expr(e)).collect()
+    }
+
+    fn block(&mut self, stmts: &[StmtId], tail: Option<ExprId>, span: Span) -> Expr {
+        let (body, value) = self.stmts(stmts, tail);
+        Expr::new(ExprKind::Block { body, value: value.map(Box::new) }, span)
+    }
+
+    /// `target.x`, or `target?.x` when `optional`
+    fn access(&mut self, target: ExprId, optional: bool, span: Span, access: impl FnOnce(Expr) -> ExprKind) -> Expr {
+        let target = self.expr(target);
+        if !optional {
+            return Expr::new(access(target), span);
+        }
+        let tmp = self.temp("t");
+        let var = Expr::new(ExprKind::Var(Var::new(tmp.clone())), span);
+        let looked = prim(Prim::LookThrough, vec![target], span);
+        let branch = ExprKind::If {
+            condition: Box::new(prim(Prim::IsNone, vec![var.clone()], span)),
+            then_branch: Box::new(Expr::new(ExprKind::Literal(Literal::None), span)),
+            else_branch: Some(Box::new(Expr::new(access(var), span))),
+        };
+        with_temp(&tmp, looked, branch, span)
+    }
+
+    fn coalesce(&mut self, left: ExprId, right: ExprId, span: Span) -> Expr {
+        let left = prim(Prim::LookThrough, vec![self.expr(left)], span);
+        let right = self.expr(right);
+        let tmp = self.temp("t");
+        let var = Expr::new(ExprKind::Var(Var::new(tmp.clone())), span);
+        let branch = ExprKind::If {
+            condition: Box::new(prim(Prim::IsNone, vec![var.clone()], span)),
+            then_branch: Box::new(right),
+            else_branch: Some(Box::new(var)),
+        };
+        with_temp(&tmp, left, branch, span)
+    }
+
+    fn propagate(&mut self, inner: ExprId, span: Span) -> Expr {
+        let inner = self.expr(inner);
+        let tmp = self.temp("t");
+        let var = Expr::new(ExprKind::Var(Var::new(tmp.clone())), span);
+        let early = ExprKind::Block { body: vec![Stmt::new(StmtKind::Return(var.clone()), span)], value: None };
+        let branch = ExprKind::If {
+            condition: Box::new(prim(Prim::Failed, vec![var.clone()], span)),
+            then_branch: Box::new(Expr::new(early, span)),
+            else_branch: Some(Box::new(prim(Prim::Unwrap, vec![var], span))),
+        };
+        with_temp(&tmp, inner, branch, span)
+    }
+}
+
+fn param_names(params: &[Param]) -> Vec<String> {
+    params.iter().map(|p| p.name.clone()).collect()
+}
+
+/// Parameter index and bound for each parameter typed as a bounded type parameter.
+fn param_bounds(type_params: &[TypeParam], params: &[Param]) -> Vec<(usize, String)> {
+    params.iter().enumerate()
+        .filter_map(|(i, param)| match &param.ty {
+            Some(TypeExpr::Named(ty)) => type_params.iter()
+                .find(|tp| tp.name == *ty)
+                .and_then(|tp| tp.bound.clone())
+                .map(|bound| (i, bound)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn prim(op: Prim, args: Vec<Expr>, span: Span) -> Expr {
+    Expr::new(ExprKind::Prim { op, args }, span)
+}
+
+fn let_temp(name: &str, value: Expr) -> Stmt {
+    let span = value.span;
+    Stmt::new(StmtKind::Let { constant: false, pattern: Pattern::Identifier(Var::new(name)), value }, span)
+}
+
+/// `const name = value`
+fn declare(name: &str, value: ExprKind, span: Span) -> StmtKind {
+    StmtKind::Let { constant: true, pattern: Pattern::Identifier(Var::new(name)), value: Expr::new(value, span) }
+}
+
+/// `{ let name = value; then }`
+fn with_temp(name: &str, value: Expr, then: ExprKind, span: Span) -> Expr {
+    Expr::new(ExprKind::Block {
+        body: vec![let_temp(name, value)],
+        value: Some(Box::new(Expr::new(then, span))),
+    }, span)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir::printer::print;
+    use crate::lexer::lexer as lex;
+    use crate::parser::Parser;
+
+    fn lowered(source: &str) -> String {
+        print(&lower(&Parser::new(lex(source)).parse()))
+    }
+
+    #[test]
+    fn for_loop_becomes_cursor_loop() {
+        assert_eq!(lowered("for (k, v) in pairs { println(k); }"), "\
+{
+  let $it0 = %iter(pairs)
+  while (%has_next($it0)) {
+    let (k, v) = %next($it0)
+    %println(k)
+  }
+}
+");
+    }
+
+    #[test]
+    fn defer_wraps_the_rest_of_the_block() {
+        assert_eq!(lowered("let v = { defer println(1); let a = 2; a };"), "\
+let v = {
+  try {
+    let a = 2
+    a
+  } finally {
+    {
+      %println(1)
+    }
+  }
+}
+");
+    }
+
+    #[test]
+    fn defer_is_found_under_attributes() {
+        // the parser only puts attributes on declarations, but a loaded AST can wrap anything
+        let mut ast = Parser::new(lex("defer println(1); println(2);")).parse();
+        let defer = ast.body[0];
+        let span = ast[defer].span;
+        let attribute = attributes::Attribute { name: "x".to_string(), args: Vec::new(), span };
+        ast.body[0] = ast.add_stmt(ast::StmtKind::Attributed { attributes: vec![attribute], stmt: defer }, span);
+        assert_eq!(print(&lower(&ast)), "\
+try {
+  %println(2)
+} finally {
+  {
+    %println(1)
+  }
+}
+");
+    }
+
+    #[test]
+    fn operators_become_branches() {
+        assert_eq!(lowered("let x = a ?? b;"), "\
+let x = {
+  let $t0 = %look_through(a)
+  if (%is_none($t0)) b else $t0
+}
+");
+        assert_eq!(lowered("let y = o?.f(g());"), "\
+let y = {
+  let $t0 = %look_through(o)
+  if (%is_none($t0)) none else $t0.f(g())
+}
+");
+    }
+
+    #[test]
+    fn none_checks_narrow_the_branch_they_guard() {
+        assert_eq!(lowered("let z = x == none ? 0 : x + 1;"), "let z = if (x == none) 0 else (%look_through(x) + 1)\n");
+        assert_eq!(lowered("if (x != none) { let x = some(1); println(x); }"), "\
+if (x != none) {
+  let x = %some(1)
+  %println(x)
+}
+");
+    }
+
+    #[test]
+    fn functions_and_impls_keep_only_their_bounds() {
+        assert_eq!(lowered("fn f<T: Shape>(T a, int b) -> int { defer println(b); a.area() }"), "\
+const f = fn f(a: Shape, b) {
+  try {
+    a.area()
+  } finally {
+    {
+      %println(b)
+    }
+  }
+}
+");
+        assert_eq!(lowered("interface Shape { fn area(self) -> int; } impl Shape for int { fn area(self) -> int { self } }"), "\
+const Shape = interface Shape { area/1 }
+impl Shape for int {
+  fn area(self) {
+    self
+  }
+}
+");
+    }
+}
@@ -0,0 +1,8 @@
+pub mod nodes;
+pub mod lower;
+pub mod resolve;
+pub mod printer;
+
+pub use nodes::*;
+pub use lower::lower;
+pub use resolve::{resolve, Program};
@@ -0,0 +1,297 @@
+use std::rc::Rc;
+
+use crate::ast::{self, Span};
+use crate::lexer::Operator;
+
+/// A core statement and the source text it was lowered from
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Span,
+}
+
+impl Stmt {
+    pub fn new(kind: StmtKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StmtKind {
+    /// Bind the names in `pattern` in the innermost scope. This is the only
+    /// declaration: functions, interfaces, classes, types and imports are
+    /// values bound by a `const`.
+    Let {
+        constant: bool,
+        pattern: Pattern,
+        value: Expr,
+    },
+
+    /// Evaluate for effects and drop the value
+    Expr(Expr),
+
+    /// Run `body` in a fresh scope for as long as `condition` is true
+    While {
+        condition: Expr,
+        body: Vec<Stmt>,
+    },
+
+    Throw(Expr),
+
+    /// Leave the running method with this value (the early exit of `?`)
+    Return(Expr),
+
+    /// Add names to the running module's exports
+    Export(Vec<String>),
+
+    /// Add methods to a type; an impl binds no name
+    Impl(ImplDecl),
+}
+
+/// A core expression and the source text it was lowered from
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind {
+    Literal(Literal),
+
+    Var(Var),
+
+    Tuple(Vec<Expr>),
+
+    /// `t.0`
+    Index {
+        tuple: Box<Expr>,
+        index: usize,
+    },
+
+    /// `object.name`
+    Member {
+        object: Box<Expr>,
+        name: String,
+    },
+
+    /// A call. A `Member` or `Super` callee is a method call.
+    Call {
+        callee: Box<Expr>,
+        args: Vec<Expr>,
+    },
+
+    /// `super.method`, only meaningful as a callee; `this` is the method's `self`
+    Super {
+        method: String,
+        this: Var,
+    },
+
+    /// A built-in operation on already evaluated arguments
+    Prim {
+        op: Prim,
+        args: Vec<Expr>,
+    },
+
+    /// Statements in a fresh scope, then the value of `value` (`none` without one)
+    Block {
+        body: Vec<Stmt>,
+        value: Option<Box<Expr>>,
+    },
+
+    If {
+        condition: Box<Expr>,
+        then_branch: Box<Expr>,
+        else_branch: Option<Box<Expr>>,
+    },
+
+    /// Run `body` and `value` in the enclosing scope. `catch` handles raised
+    /// errors (not early returns); `finally` runs on every exit, and an error
+    /// there replaces the pending result.
+    Try {
+        body: Vec<Stmt>,
+        value: Option<Box<Expr>>,
+        catch: Option<Catch>,
+        finally: Option<Vec<Stmt>>,
+    },
+
+    /// A new function
+    Function(FunctionDecl),
+
+    Interface(InterfaceDecl),
+
+    /// A new class
+    Class(ClassDecl),
+
+    /// A `type` alias or `newtype`. A `target` that is not a built-in type
+    /// names a type declared earlier.
+    TypeDecl {
+        name: String,
+        target: Var,
+        nominal: bool,
+    },
+
+    /// The namespace of a module, loading it on first use
+    Import {
+        path: String,
+    },
+}
+
+/// A variable and, once `resolve` has run, where it lives.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Var {
+    pub name: String,
+    pub slot: Option<Slot>,
+}
+
+impl Var {
+    /// A reference to `name` that has not been resolved yet
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into(), slot: None }
+    }
+}
+
+/// A variable's place at runtime: `depth` scopes out from the innermost one,
+/// at `index` within that scope. A declaration is always at depth 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Slot {
+    pub depth: usize,
+    pub index: usize,
+}
+
+/// The names a `let` binds
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    Identifier(Var),
+    Tuple(Vec<Pattern>),
+}
+
+impl Pattern {
+    /// Mirror a surface pattern with unresolved variables
+    pub fn new(pattern: &ast::Pattern) -> Self {
+        match pattern {
+            ast::Pattern::Identifier(name) => Pattern::Identifier(Var::new(name.clone())),
+            ast::Pattern::Tuple(items) => Pattern::Tuple(items.iter().map(Pattern::new).collect()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    Int(i64),
+    Str(String),
+    Bool(bool),
+    None,
+}
+
+/// Built-in operations. Each evaluates all of its arguments first.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Prim {
+    /// A binary operator other than `??`, which lowers to a branch
+    Binary(Operator),
+    Ok,
+    Err,
+    Some,
+    /// Whether a Result/Option is `err`/`none`; anything else is a TypeError
+    Failed,
+    /// The value inside `ok`/`some`
+    Unwrap,
+    IsNone,
+    /// `some(v)` to `v`, anything else unchanged: the target of `?.`
+    LookThrough,
+    /// `start, end[, step]`
+    Range { inclusive: bool },
+    /// `target, range`
+    Slice,
+    /// A cursor over the items of a range, tuple or string, for `for`
+    Iter,
+    HasNext,
+    /// The cursor's next item, moving it along
+    Next,
+    Print { newline: bool },
+    /// `parseInt(text)`: `ok` of the integer `text` spells, else `err` of why not
+    ParseInt,
+    /// `readFile(path)`: `ok` of the file's text, else `err` of why it cannot be read
+    ReadFile,
+    /// `writeFile(path, text)`: `ok` of the bytes written, else `err` of why not
+    WriteFile,
+}
+
+impl Prim {
+    /// The built-in function called `name`, which a call to that name
+    /// means when nothing declares it, and how many arguments it takes.
+    pub fn builtin(name: &str) -> Option<(Prim, usize)> {
+        match name {
+            "parseInt" => Some((Prim::ParseInt, 1)),
+            "readFile" => Some((Prim::ReadFile, 1)),
+            "writeFile" => Some((Prim::WriteFile, 2)),
+            _ => None,
+        }
+    }
+}
+
+/// A function with its types erased. Only bounds are kept, since calls
+/// check them against the arguments.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionDecl {
+    pub name: String,
+    pub params: Vec<String>,
+    /// Parameter index and the interface its argument must implement
+    pub bounds: Vec<(usize, String)>,
+    /// Marked `@inline`: calls may be replaced with the body
+    pub inline: bool,
+    /// Runs in a scope of the parameters, under the module's globals.
+    /// Shared with the runtime functions built from this declaration.
+    pub body: Rc<Expr>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InterfaceDecl {
+    pub name: String,
+    /// Method name and parameter count, `self` included
+    pub methods: Vec<(String, usize)>,
+}
+
+/// `impl interface for target`; each method takes the receiver as its first parameter
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImplDecl {
+    pub interface: String,
+    pub target: String,
+    pub methods: Vec<FunctionDecl>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassDecl {
+    pub name: String,
+    pub params: Vec<String>,
+    pub bounds: Vec<(usize, String)>,
+    pub public_fields: Vec<String>,
+    /// Parent class and the arguments passed to its constructor, which see
+    /// the module's globals and the class's parameters
+    pub parent: Option<(Var, Vec<Expr>)>,
+    pub methods: Vec<MethodDecl>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MethodDecl {
+    pub name: String,
+    pub public: bool,
+    pub params: Vec<String>,
+    pub bounds: Vec<(usize, String)>,
+    /// Runs in a scope of `self` then the parameters, under the module's
+    /// globals. Shared with the runtime methods built from this declaration.
+    pub body: Rc<Expr>,
+}
+
+/// `catch (name) { body }`; the body runs in a fresh scope holding `name`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Catch {
+    pub name: Option<Var>,
+    pub body: Vec<Stmt>,
+}
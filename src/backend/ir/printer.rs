@@ -0,0 +1,292 @@
+//! Text dump of the core IR, for debugging lowering.
+//!
+//! It reads like the source language, with built-in operations written
+//! `%name(args)` and binary operators fully parenthesized. Resolved variables
+//! show their slot as `name@depth:index`. It is not meant to be parsed back.
+
+use crate::ir::{ClassDecl, Expr, ExprKind, FunctionDecl, Literal, Pattern, Prim, Stmt, StmtKind, Var};
+
+/// The IR for a whole program, one statement per line.
+pub fn print(program: &[Stmt]) -> String {
+    let mut printer = Printer::default();
+    for stmt in program {
+        printer.stmt(stmt);
+    }
+    printer.out
+}
+
+#[derive(Default)]
+struct Printer {
+    out: String,
+    depth: usize,
+}
+
+impl Printer {
+    fn line_start(&mut self) {
+        self.out.push_str(&"  ".repeat(self.depth));
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        self.line_start();
+        match &stmt.kind {
+            StmtKind::Let { constant, pattern, value } => {
+                self.out.push_str(if *constant { "const " } else { "let " });
+                self.pattern(pattern);
+                self.out.push_str(" = ");
+                self.expr(value);
+            }
+            StmtKind::Expr(expr) => self.expr(expr),
+            StmtKind::While { condition, body } => {
+                self.out.push_str("while ");
+                self.condition(condition);
+                self.out.push(' ');
+                self.body(body, None);
+            }
+            StmtKind::Throw(expr) => {
+                self.out.push_str("throw ");
+                self.expr(expr);
+            }
+            StmtKind::Return(expr) => {
+                self.out.push_str("return ");
+                self.expr(expr);
+            }
+            StmtKind::Export(names) => self.out.push_str(&format!("export {}", names.join(", "))),
+            StmtKind::Impl(decl) => {
+                self.out.push_str(&format!("impl {} for {} {{\n", decl.interface, decl.target));
+                self.depth += 1;
+                for method in &decl.methods {
+                    self.line_start();
+                    self.function(method);
+                    self.out.push('\n');
+                }
+                self.depth -= 1;
+                self.line_start();
+                self.out.push('}');
+            }
+        }
+        self.out.push('\n');
+    }
+
+    fn function(&mut self, decl: &FunctionDecl) {
+        let params = param_list(&decl.params, &decl.bounds, &[]);
+        let inline = if decl.inline { "inline " } else { "" };
+        self.out.push_str(&format!("{}fn {}({}) ", inline, decl.name, params));
+        self.expr(&decl.body);
+    }
+
+    fn class(&mut self, decl: &ClassDecl) {
+        let params = param_list(&decl.params, &decl.bounds, &decl.public_fields);
+        self.out.push_str(&format!("class {}({})", decl.name, params));
+        if let Some((parent, args)) = &decl.parent {
+            self.out.push_str(" extends ");
+            self.var(parent);
+            self.out.push('(');
+            self.exprs(args);
+            self.out.push(')');
+        }
+        self.out.push_str(" {\n");
+        self.depth += 1;
+        for method in &decl.methods {
+            self.line_start();
+            let public = if method.public { "pub " } else { "" };
+            let params = param_list(&method.params, &method.bounds, &[]);
+            self.out.push_str(&format!("{}fn {}({}) ", public, method.name, params));
+            self.expr(&method.body);
+            self.out.push('\n');
+        }
+        self.depth -= 1;
+        self.line_start();
+        self.out.push('}');
+    }
+
+    fn pattern(&mut self, pattern: &Pattern) {
+        match pattern {
+            Pattern::Identifier(var) => self.var(var),
+            Pattern::Tuple(items) => {
+                self.out.push('(');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        self.out.push_str(", ");
+                    }
+                    self.pattern(item);
+                }
+                if items.len() == 1 {
+                    self.out.push(',');
+                }
+                self.out.push(')');
+            }
+        }
+    }
+
+    /// `{ stmt* value? }` over several lines
+    fn body(&mut self, body: &[Stmt], value: Option<&Expr>) {
+        if body.is_empty() && value.is_none() {
+            self.out.push_str("{}");
+            return;
+        }
+        self.out.push_str("{\n");
+        self.depth += 1;
+        for stmt in body {
+            self.stmt(stmt);
+        }
+        if let Some(value) = value {
+            self.line_start();
+            self.expr(value);
+            self.out.push('\n');
+        }
+        self.depth -= 1;
+        self.line_start();
+        self.out.push('}');
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Literal(Literal::Int(i)) => self.out.push_str(&i.to_string()),
+            ExprKind::Literal(Literal::Str(s)) => self.out.push_str(&format!("{:?}", s)),
+            ExprKind::Literal(Literal::Bool(b)) => self.out.push_str(&b.to_string()),
+            ExprKind::Literal(Literal::None) => self.out.push_str("none"),
+            ExprKind::Var(var) => self.var(var),
+            ExprKind::Tuple(items) => {
+                self.out.push('(');
+                self.exprs(items);
+                if items.len() == 1 {
+                    self.out.push(',');
+                }
+                self.out.push(')');
+            }
+            ExprKind::Index { tuple, index } => {
+                self.expr(tuple);
+                self.out.push_str(&format!(".{}", index));
+            }
+            ExprKind::Member { object, name } => {
+                self.expr(object);
+                self.out.push_str(&format!(".{}", name));
+            }
+            ExprKind::Call { callee, args } => {
+                self.expr(callee);
+                self.out.push('(');
+                self.exprs(args);
+                self.out.push(')');
+            }
+            ExprKind::Super { method, .. } => self.out.push_str(&format!("super.{}", method)),
+            ExprKind::Prim { op: Prim::Binary(op), args } if args.len() == 2 => {
+                self.out.push('(');
+                self.expr(&args[0]);
+                self.out.push_str(&format!(" {} ", op.as_str()));
+                self.expr(&args[1]);
+                self.out.push(')');
+            }
+            ExprKind::Prim { op, args } => {
+                self.out.push_str(&format!("%{}(", prim_name(op)));
+                self.exprs(args);
+                self.out.push(')');
+            }
+            ExprKind::Block { body, value } => self.body(body, value.as_deref()),
+            ExprKind::If { condition, then_branch, else_branch } => {
+                self.out.push_str("if ");
+                self.condition(condition);
+                self.out.push(' ');
+                self.expr(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.out.push_str(" else ");
+                    self.expr(else_branch);
+                }
+            }
+            ExprKind::Try { body, value, catch, finally } => {
+                self.out.push_str("try ");
+                self.body(body, value.as_deref());
+                if let Some(catch) = catch {
+                    self.out.push_str(" catch ");
+                    if let Some(name) = &catch.name {
+                        self.out.push('(');
+                        self.var(name);
+                        self.out.push_str(") ");
+                    }
+                    self.body(&catch.body, None);
+                }
+                if let Some(finally) = finally {
+                    self.out.push_str(" finally ");
+                    self.body(finally, None);
+                }
+            }
+            ExprKind::Function(decl) => self.function(decl),
+            ExprKind::Interface(decl) => {
+                let methods: Vec<String> = decl.methods.iter()
+                    .map(|(name, arity)| format!("{}/{}", name, arity))
+                    .collect();
+                self.out.push_str(&format!("interface {} {{ {} }}", decl.name, methods.join(", ")));
+            }
+            ExprKind::Class(decl) => self.class(decl),
+            ExprKind::TypeDecl { target, nominal, .. } => {
+                self.out.push_str(if *nominal { "newtype " } else { "type " });
+                self.var(target);
+            }
+            ExprKind::Import { path } => self.out.push_str(&format!("import {:?}", path)),
+        }
+    }
+
+    fn var(&mut self, var: &Var) {
+        self.out.push_str(&var.name);
+        if let Some(slot) = var.slot {
+            self.out.push_str(&format!("@{}:{}", slot.depth, slot.index));
+        }
+    }
+
+    /// `(cond)`; binary operators already come parenthesized
+    fn condition(&mut self, expr: &Expr) {
+        if let ExprKind::Prim { op: Prim::Binary(_), .. } = expr.kind {
+            self.expr(expr);
+        } else {
+            self.out.push('(');
+            self.expr(expr);
+            self.out.push(')');
+        }
+    }
+
+    fn exprs(&mut self, exprs: &[Expr]) {
+        for (i, expr) in exprs.iter().enumerate() {
+            if i > 0 {
+                self.out.push_str(", ");
+            }
+            self.expr(expr);
+        }
+    }
+}
+
+/// `a: Bound, pub b`: each parameter with its bound, if any, and `pub` for public fields
+fn param_list(params: &[String], bounds: &[(usize, String)], public: &[String]) -> String {
+    params.iter().enumerate()
+        .map(|(i, p)| {
+            let public = if public.contains(p) { "pub " } else { "" };
+            match bounds.iter().find(|(index, _)| *index == i) {
+                Some((_, bound)) => format!("{}{}: {}", public, p, bound),
+                None => format!("{}{}", public, p),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn prim_name(op: &Prim) -> &'static str {
+    match op {
+        Prim::Binary(_) => "binary",
+        Prim::Ok => "ok",
+        Prim::Err => "err",
+        Prim::Some => "some",
+        Prim::Failed => "failed",
+        Prim::Unwrap => "unwrap",
+        Prim::IsNone => "is_none",
+        Prim::LookThrough => "look_through",
+        Prim::Range { inclusive: false } => "range",
+        Prim::Range { inclusive: true } => "range_inclusive",
+        Prim::Slice => "slice",
+        Prim::Iter => "iter",
+        Prim::HasNext => "has_next",
+        Prim::Next => "next",
+        Prim::Print { newline: false } => "print",
+        Prim::Print { newline: true } => "println",
+        Prim::ParseInt => "parse_int",
+        Prim::ReadFile => "read_file",
+        Prim::WriteFile => "write_file",
+    }
+}
@@ -0,0 +1,616 @@
+//! Static name resolution.
+//!
+//! Every variable is given the slot it occupies at runtime, so the
+//! interpreter indexes frames instead of looking names up, and a name that
+//! nothing declares is reported before the program starts.
+//!
+//! Scopes open exactly where the interpreter pushes a frame: a module's
+//! globals, each `Block`, each `while` body and each `catch`. A function or
+//! impl method body runs in a frame of its parameters, a class method body
+//! in a frame of `self` then its parameters, and a class's `extends`
+//! arguments in a frame of the class's parameters; all of them see the
+//! module's globals past that and nothing else. They run later than they
+//! are declared, so they are resolved once the rest of the module has been
+//! and can use globals declared further down.
+//!
+//! A `const` can be neither declared again in its scope nor initialized
+//! with anything but literals, operators, tuples and other constants, so
+//! its value is fixed before the program runs.
+//!
+//! A member of an imported module, as in `util.helper`, must be one of the
+//! module's globals and, unless the module is the one running, exported by
+//! it with `pub`.
+//!
+//! A call to a name nothing declares that is a built-in function, such as
+//! `parseInt(s)`, becomes that primitive.
+//!
+//! A call to a global `@inline` function is replaced with its body when
+//! the body reads nothing but its parameters and has no statements, which
+//! makes it mean the same wherever it is copied:
+//!
+//! ```text
+//! f(a, b)   { let $arg0 = a; let $arg1 = b; { let x = $arg0; let y = $arg1; body } }
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+use std::rc::Rc;
+
+use crate::ast::Span;
+use crate::ir::{Catch, ClassDecl, Expr, ExprKind, FunctionDecl, MethodDecl, Pattern, Prim, Slot, Stmt, StmtKind, Var};
+
+/// A module's statements, resolved, and the names of its globals by slot.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    pub body: Vec<Stmt>,
+    pub globals: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolveError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Fill in the slot of every variable in a module. `load` gives the
+/// lowered statements of an import path, if it can be read, so uses of
+/// the module's members can be checked.
+pub fn resolve(mut body: Vec<Stmt>, load: &mut dyn FnMut(&str) -> Option<Vec<Stmt>>) -> Result<Program, ResolveError> {
+    let mut resolver = Resolver {
+        scopes: vec![Scope::default()],
+        deferred: Vec::new(),
+        inline: HashMap::new(),
+        load,
+        modules: HashMap::new(),
+    };
+    resolver.stmts(&mut body)?;
+
+    // functions and classes declared in a body add to the list, so go until it stays empty
+    while !resolver.deferred.is_empty() {
+        for (locals, code) in std::mem::take(&mut resolver.deferred) {
+            let mut scope = Scope::default();
+            for name in locals {
+                scope.bind_next(name);
+            }
+            resolver.scopes.push(scope);
+            resolver.exprs(code)?;
+            resolver.scopes.pop();
+        }
+    }
+
+    let mut globals = vec![String::new(); resolver.scopes[0].len];
+    for (name, (index, _)) in resolver.scopes.swap_remove(0).names {
+        globals[index] = name;
+    }
+    Ok(Program { body, globals })
+}
+
+#[derive(Default)]
+struct Scope {
+    /// Slot of each name and whether it is constant
+    names: HashMap<String, (usize, bool)>,
+    /// Slots handed out so far
+    len: usize,
+}
+
+impl Scope {
+    /// Give `name` a new slot. A repeated name is shadowed by the later one.
+    fn bind_next(&mut self, name: String) {
+        self.names.insert(name, (self.len, false));
+        self.len += 1;
+    }
+}
+
+struct Resolver<'a> {
+    /// Open scopes, globals first
+    scopes: Vec<Scope>,
+    /// Function and method bodies and `extends` arguments still to resolve,
+    /// each with the names of its frame in slot order
+    deferred: Vec<(Vec<String>, &'a mut [Expr])>,
+    /// Parameters and unresolved body of the global `@inline` functions
+    /// that can be inlined, by slot
+    inline: HashMap<usize, (Vec<String>, Expr)>,
+    load: &'a mut dyn FnMut(&str) -> Option<Vec<Stmt>>,
+    /// The global imports whose module could be loaded, by slot
+    modules: HashMap<usize, Module>,
+}
+
+/// What code outside an imported module may see of it
+struct Module {
+    name: String,
+    globals: HashSet<String>,
+    exports: HashSet<String>,
+}
+
+impl Module {
+    fn new(path: &str, body: &[Stmt]) -> Self {
+        let mut globals = HashSet::new();
+        let mut exports = HashSet::new();
+        for stmt in body {
+            match &stmt.kind {
+                StmtKind::Let { pattern, .. } => pattern_names(pattern, &mut globals),
+                StmtKind::Export(names) => exports.extend(names.iter().cloned()),
+                _ => {}
+            }
+        }
+        let name = Path::new(path).file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+        Self { name, globals, exports }
+    }
+
+    /// The error for `module.name` from outside the module, if any.
+    fn member(&self, name: &str) -> Option<String> {
+        if !self.globals.contains(name) {
+            Some(format!("Module '{}' has no member '{}'", self.name, name))
+        } else if !self.exports.contains(name) {
+            Some(format!("`{}` is private to module `{}`", name, self.name))
+        } else {
+            None
+        }
+    }
+}
+
+fn pattern_names(pattern: &Pattern, out: &mut HashSet<String>) {
+    match pattern {
+        Pattern::Identifier(var) => {
+            out.insert(var.name.clone());
+        }
+        Pattern::Tuple(items) => items.iter().for_each(|item| pattern_names(item, out)),
+    }
+}
+
+impl<'a> Resolver<'a> {
+    fn stmts(&mut self, stmts: &'a mut [Stmt]) -> Result<(), ResolveError> {
+        stmts.iter_mut().try_for_each(|stmt| self.stmt(stmt))
+    }
+
+    fn stmt(&mut self, stmt: &'a mut Stmt) -> Result<(), ResolveError> {
+        let Stmt { kind, span } = stmt;
+        let span = *span;
+        match kind {
+            StmtKind::Let { constant, pattern, value } => {
+                if *constant {
+                    self.constant(value)?;
+                }
+                let inline = self.inlinable(*constant, pattern, value);
+                let module = match &value.kind {
+                    ExprKind::Import { path } if self.scopes.len() == 1 => {
+                        (self.load)(path).map(|body| Module::new(path, &body))
+                    }
+                    _ => None,
+                };
+                self.expr(value)?;
+                self.declare(pattern, *constant, span)?;
+                if let Pattern::Identifier(Var { slot: Some(slot), .. }) = pattern {
+                    if let Some(inline) = inline {
+                        self.inline.insert(slot.index, inline);
+                    }
+                    if let Some(module) = module {
+                        self.modules.insert(slot.index, module);
+                    }
+                }
+                Ok(())
+            }
+            StmtKind::Expr(expr) | StmtKind::Throw(expr) | StmtKind::Return(expr) => self.expr(expr),
+            StmtKind::While { condition, body } => {
+                self.expr(condition)?;
+                self.scoped(|r| r.stmts(body))
+            }
+            StmtKind::Export(_) => Ok(()),
+            StmtKind::Impl(decl) => {
+                decl.methods.iter_mut().for_each(|method| self.function(method));
+                Ok(())
+            }
+        }
+    }
+
+    /// Give each name in `pattern` a slot in the innermost scope. Declaring
+    /// a name again reuses its slot, unless the first one was constant.
+    fn declare(&mut self, pattern: &mut Pattern, constant: bool, span: Span) -> Result<(), ResolveError> {
+        match pattern {
+            Pattern::Identifier(var) => {
+                let scope = self.scopes.last_mut().expect("the global scope is never closed");
+                let index = match scope.names.get(&var.name) {
+                    Some(&(_, true)) => return Err(ResolveError {
+                        message: format!("Cannot redefine constant '{}'", var.name),
+                        span,
+                    }),
+                    Some(&(index, false)) => index,
+                    None => {
+                        scope.len += 1;
+                        scope.len - 1
+                    }
+                };
+                scope.names.insert(var.name.clone(), (index, constant));
+                var.slot = Some(Slot { depth: 0, index });
+                Ok(())
+            }
+            Pattern::Tuple(items) => items.iter_mut().try_for_each(|item| self.declare(item, constant, span)),
+        }
+    }
+
+    /// Check that a `const` initializer is built from literals, operators,
+    /// tuples and other constants only. Lowering binds functions,
+    /// interfaces, classes, types and imports as constants too, so their
+    /// declarations count.
+    fn constant(&self, expr: &Expr) -> Result<(), ResolveError> {
+        match &expr.kind {
+            ExprKind::Literal(_)
+            | ExprKind::Function(_)
+            | ExprKind::Interface(_)
+            | ExprKind::Class(_)
+            | ExprKind::TypeDecl { .. }
+            | ExprKind::Import { .. } => Ok(()),
+            ExprKind::Var(var) => match self.scopes.iter().rev().find_map(|scope| scope.names.get(&var.name)) {
+                // one that is not declared at all is reported by `expr`
+                Some(&(_, true)) | None => Ok(()),
+                Some(&(_, false)) => Err(ResolveError {
+                    message: format!("Cannot initialize a constant from variable '{}'", var.name),
+                    span: expr.span,
+                }),
+            },
+            ExprKind::Tuple(items)
+            | ExprKind::Prim { op: Prim::Binary(_) | Prim::Some | Prim::Ok | Prim::Err | Prim::Range { .. }, args: items } => {
+                items.iter().try_for_each(|item| self.constant(item))
+            }
+            _ => Err(ResolveError {
+                message: "A constant must be initialized with literals, operators and other constants".to_string(),
+                span: expr.span,
+            }),
+        }
+    }
+
+    /// Run `f` in a new innermost scope.
+    fn scoped(&mut self, f: impl FnOnce(&mut Self) -> Result<(), ResolveError>) -> Result<(), ResolveError> {
+        self.scopes.push(Scope::default());
+        let result = f(self);
+        self.scopes.pop();
+        result
+    }
+
+    /// The slot `var` refers to from here, nearest declaration first.
+    fn find(&self, var: &Var) -> Option<Slot> {
+        self.scopes.iter().rev().enumerate().find_map(|(depth, scope)| {
+            scope.names.get(&var.name).map(|&(index, _)| Slot { depth, index })
+        })
+    }
+
+    fn lookup(&self, var: &mut Var, span: Span, undefined: impl FnOnce(&str) -> String) -> Result<(), ResolveError> {
+        var.slot = self.find(var);
+        match var.slot {
+            Some(_) => Ok(()),
+            None => Err(ResolveError { message: undefined(&var.name), span }),
+        }
+    }
+
+    fn expr(&mut self, expr: &'a mut Expr) -> Result<(), ResolveError> {
+        if let Some(inlined) = self.inlined(expr) {
+            *expr = inlined;
+        } else if let Some(builtin) = self.builtin(expr) {
+            *expr = builtin?;
+        }
+        let Expr { kind, span } = expr;
+        let span = *span;
+        match kind {
+            ExprKind::Literal(_) | ExprKind::Interface(_) | ExprKind::Import { .. } => Ok(()),
+            ExprKind::Var(var) => self.lookup(var, span, |name| format!("Undefined identifier '{}'", name)),
+            ExprKind::Tuple(items) | ExprKind::Prim { args: items, .. } => self.exprs(items),
+            ExprKind::Index { tuple: object, .. } => self.expr(object),
+            ExprKind::Member { object, name } => {
+                let hidden = self.imported(object).and_then(|module| module.member(name));
+                self.expr(object)?;
+                match hidden {
+                    Some(message) => Err(ResolveError { message, span }),
+                    None => Ok(()),
+                }
+            }
+            ExprKind::Call { callee, args } => {
+                self.expr(callee)?;
+                self.exprs(args)
+            }
+            ExprKind::Super { this, .. } => self.lookup(this, span, |_| "'super' used outside of a method".to_string()),
+            ExprKind::Block { body, value } => self.scoped(|r| {
+                r.stmts(body)?;
+                value.as_deref_mut().map_or(Ok(()), |value| r.expr(value))
+            }),
+            ExprKind::If { condition, then_branch, else_branch } => {
+                self.expr(condition)?;
+                self.expr(then_branch)?;
+                else_branch.as_deref_mut().map_or(Ok(()), |e| self.expr(e))
+            }
+            ExprKind::Try { body, value, catch, finally } => {
+                self.stmts(body)?;
+                if let Some(value) = value {
+                    self.expr(value)?;
+                }
+                if let Some(catch) = catch {
+                    let Catch { name, body } = catch;
+                    self.scoped(|r| {
+                        if let Some(name) = name {
+                            r.scopes.last_mut().expect("just opened").bind_next(name.name.clone());
+                            name.slot = Some(Slot { depth: 0, index: 0 });
+                        }
+                        r.stmts(body)
+                    })?;
+                }
+                finally.as_deref_mut().map_or(Ok(()), |finally| self.stmts(finally))
+            }
+            ExprKind::Function(decl) => {
+                self.function(decl);
+                Ok(())
+            }
+            ExprKind::Class(decl) => self.class(decl, span),
+            // a name that is not declared may be a built-in type, which the
+            // runtime checks first
+            ExprKind::TypeDecl { target, .. } => {
+                target.slot = self.find(target);
+                Ok(())
+            }
+        }
+    }
+
+    fn exprs(&mut self, exprs: &'a mut [Expr]) -> Result<(), ResolveError> {
+        exprs.iter_mut().try_for_each(|expr| self.expr(expr))
+    }
+
+    /// The module `expr` names, if it is a global import.
+    fn imported(&self, expr: &Expr) -> Option<&Module> {
+        let ExprKind::Var(var) = &expr.kind else { return None };
+        let slot = self.find(var)?;
+        self.modules.get(&slot.index).filter(|_| slot.depth == self.scopes.len() - 1)
+    }
+
+    /// The parameters and body of `const pattern = value`, if it declares a
+    /// global `@inline` function whose calls can be replaced with its body.
+    /// A function with bounds keeps its calls, which check them.
+    fn inlinable(&self, constant: bool, pattern: &Pattern, value: &Expr) -> Option<(Vec<String>, Expr)> {
+        match (pattern, &value.kind) {
+            (Pattern::Identifier(var), ExprKind::Function(decl))
+                if constant
+                    && decl.inline
+                    && decl.bounds.is_empty()
+                    && self.scopes.len() == 1
+                    && !self.scopes[0].names.contains_key(&var.name)
+                    && reads_only(&decl.body, &decl.params) =>
+            {
+                Some((decl.params.clone(), (*decl.body).clone()))
+            }
+            _ => None,
+        }
+    }
+
+    /// The body of the `@inline` function `expr` calls, with the arguments
+    /// bound to its parameters, if it can replace the call.
+    fn inlined(&self, expr: &Expr) -> Option<Expr> {
+        let ExprKind::Call { callee, args } = &expr.kind else { return None };
+        let ExprKind::Var(var) = &callee.kind else { return None };
+        let slot = self.find(var)?;
+        let (params, body) = self.inline.get(&slot.index).filter(|_| slot.depth == self.scopes.len() - 1)?;
+        if params.len() != args.len() {
+            return None;
+        }
+
+        // evaluate every argument before binding any parameter, so that an
+        // argument naming another parameter sees the caller's variable
+        let span = expr.span;
+        let temps: Vec<String> = (0..args.len()).map(|i| format!("$arg{}", i)).collect();
+        let bind = |names: &[String], values: Vec<Expr>| -> Vec<Stmt> {
+            names.iter().zip(values).map(|(name, value)| Stmt::new(StmtKind::Let {
+                constant: false,
+                pattern: Pattern::Identifier(Var::new(name.clone())),
+                value,
+            }, span)).collect()
+        };
+        let temp_vars = temps.iter().map(|temp| Expr::new(ExprKind::Var(Var::new(temp.clone())), span)).collect();
+        let inner = Expr::new(ExprKind::Block {
+            body: bind(params, temp_vars),
+            value: Some(Box::new(body.clone())),
+        }, span);
+        Some(Expr::new(ExprKind::Block { body: bind(&temps, args.clone()), value: Some(Box::new(inner)) }, span))
+    }
+
+    /// The primitive `expr` calls, if its callee is a built-in function that
+    /// nothing here declares.
+    fn builtin(&self, expr: &Expr) -> Option<Result<Expr, ResolveError>> {
+        let ExprKind::Call { callee, args } = &expr.kind else { return None };
+        let ExprKind::Var(var) = &callee.kind else { return None };
+        let (op, arity) = Prim::builtin(&var.name).filter(|_| self.find(var).is_none())?;
+        if args.len() != arity {
+            return Some(Err(ResolveError {
+                message: format!("{} takes {} argument(s) but {} were given", var.name, arity, args.len()),
+                span: expr.span,
+            }));
+        }
+        Some(Ok(Expr::new(ExprKind::Prim { op, args: args.clone() }, expr.span)))
+    }
+
+    /// The body runs when the function is called, so it is resolved later.
+    fn function(&mut self, decl: &'a mut FunctionDecl) {
+        let body = Rc::get_mut(&mut decl.body).expect("function bodies are not shared before they are resolved");
+        self.deferred.push((decl.params.clone(), std::slice::from_mut(body)));
+    }
+
+    /// The parent is looked up where the class is declared; the code inside
+    /// runs later.
+    fn class(&mut self, decl: &'a mut ClassDecl, span: Span) -> Result<(), ResolveError> {
+        let ClassDecl { params, parent, methods, .. } = decl;
+        if let Some((parent, args)) = parent {
+            self.lookup(parent, span, |name| format!("Undefined class '{}'", name))?;
+            self.deferred.push((params.clone(), args));
+        }
+        for MethodDecl { params, body, .. } in methods {
+            let locals = std::iter::once("self".to_string()).chain(params.iter().cloned()).collect();
+            let body = Rc::get_mut(body).expect("method bodies are not shared before they are resolved");
+            self.deferred.push((locals, std::slice::from_mut(body)));
+        }
+        Ok(())
+    }
+}
+
+/// Whether `expr` reads no variable but `params` and runs no statements,
+/// so that it means the same wherever it is copied.
+fn reads_only(expr: &Expr, params: &[String]) -> bool {
+    let all = |exprs: &[Expr]| exprs.iter().all(|expr| reads_only(expr, params));
+    match &expr.kind {
+        ExprKind::Literal(_) => true,
+        ExprKind::Var(var) => params.contains(&var.name),
+        ExprKind::Tuple(items) | ExprKind::Prim { args: items, .. } => all(items),
+        ExprKind::Index { tuple: object, .. } | ExprKind::Member { object, .. } => reads_only(object, params),
+        ExprKind::Call { callee, args } => reads_only(callee, params) && all(args),
+        ExprKind::Block { body, value } => body.is_empty() && value.as_deref().is_none_or(|value| reads_only(value, params)),
+        ExprKind::If { condition, then_branch, else_branch } => {
+            reads_only(condition, params)
+                && reads_only(then_branch, params)
+                && else_branch.as_deref().is_none_or(|e| reads_only(e, params))
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir::{lower, printer::print};
+    use crate::lexer::lexer as lex;
+    use crate::parser::Parser;
+
+    fn resolved(source: &str) -> Result<Program, ResolveError> {
+        resolve(lower(&Parser::new(lex(source)).parse()), &mut |_| None)
+    }
+
+    fn error(source: &str) -> String {
+        resolved(source).expect_err("should not resolve").message
+    }
+
+    #[test]
+    fn blocks_shadow_and_reuse_slots() {
+        let program = resolved("let a = 1; let b = { let a = a + 1; let a = a * 2; a }; let a = b;").unwrap();
+        assert_eq!(program.globals, ["a", "b"]);
+        assert_eq!(print(&program.body), "\
+let a@0:0 = 1
+let b@0:1 = {
+  let a@0:0 = (a@1:0 + 1)
+  let a@0:0 = (a@0:0 * 2)
+  a@0:0
+}
+let a@0:0 = b@0:1
+");
+    }
+
+    #[test]
+    fn methods_see_self_params_and_later_globals() {
+        let program = resolved(r#"
+            class B(z) {}
+            class A(x) extends B(x + k) {
+                fn f(y) { try { println(y); } catch (e) { println(e.message + k); } self.x }
+            }
+            const k = 1;
+        "#).unwrap();
+        assert_eq!(print(&program.body), "\
+const B@0:0 = class B(z) {
+}
+const A@0:1 = class A(x) extends B@0:0((x@0:0 + k@1:2)) {
+  fn f(y) {
+    try {
+      {
+        %println(y@2:1)
+      }
+    } catch (e@0:0) {
+      %println((e@0:0.message + k@3:2))
+    }
+    self@1:0.x
+  }
+}
+const k@0:2 = 1
+");
+    }
+
+    #[test]
+    fn names_must_be_declared_in_scope() {
+        assert_eq!(error("println(x); let x = 1;"), "Undefined identifier 'x'");
+        assert_eq!(error("let t = { let inner = 1; inner }; println(inner);"), "Undefined identifier 'inner'");
+        assert_eq!(error("let v = 1; class C() { fn f() { v + w } }"), "Undefined identifier 'w'");
+        assert_eq!(error("for i in 0..3 { println(i); } println(i);"), "Undefined identifier 'i'");
+        assert_eq!(error("class A extends B() {}"), "Undefined class 'B'");
+        assert_eq!(error("const c = 1; let c = 2;"), "Cannot redefine constant 'c'");
+        assert_eq!(error("super.f();"), "'super' used outside of a method");
+        assert_eq!(error("fn f(a) { let b = 1; g(a) } fn g(x) { b }"), "Undefined identifier 'b'");
+        assert_eq!(error("interface I { fn f(self); } impl I for int { fn f(self) { me } }"), "Undefined identifier 'me'");
+        assert_eq!(error("parseInt(\"1\", 2);"), "parseInt takes 1 argument(s) but 2 were given");
+        assert!(resolved("let c = 1; const c = 2; let d = { const c = 3; c }; type T = int; type U = T;").is_ok());
+    }
+
+    #[test]
+    fn calls_to_inline_functions_are_replaced_with_their_body() {
+        let program = resolved(r#"
+            @inline fn sub(a, b) { a - b }
+            @inline fn show(x) { println(x); }
+            let (a, b) = (1, 2);
+            println(sub(b, a));
+            fn f(sub) { sub(1, 2) }
+            show(a);
+        "#).unwrap();
+        assert!(print(&program.body[..1]).starts_with("const sub@0:0 = inline fn sub(a, b) {"));
+        assert_eq!(print(&program.body[3..]), "\
+%println({
+  let $arg0@0:0 = b@1:3
+  let $arg1@0:1 = a@1:2
+  {
+    let a@0:0 = $arg0@1:0
+    let b@0:1 = $arg1@1:1
+    {
+      (a@1:0 - b@1:1)
+    }
+  }
+})
+const f@0:4 = fn f(sub) {
+  sub@1:0(1, 2)
+}
+show@0:1(a@0:2)
+");
+    }
+
+    #[test]
+    fn only_exported_members_of_imports_are_visible() {
+        let util = lower(&Parser::new(lex("pub fn double(x) { helper(x) * 2 } fn helper(x) { x }")).parse());
+        let check = |source: &str| {
+            resolve(lower(&Parser::new(lex(source)).parse()), &mut |path| (path == "lib/util.sst").then(|| util.clone()))
+        };
+        assert!(check("import util from \"lib/util.sst\"; println(util.double(1));").is_ok());
+        assert_eq!(
+            check("import \"lib/util.sst\"; fn f() { util.helper(1) }").unwrap_err().message,
+            "`helper` is private to module `util`"
+        );
+        assert_eq!(
+            check("import util from \"lib/util.sst\"; println(util.triple);").unwrap_err().message,
+            "Module 'util' has no member 'triple'"
+        );
+        // a module that cannot be read is reported when it is imported
+        assert!(check("import other from \"other.sst\"; println(other.anything);").is_ok());
+    }
+
+    #[test]
+    fn constants_are_initialized_from_constants() {
+        assert!(resolved(r#"
+            const a = 2;
+            const (b, c) = (a * 3, "x" + "y");
+            const d = some(b < a);
+            const r = 0..a step 1;
+            class K() {}
+            fn h() {}
+            const e = (K, h);
+            let f = { const g = a + b; g };
+        "#).is_ok());
+        assert_eq!(error("let a = 1; const b = a + 1;"), "Cannot initialize a constant from variable 'a'");
+        assert_eq!(error("const a = 1; let b = { let a = 2; const c = a; c };"), "Cannot initialize a constant from variable 'a'");
+        assert_eq!(error("class C(x) { fn f(y) { const z = y; z } }"), "Cannot initialize a constant from variable 'y'");
+        assert_eq!(error("class K() {} const k = K();"), "A constant must be initialized with literals, operators and other constants");
+        assert_eq!(error("const n = none ?? 1;"), "A constant must be initialized with literals, operators and other constants");
+    }
+}
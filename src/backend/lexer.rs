@@ -1,43 +1,131 @@
+// `crate::lexer` is this inner module, re-exported from the crate root
+#[allow(clippy::module_inception)]
 pub mod lexer {
-    #[derive(Debug)]
+    #[derive(Debug, Clone)]
     pub struct Token {
         pub kind: TokenKind,
         pub value: Option<String>,
         pub line: usize,
         pub column: usize,
+        /// Just past the token's last character; filled in once it has been read.
+        pub end_line: usize,
+        pub end_column: usize,
+        /// Line of the macro call this token was expanded from, if any.
+        /// `line`/`column` then point into the macro definition.
+        pub expanded_at: Option<usize>,
     }
 
-    #[derive(Debug)]
+    #[derive(Debug, Clone, PartialEq)]
     pub enum TokenKind {
         Keyword(Keyword),
         Identifier(String),
+        Number(i64),
+        Str(String),
         Operator(Operator),
         Symbol(Symbol),
     }
 
-    #[derive(Debug)]
+    #[derive(Debug, Clone, PartialEq)]
     pub enum Keyword {
         Print,
         Println,
         Let,
         Const,
+        Import,
+        From,
+        Fn,
+        Interface,
+        Impl,
+        For,
+        None,
+        Throw,
+        Try,
+        Catch,
+        Finally,
+        Ok,
+        Err,
+        Some,
+        Defer,
+        If,
+        Else,
+        True,
+        False,
+        Macro,
+        Class,
+        Extends,
+        Super,
+        Type,
+        Newtype,
+        Pub,
     }
 
-    #[derive(Debug)]
+    #[derive(Debug, Clone, PartialEq)]
     pub enum Operator {
-        Assignment, // =
-        Plus,       // +
-        Minus,      // -
-        Multiply,   // *
-        Division,   // /
+        Assignment,   // =
+        Plus,         // +
+        Minus,        // -
+        Multiply,     // *
+        Division,     // /
+        Coalesce,     // ??
+        In,           // in
+        Equal,        // ==
+        NotEqual,     // !=
+        Less,         // <
+        LessEqual,    // <=
+        Greater,      // >
+        GreaterEqual, // >=
+        InstanceOf,   // instanceof
     }
 
-    #[derive(Debug)]
+    impl Operator {
+        pub const ALL: [Operator; 14] = [
+            Operator::Assignment, Operator::Plus, Operator::Minus, Operator::Multiply,
+            Operator::Division, Operator::Coalesce, Operator::In, Operator::Equal,
+            Operator::NotEqual, Operator::Less, Operator::LessEqual, Operator::Greater,
+            Operator::GreaterEqual, Operator::InstanceOf,
+        ];
+
+        /// How the operator is written in source
+        pub fn as_str(&self) -> &'static str {
+            match self {
+                Operator::Assignment => "=",
+                Operator::Plus => "+",
+                Operator::Minus => "-",
+                Operator::Multiply => "*",
+                Operator::Division => "/",
+                Operator::Coalesce => "??",
+                Operator::In => "in",
+                Operator::Equal => "==",
+                Operator::NotEqual => "!=",
+                Operator::Less => "<",
+                Operator::LessEqual => "<=",
+                Operator::Greater => ">",
+                Operator::GreaterEqual => ">=",
+                Operator::InstanceOf => "instanceof",
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
     pub enum Symbol {
         SemiColon, // ;
         LParen,    // (
         RParen,    // )
-        DblQuote,  // "
+        LBrace,    // {
+        RBrace,    // }
+        LBracket,  // [
+        RBracket,  // ]
+        Comma,     // ,
+        Colon,     // :
+        Bang,      // !
+        Dollar,    // $
+        At,        // @
+        Dot,       // .
+        Arrow,     // ->
+        DotDot,    // ..
+        DotDotEq,  // ..=
+        SafeDot,   // ?.
+        Question,  // ?
     }
 
     #[derive(Debug)]
@@ -50,14 +138,42 @@ pub mod lexer {
         String,
     }
 
+    /// Source text that cannot be split into tokens, and where.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct LexError {
+        pub message: String,
+        pub line: usize,
+        pub column: usize,
+    }
+
+    impl std::fmt::Display for LexError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{} at line {}, column {}", self.message, self.line, self.column)
+        }
+    }
+
+    /// [`tokenize`] for text known to be well formed, such as in tests;
+    /// panics on a [`LexError`] like the parser does on a syntax error.
     pub fn lexer(text: &str) -> Vec<Token> {
-        let mut tokens = Vec::new();
+        tokenize(text).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn tokenize(text: &str) -> Result<Vec<Token>, LexError> {
+        let mut tokens: Vec<Token> = Vec::new();
         let mut chars = text.chars().peekable();
 
         let mut line = 1;
         let mut column = 1;
+        // tokens before this index already know where they end
+        let mut ended = 0;
 
         while let Some(&ch) = chars.peek() {
+            // whatever the last iteration pushed ends where we are now
+            for tok in &mut tokens[ended..] {
+                tok.end_line = line;
+                tok.end_column = column;
+            }
+            ended = tokens.len();
 
             // --- WHITESPACE ---
             if ch.is_whitespace() {
@@ -81,6 +197,9 @@ pub mod lexer {
                         value: None,
                         line,
                         column,
+                        end_line: 0,
+                        end_column: 0,
+                        expanded_at: None,
                     });
                     chars.next(); column += 1;
                     continue;
@@ -91,6 +210,9 @@ pub mod lexer {
                         value: None,
                         line,
                         column,
+                        end_line: 0,
+                        end_column: 0,
+                        expanded_at: None,
                     });
                     chars.next(); column += 1;
                     continue;
@@ -101,16 +223,143 @@ pub mod lexer {
                         value: None,
                         line,
                         column,
+                        end_line: 0,
+                        end_column: 0,
+                        expanded_at: None,
+                    });
+                    chars.next(); column += 1;
+                    continue;
+                }
+                '{' => {
+                    tokens.push(Token {
+                        kind: TokenKind::Symbol(Symbol::LBrace),
+                        value: None,
+                        line,
+                        column,
+                        end_line: 0,
+                        end_column: 0,
+                        expanded_at: None,
                     });
                     chars.next(); column += 1;
                     continue;
                 }
-                '"' => {
+                '}' => {
                     tokens.push(Token {
-                        kind: TokenKind::Symbol(Symbol::DblQuote),
+                        kind: TokenKind::Symbol(Symbol::RBrace),
                         value: None,
                         line,
                         column,
+                        end_line: 0,
+                        end_column: 0,
+                        expanded_at: None,
+                    });
+                    chars.next(); column += 1;
+                    continue;
+                }
+                '$' => {
+                    tokens.push(Token {
+                        kind: TokenKind::Symbol(Symbol::Dollar),
+                        value: None,
+                        line,
+                        column,
+                        end_line: 0,
+                        end_column: 0,
+                        expanded_at: None,
+                    });
+                    chars.next(); column += 1;
+                    continue;
+                }
+                '@' => {
+                    tokens.push(Token {
+                        kind: TokenKind::Symbol(Symbol::At),
+                        value: None,
+                        line,
+                        column,
+                        end_line: 0,
+                        end_column: 0,
+                        expanded_at: None,
+                    });
+                    chars.next(); column += 1;
+                    continue;
+                }
+                ':' => {
+                    tokens.push(Token {
+                        kind: TokenKind::Symbol(Symbol::Colon),
+                        value: None,
+                        line,
+                        column,
+                        end_line: 0,
+                        end_column: 0,
+                        expanded_at: None,
+                    });
+                    chars.next(); column += 1;
+                    continue;
+                }
+                ',' => {
+                    tokens.push(Token {
+                        kind: TokenKind::Symbol(Symbol::Comma),
+                        value: None,
+                        line,
+                        column,
+                        end_line: 0,
+                        end_column: 0,
+                        expanded_at: None,
+                    });
+                    chars.next(); column += 1;
+                    continue;
+                }
+                '.' => {
+                    let mut ahead = chars.clone();
+                    ahead.next();
+
+                    let (kind, len) = if ahead.peek() == Some(&'.') {
+                        ahead.next();
+                        if ahead.peek() == Some(&'=') {
+                            (TokenKind::Symbol(Symbol::DotDotEq), 3)
+                        } else {
+                            (TokenKind::Symbol(Symbol::DotDot), 2)
+                        }
+                    } else {
+                        (TokenKind::Symbol(Symbol::Dot), 1)
+                    };
+
+                    tokens.push(Token {
+                        kind,
+                        value: None,
+                        line,
+                        column,
+                        end_line: 0,
+                        end_column: 0,
+                        expanded_at: None,
+                    });
+                    for _ in 0..len {
+                        chars.next();
+                    }
+                    column += len;
+                    continue;
+                }
+                '[' => {
+                    tokens.push(Token {
+                        kind: TokenKind::Symbol(Symbol::LBracket),
+                        value: None,
+                        line,
+                        column,
+                        end_line: 0,
+                        end_column: 0,
+                        expanded_at: None,
+                    });
+                    chars.next(); column += 1;
+                    continue;
+                }
+                ']' => {
+                    tokens.push(Token {
+                        kind: TokenKind::Symbol(Symbol::RBracket),
+                        value: None,
+                        line,
+                        column,
+                        end_line: 0,
+                        end_column: 0,
+                        expanded_at: None,
                     });
                     chars.next(); column += 1;
                     continue;
@@ -120,14 +369,36 @@ pub mod lexer {
 
             // --- OPERATORS ---
             match ch {
-                '=' => {
+                '=' | '!' | '<' | '>' => {
+                    let mut ahead = chars.clone();
+                    ahead.next();
+                    let followed_by_eq = ahead.peek() == Some(&'=');
+
+                    let kind = match (ch, followed_by_eq) {
+                        ('=', false) => TokenKind::Operator(Operator::Assignment),
+                        ('=', true) => TokenKind::Operator(Operator::Equal),
+                        ('!', false) => TokenKind::Symbol(Symbol::Bang),
+                        ('!', true) => TokenKind::Operator(Operator::NotEqual),
+                        ('<', false) => TokenKind::Operator(Operator::Less),
+                        ('<', true) => TokenKind::Operator(Operator::LessEqual),
+                        ('>', false) => TokenKind::Operator(Operator::Greater),
+                        _ => TokenKind::Operator(Operator::GreaterEqual),
+                    };
+                    let len = if followed_by_eq { 2 } else { 1 };
+
                     tokens.push(Token {
-                        kind: TokenKind::Operator(Operator::Assignment),
+                        kind,
                         value: None,
                         line,
                         column,
+                        end_line: 0,
+                        end_column: 0,
+                        expanded_at: None,
                     });
-                    chars.next(); column += 1;
+                    for _ in 0..len {
+                        chars.next();
+                    }
+                    column += len;
                     continue;
                 }
                 '+' => {
@@ -136,16 +407,42 @@ pub mod lexer {
                         value: None,
                         line,
                         column,
+                        end_line: 0,
+                        end_column: 0,
+                        expanded_at: None,
                     });
                     chars.next(); column += 1;
                     continue;
                 }
                 '-' => {
+                    let mut ahead = chars.clone();
+                    ahead.next();
+
+                    // `->` introduces a return type
+                    if ahead.peek() == Some(&'>') {
+                        tokens.push(Token {
+                            kind: TokenKind::Symbol(Symbol::Arrow),
+                            value: None,
+                            line,
+                            column,
+                            end_line: 0,
+                            end_column: 0,
+                            expanded_at: None,
+                        });
+                        chars.next();
+                        chars.next();
+                        column += 2;
+                        continue;
+                    }
+
                     tokens.push(Token {
                         kind: TokenKind::Operator(Operator::Minus),
                         value: None,
                         line,
                         column,
+                        end_line: 0,
+                        end_column: 0,
+                        expanded_at: None,
                     });
                     chars.next(); column += 1;
                     continue;
@@ -156,6 +453,9 @@ pub mod lexer {
                         value: None,
                         line,
                         column,
+                        end_line: 0,
+                        end_column: 0,
+                        expanded_at: None,
                     });
                     chars.next(); column += 1;
                     continue;
@@ -166,13 +466,124 @@ pub mod lexer {
                         value: None,
                         line,
                         column,
+                        end_line: 0,
+                        end_column: 0,
+                        expanded_at: None,
                     });
                     chars.next(); column += 1;
                     continue;
                 }
+                '?' => {
+                    let mut ahead = chars.clone();
+                    ahead.next();
+
+                    let (kind, len) = match ahead.peek() {
+                        Some('?') => (TokenKind::Operator(Operator::Coalesce), 2),
+                        Some('.') => (TokenKind::Symbol(Symbol::SafeDot), 2),
+                        _ => (TokenKind::Symbol(Symbol::Question), 1),
+                    };
+
+                    tokens.push(Token {
+                        kind,
+                        value: None,
+                        line,
+                        column,
+                        end_line: 0,
+                        end_column: 0,
+                        expanded_at: None,
+                    });
+                    for _ in 0..len {
+                        chars.next();
+                    }
+                    column += len;
+                    continue;
+                }
                 _ => {}
             }
 
+            // --- STRINGS ---
+            if ch == '"' {
+                chars.next(); column += 1; // opening "
+                let start_line = line;
+                let unterminated = || LexError {
+                    message: "Unterminated string literal".to_string(),
+                    line: start_line,
+                    column: start_column,
+                };
+
+                let mut text = String::new();
+                loop {
+                    let c = chars.next().ok_or_else(unterminated)?;
+                    column += 1;
+
+                    match c {
+                        '"' => break,
+                        '\\' => {
+                            let escaped = chars.next().ok_or_else(unterminated)?;
+                            column += 1;
+                            text.push(match escaped {
+                                'n' => '\n',
+                                't' => '\t',
+                                other => other, // \" and \\
+                            });
+                        }
+                        '\n' => {
+                            line += 1;
+                            column = 1;
+                            text.push(c);
+                        }
+                        _ => text.push(c),
+                    }
+                }
+
+                tokens.push(Token {
+                    kind: TokenKind::Str(text.clone()),
+                    value: Some(text),
+                    line: start_line,
+                    column: start_column,
+                    end_line: 0,
+                    end_column: 0,
+                    expanded_at: None,
+                });
+
+                continue;
+            }
+
+            // --- NUMBERS ---
+            // Only plain digits: `t.0.1` must lex as two tuple indices, not a float.
+            // `_` may separate them, as in `1_000_000`.
+            if ch.is_ascii_digit() {
+                let mut digits = String::new();
+
+                while let Some(&c) = chars.peek() {
+                    if c.is_ascii_digit() || c == '_' {
+                        digits.push(c);
+                        chars.next();
+                        column += 1;
+                    } else {
+                        break;
+                    }
+                }
+
+                let n = digits.replace('_', "").parse::<i64>().map_err(|_| LexError {
+                    message: format!("Integer literal {} does not fit in an int", digits),
+                    line,
+                    column: start_column,
+                })?;
+
+                tokens.push(Token {
+                    kind: TokenKind::Number(n),
+                    value: Some(digits),
+                    line,
+                    column: start_column,
+                    end_line: 0,
+                    end_column: 0,
+                    expanded_at: None,
+                });
+
+                continue;
+            }
+
             // --- IDENTIFIERS / KEYWORDS ---
             if ch.is_alphabetic() {
                 let mut ident = String::new();
@@ -193,15 +604,61 @@ pub mod lexer {
                     "println" => Some(Keyword::Println),
                     "let" => Some(Keyword::Let),
                     "const" => Some(Keyword::Const),
+                    "import" => Some(Keyword::Import),
+                    "from" => Some(Keyword::From),
+                    "fn" => Some(Keyword::Fn),
+                    "interface" => Some(Keyword::Interface),
+                    "impl" => Some(Keyword::Impl),
+                    "for" => Some(Keyword::For),
+                    "none" => Some(Keyword::None),
+                    "throw" => Some(Keyword::Throw),
+                    "try" => Some(Keyword::Try),
+                    "catch" => Some(Keyword::Catch),
+                    "finally" => Some(Keyword::Finally),
+                    "ok" => Some(Keyword::Ok),
+                    "err" => Some(Keyword::Err),
+                    "some" => Some(Keyword::Some),
+                    "defer" => Some(Keyword::Defer),
+                    "if" => Some(Keyword::If),
+                    "else" => Some(Keyword::Else),
+                    "true" => Some(Keyword::True),
+                    "false" => Some(Keyword::False),
+                    "macro" => Some(Keyword::Macro),
+                    "class" => Some(Keyword::Class),
+                    "extends" => Some(Keyword::Extends),
+                    "super" => Some(Keyword::Super),
+                    "type" => Some(Keyword::Type),
+                    "newtype" => Some(Keyword::Newtype),
+                    "pub" => Some(Keyword::Pub),
                     _ => None,
                 };
 
-                if let Some(kw) = keyword {
+                // operators spelled as words
+                let word_operator = match ident.as_str() {
+                    "in" => Some(Operator::In),
+                    "instanceof" => Some(Operator::InstanceOf),
+                    _ => None,
+                };
+
+                if let Some(op) = word_operator {
+                    tokens.push(Token {
+                        kind: TokenKind::Operator(op),
+                        value: None,
+                        line,
+                        column: start_column,
+                        end_line: 0,
+                        end_column: 0,
+                        expanded_at: None,
+                    });
+                } else if let Some(kw) = keyword {
                     tokens.push(Token {
                         kind: TokenKind::Keyword(kw),
                         value: None,
                         line,
                         column: start_column,
+                        end_line: 0,
+                        end_column: 0,
+                        expanded_at: None,
                     });
                 } else {
                     tokens.push(Token {
@@ -209,6 +666,9 @@ pub mod lexer {
                         value: Some(ident),
                         line,
                         column: start_column,
+                        end_line: 0,
+                        end_column: 0,
+                        expanded_at: None,
                     });
                 }
 
@@ -220,6 +680,27 @@ pub mod lexer {
             column += 1;
         }
 
-        tokens
+        for tok in &mut tokens[ended..] {
+            tok.end_line = line;
+            tok.end_column = column;
+        }
+
+        Ok(tokens)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn numbers_take_digit_separators_and_must_fit() {
+            let kinds: Vec<_> = lexer("0..1_000_000").into_iter().map(|t| t.kind).collect();
+            assert_eq!(kinds, [TokenKind::Number(0), TokenKind::Symbol(Symbol::DotDot), TokenKind::Number(1_000_000)]);
+
+            let error = tokenize("let x =\n  9223372036854775808;").unwrap_err();
+            assert_eq!(error.message, "Integer literal 9223372036854775808 does not fit in an int");
+            assert_eq!((error.line, error.column), (2, 3));
+            assert_eq!(tokenize("\"open").unwrap_err().message, "Unterminated string literal");
+        }
     }
 }
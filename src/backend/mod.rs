@@ -0,0 +1,9 @@
+//! Everything that happens before a program runs: lexing, parsing, the
+//! AST, type checking and its lowering to the core IR the interpreter
+//! executes.
+
+pub mod lexer;
+pub mod ast;
+pub mod parser;
+pub mod typeck;
+pub mod ir;
@@ -0,0 +1,324 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::lexer::{Keyword, Operator, Symbol, Token, TokenKind};
+
+/// Nested expansions deeper than this are assumed to be runaway recursion
+const MAX_DEPTH: usize = 64;
+
+/// A declarative macro:
+///
+/// ```text
+/// macro log!($msg) {
+///     println("[log] " + $msg);
+/// }
+/// log!("starting");
+/// ```
+struct MacroDef {
+    params: Vec<String>,
+    body: Vec<Token>,
+    line: usize,
+}
+
+/// Expands macro definitions and calls over the token stream, before parsing.
+#[derive(Default)]
+pub struct Expander {
+    macros: HashMap<String, MacroDef>,
+    /// Bumped per expansion so each one gets fresh hygienic names
+    expansions: usize,
+    /// Every identifier in the program plus those made for hygiene, which
+    /// a new hygienic name must not collide with
+    taken: HashSet<String>,
+}
+
+/// Remove macro definitions from `tokens` and replace every `name!(...)` call with its expansion.
+pub fn expand(tokens: Vec<Token>) -> Vec<Token> {
+    let taken = tokens.iter()
+        .filter_map(|t| match &t.kind {
+            TokenKind::Identifier(n) => Some(n.clone()),
+            _ => None,
+        })
+        .collect();
+    Expander { taken, ..Expander::default() }.expand(tokens, 0)
+}
+
+impl Expander {
+    fn expand(&mut self, tokens: Vec<Token>, depth: usize) -> Vec<Token> {
+        let mut out = Vec::new();
+        let mut i = 0;
+
+        while i < tokens.len() {
+            match &tokens[i].kind {
+                TokenKind::Keyword(Keyword::Macro) => {
+                    i = self.define(&tokens, i);
+                }
+
+                TokenKind::Identifier(name)
+                    if matches!(tokens.get(i + 1).map(|t| &t.kind), Some(TokenKind::Symbol(Symbol::Bang))) =>
+                {
+                    let call_line = tokens[i].line;
+                    let (args, next) = split_args(&tokens, i + 2, name, call_line);
+                    let expanded = self.instantiate(name, args, call_line);
+
+                    if depth >= MAX_DEPTH {
+                        panic!("Macro recursion limit reached expanding '{}!' at line {}", name, call_line);
+                    }
+                    let expanded = self.expand(expanded, depth + 1);
+
+                    // `log!(x);` where the body already ends in `;`
+                    let ends_stmt = matches!(expanded.last().map(|t| &t.kind), Some(TokenKind::Symbol(Symbol::SemiColon)));
+                    out.extend(expanded);
+
+                    i = next;
+                    if ends_stmt && matches!(tokens.get(i).map(|t| &t.kind), Some(TokenKind::Symbol(Symbol::SemiColon))) {
+                        i += 1;
+                    }
+                }
+
+                _ => {
+                    out.push(tokens[i].clone());
+                    i += 1;
+                }
+            }
+        }
+
+        out
+    }
+
+    /// Parse `macro name!($a, $b) { body }` starting at `start`; returns the index after it.
+    fn define(&mut self, tokens: &[Token], start: usize) -> usize {
+        let line = tokens[start].line;
+        let mut i = start + 1;
+
+        let name = match tokens.get(i).map(|t| &t.kind) {
+            Some(TokenKind::Identifier(n)) => n.clone(),
+            _ => panic!("Expected macro name after 'macro' at line {}", line),
+        };
+        i += 1;
+
+        expect(tokens, i, TokenKind::Symbol(Symbol::Bang), "'!' after macro name", line);
+        expect(tokens, i + 1, TokenKind::Symbol(Symbol::LParen), "'(' after macro name", line);
+        i += 2;
+
+        // $a, $b, ...
+        let mut params = Vec::new();
+        while !matches!(tokens.get(i).map(|t| &t.kind), Some(TokenKind::Symbol(Symbol::RParen)) | None) {
+            expect(tokens, i, TokenKind::Symbol(Symbol::Dollar), "'$name' macro parameter", line);
+            match tokens.get(i + 1).map(|t| &t.kind) {
+                Some(TokenKind::Identifier(p)) => params.push(p.clone()),
+                _ => panic!("Expected parameter name after '$' in macro '{}' at line {}", name, line),
+            }
+            i += 2;
+
+            if matches!(tokens.get(i).map(|t| &t.kind), Some(TokenKind::Symbol(Symbol::Comma))) {
+                i += 1;
+            }
+        }
+        expect(tokens, i, TokenKind::Symbol(Symbol::RParen), "')' after macro parameters", line);
+        i += 1;
+
+        expect(tokens, i, TokenKind::Symbol(Symbol::LBrace), "'{' to start macro body", line);
+        let body_start = i + 1;
+        let body_end = matching_close(tokens, i)
+            .unwrap_or_else(|| panic!("Unclosed body of macro '{}' defined at line {}", name, line));
+
+        self.macros.insert(name, MacroDef {
+            params,
+            body: tokens[body_start..body_end].to_vec(),
+            line,
+        });
+
+        body_end + 1
+    }
+
+    /// Substitute `args` into the body of `name`, renaming the names it binds.
+    fn instantiate(&mut self, name: &str, args: Vec<Vec<Token>>, call_line: usize) -> Vec<Token> {
+        let def = self.macros.get(name)
+            .unwrap_or_else(|| panic!("Unknown macro '{}!' at line {}", name, call_line));
+
+        if args.len() != def.params.len() {
+            panic!(
+                "Macro '{}!' takes {} argument(s) but {} were given at line {} (macro defined at line {})",
+                name, def.params.len(), args.len(), call_line, def.line
+            );
+        }
+
+        self.expansions += 1;
+        // plain identifiers, so the expanded program still prints as valid source
+        let mut hygienic = HashMap::new();
+        for n in bound_names(&def.body) {
+            let renamed = (self.expansions..)
+                .map(|k| format!("{}{}", n, k))
+                .find(|candidate| !self.taken.contains(candidate))
+                .unwrap();
+            self.taken.insert(renamed.clone());
+            hygienic.insert(n, renamed);
+        }
+
+        let mut out = Vec::new();
+        let mut i = 0;
+        while i < def.body.len() {
+            let tok = &def.body[i];
+
+            // $param -> argument tokens, which keep their call-site positions
+            if tok.kind == TokenKind::Symbol(Symbol::Dollar) {
+                let param = match def.body.get(i + 1).map(|t| &t.kind) {
+                    Some(TokenKind::Identifier(p)) => p,
+                    _ => panic!("Expected parameter name after '$' in macro '{}' at line {}", name, tok.line),
+                };
+                let index = def.params.iter().position(|p| p == param).unwrap_or_else(|| panic!(
+                    "Unknown macro parameter '${}' at line {} (in macro expanded at line {})",
+                    param, tok.line, call_line
+                ));
+                out.extend(args[index].iter().cloned());
+                i += 2;
+                continue;
+            }
+
+            let mut tok = tok.clone();
+            tok.expanded_at = Some(call_line);
+
+            // names the macro itself binds can't capture or shadow the caller's
+            if let TokenKind::Identifier(n) = &tok.kind {
+                let is_member = i > 0 && matches!(
+                    def.body[i - 1].kind,
+                    TokenKind::Symbol(Symbol::Dot) | TokenKind::Symbol(Symbol::SafeDot)
+                );
+                if let Some(renamed) = hygienic.get(n).filter(|_| !is_member) {
+                    let renamed = renamed.clone();
+                    tok.kind = TokenKind::Identifier(renamed.clone());
+                    tok.value = Some(renamed);
+                }
+            }
+
+            out.push(tok);
+            i += 1;
+        }
+
+        out
+    }
+}
+
+/// Names introduced by `let`/`const`/`for` patterns and `catch (e)` in a macro body.
+fn bound_names(body: &[Token]) -> HashSet<String> {
+    let mut names = HashSet::new();
+
+    for (i, tok) in body.iter().enumerate() {
+        let stop = match tok.kind {
+            TokenKind::Keyword(Keyword::Let) | TokenKind::Keyword(Keyword::Const) => TokenKind::Operator(Operator::Assignment),
+            TokenKind::Keyword(Keyword::For) => TokenKind::Operator(Operator::In),
+            TokenKind::Keyword(Keyword::Catch)
+                if matches!(body.get(i + 1).map(|t| &t.kind), Some(TokenKind::Symbol(Symbol::LParen))) =>
+            {
+                TokenKind::Symbol(Symbol::RParen)
+            }
+            _ => continue,
+        };
+
+        let rest = &body[i + 1..];
+        let binding = &rest[..rest.iter().position(|t| t.kind == stop).unwrap_or(rest.len())];
+        // a type written before the pattern names no variable
+        let pattern = &binding[pattern_start(binding)..];
+        for (j, t) in pattern.iter().enumerate() {
+            // `$x` in a pattern binds whatever the caller passed, not a macro name
+            let after_dollar = j > 0 && pattern[j - 1].kind == TokenKind::Symbol(Symbol::Dollar);
+            if let TokenKind::Identifier(n) = &t.kind {
+                if !after_dollar {
+                    names.insert(n.clone());
+                }
+            }
+        }
+    }
+
+    names
+}
+
+/// Where the pattern ending `binding` starts: its last identifier, or the
+/// `(` that opens a trailing tuple pattern. Anything before it is a type.
+fn pattern_start(binding: &[Token]) -> usize {
+    let Some(last) = binding.len().checked_sub(1) else { return 0 };
+    if binding[last].kind != TokenKind::Symbol(Symbol::RParen) {
+        let dollar = last > 0 && binding[last - 1].kind == TokenKind::Symbol(Symbol::Dollar);
+        return last - dollar as usize;
+    }
+    let mut depth = 0;
+    for (i, t) in binding.iter().enumerate().rev() {
+        match t.kind {
+            TokenKind::Symbol(Symbol::RParen) => depth += 1,
+            TokenKind::Symbol(Symbol::LParen) => {
+                depth -= 1;
+                if depth == 0 {
+                    return i;
+                }
+            }
+            _ => {}
+        }
+    }
+    0
+}
+
+/// Split `( a, b, c )` starting at `open` into per-argument token lists,
+/// only splitting on commas outside nested brackets. Returns the index after `)`.
+fn split_args(tokens: &[Token], open: usize, name: &str, line: usize) -> (Vec<Vec<Token>>, usize) {
+    expect(tokens, open, TokenKind::Symbol(Symbol::LParen), "'(' after macro name", line);
+    let close = matching_close(tokens, open)
+        .unwrap_or_else(|| panic!("Unclosed call to macro '{}!' at line {}", name, line));
+
+    let mut args = Vec::new();
+    let mut current = Vec::new();
+    let mut depth = 0;
+    for tok in &tokens[open + 1..close] {
+        match tok.kind {
+            TokenKind::Symbol(Symbol::LParen | Symbol::LBrace | Symbol::LBracket) => depth += 1,
+            TokenKind::Symbol(Symbol::RParen | Symbol::RBrace | Symbol::RBracket) => depth -= 1,
+            TokenKind::Symbol(Symbol::Comma) if depth == 0 => {
+                args.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(tok.clone());
+    }
+    if !current.is_empty() {
+        args.push(current);
+    }
+
+    (args, close + 1)
+}
+
+/// Index of the bracket closing the one at `open`.
+fn matching_close(tokens: &[Token], open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, tok) in tokens.iter().enumerate().skip(open) {
+        match tok.kind {
+            TokenKind::Symbol(Symbol::LParen | Symbol::LBrace | Symbol::LBracket) => depth += 1,
+            TokenKind::Symbol(Symbol::RParen | Symbol::RBrace | Symbol::RBracket) => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn expect(tokens: &[Token], i: usize, kind: TokenKind, what: &str, line: usize) {
+    if tokens.get(i).map(|t| &t.kind) != Some(&kind) {
+        panic!("Expected {} in macro at line {}", what, line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lexer as lex;
+
+    #[test]
+    fn bound_names_skip_types_and_parameters() {
+        let body = lex("let Meters? d = x; const (int, string) (a, $b) = t; for (i, j) in y {} catch (e) {} let $c = 1;");
+        let mut names: Vec<String> = bound_names(&body).into_iter().collect();
+        names.sort();
+        assert_eq!(names, ["a", "d", "e", "i", "j"]);
+    }
+}
@@ -1,3 +1,5 @@
+#[allow(clippy::module_inception)]
 pub mod parser;
+pub mod macros;
 
 pub use parser::Parser;
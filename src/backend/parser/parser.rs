@@ -1,27 +1,36 @@
 use crate::lexer::{Keyword, Operator, Symbol, Token, TokenKind};
-use crate::ast::{Expr, Stmt};
+use crate::ast::attributes::declaration;
+use crate::ast::{
+    Ast, Attribute, CatchClause, ClassDecl, ExprId, ExprKind, FunctionDecl, ImplDecl, InterfaceDecl, MethodDecl,
+    MethodSig, Param, ParentClass, Pattern, Span, StmtId, StmtKind, TypeExpr, TypeParam,
+};
+use crate::parser::macros;
 
 /// A simple recursive-descent parser
 pub struct Parser {
     tokens: Vec<Token>,
     pos: usize,
+    /// Where parsed nodes are allocated
+    ast: Ast,
 }
 
 impl Parser {
-    /// Create a new parser
+    /// Create a new parser. Macros are expanded here, before any parsing.
     pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, pos: 0 }
+        Self { tokens: macros::expand(tokens), pos: 0, ast: Ast::default() }
     }
 
     /// Parse all statements in the file
-    pub fn parse(&mut self) -> Vec<Stmt> {
+    pub fn parse(&mut self) -> Ast {
         let mut statements = Vec::new();
 
         while !self.is_end() {
             statements.push(self.parse_statement());
         }
 
-        statements
+        let mut ast = std::mem::take(&mut self.ast);
+        ast.body = statements;
+        ast
     }
 
     // --------------------------
@@ -42,13 +51,56 @@ impl Parser {
         self.pos >= self.tokens.len()
     }
 
+    /// "line N", plus the macro call site for tokens produced by a macro expansion
+    fn location(&self) -> String {
+        let tok = self.current();
+        match tok.expanded_at {
+            Some(call) => format!("line {} (in macro expanded at line {})", tok.line, call),
+            None => format!("line {}", tok.line),
+        }
+    }
+
+    /// Whether the token after the current one can begin an expression.
+    fn next_starts_expression(&self) -> bool {
+        match self.tokens.get(self.pos + 1).map(|t| &t.kind) {
+            Some(TokenKind::Identifier(_)) |
+            Some(TokenKind::Number(_)) |
+            Some(TokenKind::Str(_)) |
+            Some(TokenKind::Symbol(Symbol::LParen)) |
+            Some(TokenKind::Symbol(Symbol::LBrace)) => true,
+            Some(TokenKind::Keyword(kw)) => matches!(
+                kw,
+                Keyword::None | Keyword::True | Keyword::False | Keyword::If |
+                Keyword::Ok | Keyword::Err | Keyword::Some | Keyword::Super
+            ),
+            _ => false,
+        }
+    }
+
+    /// Span of the token about to be consumed
+    fn start_span(&self) -> Span {
+        Span::of(self.current())
+    }
+
+    /// From `start` through the last consumed token
+    fn span_from(&self, start: Span) -> Span {
+        start.to(Span::of(&self.tokens[self.pos - 1]))
+    }
+
+    fn span_of(&self, expr: ExprId) -> Span {
+        self.ast[expr].span
+    }
+
+    fn expr(&mut self, kind: ExprKind, span: Span) -> ExprId {
+        self.ast.add_expr(kind, span)
+    }
+
     fn matches(&mut self, kind: &TokenKind) -> bool {
         if self.is_end() {
             return false;
         }
 
-        let ok = std::mem::discriminant(&self.current().kind)
-            == std::mem::discriminant(kind);
+        let ok = &self.current().kind == kind;
 
         if ok {
             self.advance();
@@ -61,32 +113,486 @@ impl Parser {
     // STATEMENTS
     // --------------------------
 
-    fn parse_statement(&mut self) -> Stmt {
-        match &self.current().kind {
+    fn parse_statement(&mut self) -> StmtId {
+        let start = self.start_span();
+        let kind = match &self.current().kind {
+            TokenKind::Symbol(Symbol::At) => self.parse_attributed(),
+            TokenKind::Keyword(Keyword::Pub) => self.parse_pub(),
             TokenKind::Keyword(Keyword::Let) => self.parse_var_decl(false),
             TokenKind::Keyword(Keyword::Const) => self.parse_var_decl(true),
+            TokenKind::Keyword(Keyword::Import) => self.parse_import(),
+            TokenKind::Keyword(Keyword::Fn) => StmtKind::Function(self.parse_function()),
+            TokenKind::Keyword(Keyword::Interface) => self.parse_interface(),
+            TokenKind::Keyword(Keyword::Impl) => self.parse_impl(),
+            TokenKind::Keyword(Keyword::Class) => self.parse_class(),
+            TokenKind::Keyword(Keyword::Type) => self.parse_type_decl(false),
+            TokenKind::Keyword(Keyword::Newtype) => self.parse_type_decl(true),
             TokenKind::Keyword(Keyword::Print) => self.parse_print(false),
             TokenKind::Keyword(Keyword::Println) => self.parse_print(true),
-            _ => panic!("Unexpected statement at line {}", self.current().line),
+            TokenKind::Keyword(Keyword::Throw) => self.parse_throw(),
+            TokenKind::Keyword(Keyword::Try) => self.parse_try(),
+            TokenKind::Keyword(Keyword::Defer) => self.parse_defer(),
+            TokenKind::Keyword(Keyword::For) => self.parse_for(),
+            TokenKind::Keyword(Keyword::If) | TokenKind::Symbol(Symbol::LBrace) => {
+                let expr = if let TokenKind::Keyword(Keyword::If) = self.current().kind {
+                    self.parse_if()
+                } else {
+                    self.parse_block_expr()
+                };
+
+                // optional semicolon, and the statement may end the file
+                self.matches(&TokenKind::Symbol(Symbol::SemiColon));
+
+                StmtKind::Expression { expr }
+            }
+            _ if self.starts_expression() => {
+                let expr = self.parse_expression();
+
+                // expect semicolon
+                match self.current().kind {
+                    TokenKind::Symbol(Symbol::SemiColon) => self.advance(),
+                    _ => panic!("Expected ';' after expression at {}", self.location()),
+                };
+
+                StmtKind::Expression { expr }
+            }
+            _ => panic!("Unexpected statement at {}", self.location()),
+        };
+
+        let span = self.span_from(start);
+        self.ast.add_stmt(kind, span)
+    }
+
+    /// `pub` before a top-level declaration exports it from the module
+    fn parse_pub(&mut self) -> StmtKind {
+        self.advance(); // consume `pub`
+
+        match self.current().kind {
+            TokenKind::Keyword(
+                Keyword::Let | Keyword::Const | Keyword::Fn | Keyword::Interface |
+                Keyword::Class | Keyword::Type | Keyword::Newtype
+            ) => {
+                StmtKind::Public { stmt: self.parse_statement() }
+            }
+            _ => panic!("Expected a declaration after 'pub' at {}", self.location()),
         }
     }
 
-    fn parse_var_decl(&mut self, constant: bool) -> Stmt {
-        self.advance(); // consume `let` or `const`
+    /// `@name` / `@name(args)`, one or more, followed by the statement they annotate
+    fn parse_attributed(&mut self) -> StmtKind {
+        let mut attributes = Vec::new();
+
+        while !self.is_end() && self.current().kind == TokenKind::Symbol(Symbol::At) {
+            let start = self.start_span();
+            self.advance(); // consume `@`
+
+            let name = if let TokenKind::Identifier(n) = &self.current().kind {
+                let val = n.clone();
+                self.advance();
+                val
+            } else {
+                panic!("Expected attribute name after '@' at {}", self.location());
+            };
+
+            let mut args = Vec::new();
+            if self.matches(&TokenKind::Symbol(Symbol::LParen)) {
+                while !matches!(self.current().kind, TokenKind::Symbol(Symbol::RParen)) {
+                    args.push(self.parse_expression());
+
+                    if !self.matches(&TokenKind::Symbol(Symbol::Comma)) {
+                        break;
+                    }
+                }
+
+                // expect ")"
+                match self.current().kind {
+                    TokenKind::Symbol(Symbol::RParen) => self.advance(),
+                    _ => panic!("Expected ')' after attribute arguments at {}", self.location()),
+                };
+            }
+
+            attributes.push(Attribute { name, args, span: self.span_from(start) });
+        }
+
+        let at = self.location();
+        let stmt = self.parse_statement();
+        if declaration(&self.ast, stmt).is_none() {
+            panic!("Expected a declaration after attributes at {}", at);
+        }
+        StmtKind::Attributed { attributes, stmt }
+    }
+
+    /// Whether the current token can begin an expression.
+    fn starts_expression(&self) -> bool {
+        matches!(
+            self.current().kind,
+            TokenKind::Identifier(_) | TokenKind::Number(_) | TokenKind::Str(_) |
+            TokenKind::Symbol(Symbol::LParen) |
+            TokenKind::Keyword(
+                Keyword::None | Keyword::True | Keyword::False | Keyword::Ok | Keyword::Err | Keyword::Some |
+                Keyword::Super
+            )
+        )
+    }
+
+    /// fn max<T>(T a, T b) -> T { a }
+    fn parse_function(&mut self) -> FunctionDecl {
+        self.advance(); // consume `fn`
+
+        let name = self.expect_identifier("function name");
+        let type_params = self.parse_type_params();
+        let params = self.parse_params();
+        let returns = self.parse_return_type();
+        let (body, value) = self.parse_body();
+
+        FunctionDecl { name, type_params, params, returns, body, value }
+    }
+
+    /// `-> T` after a parameter list, if present
+    fn parse_return_type(&mut self) -> Option<TypeExpr> {
+        if self.matches(&TokenKind::Symbol(Symbol::Arrow)) {
+            Some(self.parse_type())
+        } else {
+            None
+        }
+    }
+
+    /// interface Shape { fn area(self) -> int; fn scale(self, int k) -> int; }
+    fn parse_interface(&mut self) -> StmtKind {
+        self.advance(); // consume `interface`
+
+        let name = self.expect_identifier("interface name");
+
+        // expect "{"
+        match self.current().kind {
+            TokenKind::Symbol(Symbol::LBrace) => self.advance(),
+            _ => panic!("Expected '{{' to start interface body at {}", self.location()),
+        };
+
+        let mut methods: Vec<MethodSig> = Vec::new();
+        while !self.matches(&TokenKind::Symbol(Symbol::RBrace)) {
+            // expect `fn`
+            match self.current().kind {
+                TokenKind::Keyword(Keyword::Fn) => self.advance(),
+                _ => panic!("Expected 'fn' method signature in interface {} at {}", name, self.location()),
+            };
+
+            let method = self.expect_identifier("method name");
+            let params = self.parse_method_params(&method);
+            let returns = self.parse_return_type();
+            if methods.iter().any(|m| m.name == method) {
+                panic!("Duplicate method '{}' in interface {} at {}", method, name, self.location());
+            }
+
+            // expect semicolon
+            match self.current().kind {
+                TokenKind::Symbol(Symbol::SemiColon) => self.advance(),
+                _ => panic!("Expected ';' after method signature at {}", self.location()),
+            };
+
+            methods.push(MethodSig { name: method, params, returns });
+        }
+
+        StmtKind::Interface(InterfaceDecl { name, methods })
+    }
+
+    /// impl Shape for int { fn area(self) -> int { self * self } }
+    fn parse_impl(&mut self) -> StmtKind {
+        self.advance(); // consume `impl`
+
+        let interface = self.expect_identifier("interface name");
+
+        // expect `for`
+        match self.current().kind {
+            TokenKind::Keyword(Keyword::For) => self.advance(),
+            _ => panic!("Expected 'for' after 'impl {}' at {}", interface, self.location()),
+        };
+
+        let target = self.expect_identifier("type name");
+
+        // expect "{"
+        match self.current().kind {
+            TokenKind::Symbol(Symbol::LBrace) => self.advance(),
+            _ => panic!("Expected '{{' to start impl body at {}", self.location()),
+        };
+
+        let mut methods = Vec::new();
+        while !self.matches(&TokenKind::Symbol(Symbol::RBrace)) {
+            match self.current().kind {
+                TokenKind::Keyword(Keyword::Fn) => {}
+                _ => panic!("Expected 'fn' method in impl {} for {} at {}", interface, target, self.location()),
+            };
+
+            let at = self.location();
+            let method = self.parse_function();
+            if method.params.first().map(|p| p.name.as_str()) != Some("self") {
+                panic!("Method '{}' must take self as its first parameter at {}", method.name, at);
+            }
+            methods.push(method);
+        }
+
+        StmtKind::Impl(ImplDecl { interface, target, methods })
+    }
+
+    /// Parameters of a method signature, which must start with `self`
+    fn parse_method_params(&mut self, method: &str) -> Vec<Param> {
+        let at = self.location();
+        let params = self.parse_params();
+        if params.first().map(|p| p.name.as_str()) != Some("self") {
+            panic!("Method '{}' must take self as its first parameter at {}", method, at);
+        }
+        params
+    }
+
+    /// `<T, U: Shape>` after a declaration's name; empty when there is none
+    fn parse_type_params(&mut self) -> Vec<TypeParam> {
+        let mut params = Vec::new();
+        if !self.matches(&TokenKind::Operator(Operator::Less)) {
+            return params;
+        }
+
+        while !self.matches(&TokenKind::Operator(Operator::Greater)) {
+            let name = self.expect_identifier("type parameter name");
+            if params.iter().any(|p: &TypeParam| p.name == name) {
+                panic!("Duplicate type parameter '{}' at {}", name, self.location());
+            }
+
+            // `T: Shape`
+            let bound = if self.matches(&TokenKind::Symbol(Symbol::Colon)) {
+                Some(self.expect_identifier("interface name"))
+            } else {
+                None
+            };
+            params.push(TypeParam { name, bound });
+
+            if !self.matches(&TokenKind::Symbol(Symbol::Comma)) {
+                // expect ">"
+                match self.current().kind {
+                    TokenKind::Operator(Operator::Greater) => self.advance(),
+                    _ => panic!("Expected '>' after type parameters at {}", self.location()),
+                };
+                break;
+            }
+        }
+
+        params
+    }
+
+    /// `(T a, b)` parameters, each with an optional type before its name
+    fn parse_params(&mut self) -> Vec<Param> {
+        self.parse_param_list(false).into_iter().map(|(param, _)| param).collect()
+    }
+
+    /// `(a, b)`, or `(pub a, b)` when `allow_pub` is set for class fields.
+    /// Each parameter comes with whether it was marked `pub`.
+    fn parse_param_list(&mut self, allow_pub: bool) -> Vec<(Param, bool)> {
+        // expect "("
+        match self.current().kind {
+            TokenKind::Symbol(Symbol::LParen) => self.advance(),
+            _ => panic!("Expected '(' before parameters at {}", self.location()),
+        };
+
+        let mut params = Vec::new();
+        while !self.matches(&TokenKind::Symbol(Symbol::RParen)) {
+            let public = allow_pub && self.matches(&TokenKind::Keyword(Keyword::Pub));
+            let ty = if self.at_typed_param() { Some(self.parse_type()) } else { None };
+            let name = self.expect_identifier("parameter name");
+            params.push((Param { name, ty }, public));
+
+            if !self.matches(&TokenKind::Symbol(Symbol::Comma)) {
+                // expect ")"
+                match self.current().kind {
+                    TokenKind::Symbol(Symbol::RParen) => self.advance(),
+                    _ => panic!("Expected ')' after parameters at {}", self.location()),
+                };
+                break;
+            }
+        }
+
+        params
+    }
 
-        // expect identifier
-        let name = if let TokenKind::Identifier(n) = &self.current().kind {
+    /// Whether a parameter starts with a type: `T a`, `int? n`, `Pair<A, B> p`, `(A, B) p`
+    fn at_typed_param(&self) -> bool {
+        match self.current().kind {
+            TokenKind::Symbol(Symbol::LParen) => true,
+            TokenKind::Identifier(_) => matches!(
+                self.tokens.get(self.pos + 1).map(|t| &t.kind),
+                Some(TokenKind::Identifier(_)) | Some(TokenKind::Operator(Operator::Less)) |
+                Some(TokenKind::Symbol(Symbol::Question))
+            ),
+            _ => false,
+        }
+    }
+
+    /// Whether a `let` or `const` starts with a type: `let int x`, `let int? x`,
+    /// `let (int, int) p`. A tuple there is a pattern unless another
+    /// pattern follows it instead of `=`.
+    fn at_typed_declaration(&self) -> bool {
+        if !matches!(self.current().kind, TokenKind::Symbol(Symbol::LParen)) {
+            return self.at_typed_param();
+        }
+        let mut depth = 0;
+        for (i, token) in self.tokens[self.pos..].iter().enumerate() {
+            match token.kind {
+                TokenKind::Symbol(Symbol::LParen) => depth += 1,
+                TokenKind::Symbol(Symbol::RParen) if depth == 1 => return !matches!(
+                    self.tokens.get(self.pos + i + 1).map(|t| &t.kind),
+                    Some(TokenKind::Operator(Operator::Assignment))
+                ),
+                TokenKind::Symbol(Symbol::RParen) => depth -= 1,
+                _ => {}
+            }
+        }
+        false
+    }
+
+    /// `int`, `T`, `Pair<A, B>` or `(A, B)`, each optionally followed by `?`
+    fn parse_type(&mut self) -> TypeExpr {
+        let mut ty = self.parse_base_type();
+        while self.matches(&TokenKind::Symbol(Symbol::Question)) {
+            ty = TypeExpr::Optional(Box::new(ty));
+        }
+        ty
+    }
+
+    fn parse_base_type(&mut self) -> TypeExpr {
+        match self.current().kind.clone() {
+            TokenKind::Identifier(name) => {
+                self.advance();
+                if !self.matches(&TokenKind::Operator(Operator::Less)) {
+                    return TypeExpr::Named(name);
+                }
+
+                let mut args = Vec::new();
+                while !self.matches(&TokenKind::Operator(Operator::Greater)) {
+                    args.push(self.parse_type());
+
+                    if !self.matches(&TokenKind::Symbol(Symbol::Comma)) {
+                        // expect ">"
+                        match self.current().kind {
+                            TokenKind::Operator(Operator::Greater) => self.advance(),
+                            _ => panic!("Expected '>' after type arguments at {}", self.location()),
+                        };
+                        break;
+                    }
+                }
+                TypeExpr::Generic(name, args)
+            }
+
+            TokenKind::Symbol(Symbol::LParen) => {
+                self.advance(); // consume "("
+
+                let mut items = Vec::new();
+                while !self.matches(&TokenKind::Symbol(Symbol::RParen)) {
+                    items.push(self.parse_type());
+
+                    if !self.matches(&TokenKind::Symbol(Symbol::Comma)) {
+                        // expect ")"
+                        match self.current().kind {
+                            TokenKind::Symbol(Symbol::RParen) => self.advance(),
+                            _ => panic!("Expected ')' after tuple type at {}", self.location()),
+                        };
+                        break;
+                    }
+                }
+                TypeExpr::Tuple(items)
+            }
+
+            _ => panic!("Expected a type at {}", self.location()),
+        }
+    }
+
+    /// `{ stmt; ... expr }`: the statements of a block or function body
+    /// and its trailing expression, which is the block's value
+    fn parse_body(&mut self) -> (Vec<StmtId>, Option<ExprId>) {
+        // expect "{"
+        match self.current().kind {
+            TokenKind::Symbol(Symbol::LBrace) => self.advance(),
+            _ => panic!("Expected '{{' at {}", self.location()),
+        };
+
+        let mut statements = Vec::new();
+        let mut value = None;
+        while !self.matches(&TokenKind::Symbol(Symbol::RBrace)) {
+            if !self.starts_expression() {
+                statements.push(self.parse_nested_statement());
+                continue;
+            }
+
+            let expr = self.parse_expression();
+
+            // `expr;` is a statement, a bare trailing `expr` is the value
+            if self.matches(&TokenKind::Symbol(Symbol::SemiColon)) {
+                let span = self.span_from(self.span_of(expr));
+                statements.push(self.ast.add_stmt(StmtKind::Expression { expr }, span));
+            } else {
+                value = Some(expr);
+
+                // expect "}"
+                match self.current().kind {
+                    TokenKind::Symbol(Symbol::RBrace) => self.advance(),
+                    _ => panic!("Expected '}}' after the block's value at {}", self.location()),
+                };
+                break;
+            }
+        }
+
+        // a trailing `if` or block statement is the block's value
+        if value.is_none() {
+            if let Some(&last) = statements.last() {
+                if let StmtKind::Expression { expr } = self.ast[last].kind {
+                    if let ExprKind::If { .. } | ExprKind::Block { .. } = self.ast[expr].kind {
+                        statements.pop();
+                        value = Some(expr);
+                    }
+                }
+            }
+        }
+
+        (statements, value)
+    }
+
+    /// Call arguments after an already-consumed "(": `a, b)`
+    fn parse_args(&mut self) -> Vec<ExprId> {
+        let mut args = Vec::new();
+        while !matches!(self.current().kind, TokenKind::Symbol(Symbol::RParen)) {
+            args.push(self.parse_expression());
+
+            if !self.matches(&TokenKind::Symbol(Symbol::Comma)) {
+                break;
+            }
+        }
+
+        // expect ")"
+        match self.current().kind {
+            TokenKind::Symbol(Symbol::RParen) => self.advance(),
+            _ => panic!("Expected ')' after arguments at {}", self.location()),
+        };
+
+        args
+    }
+
+    fn expect_identifier(&mut self, what: &str) -> String {
+        if let TokenKind::Identifier(n) = &self.current().kind {
             let val = n.clone();
             self.advance();
             val
         } else {
-            panic!("Expected identifier after let/const");
-        };
+            panic!("Expected {} at {}", what, self.location());
+        }
+    }
+
+    fn parse_var_decl(&mut self, constant: bool) -> StmtKind {
+        self.advance(); // consume `let` or `const`
+
+        let ty = if self.at_typed_declaration() { Some(self.parse_type()) } else { None };
+        let pattern = self.parse_pattern();
 
         // expect `=`
         match self.current().kind {
             TokenKind::Operator(Operator::Assignment) => self.advance(),
-            _ => panic!("Expected '=' after variable name"),
+            _ => panic!("Expected '=' after variable name at {}", self.location()),
         };
 
         let expr = self.parse_expression();
@@ -94,23 +600,170 @@ impl Parser {
         // expect semicolon
         match self.current().kind {
             TokenKind::Symbol(Symbol::SemiColon) => self.advance(),
-            _ => panic!("Expected ';' after expression"),
+            _ => panic!("Expected ';' after expression at {}", self.location()),
         };
 
-        Stmt::VarDeclaration {
+        StmtKind::VarDeclaration {
             constant,
-            name,
+            ty,
+            pattern,
             value: expr,
         }
     }
 
-    fn parse_print(&mut self, newline: bool) -> Stmt {
+    /// Parse a binding pattern: `x` or `(a, (b, c))`
+    fn parse_pattern(&mut self) -> Pattern {
+        match &self.current().kind {
+            TokenKind::Identifier(n) => {
+                let name = n.clone();
+                self.advance();
+                Pattern::Identifier(name)
+            }
+
+            TokenKind::Symbol(Symbol::LParen) => {
+                self.advance(); // consume "("
+
+                let mut items = Vec::new();
+                while !matches!(self.current().kind, TokenKind::Symbol(Symbol::RParen)) {
+                    items.push(self.parse_pattern());
+
+                    if !self.matches(&TokenKind::Symbol(Symbol::Comma)) {
+                        break;
+                    }
+                }
+
+                // expect ")"
+                match self.current().kind {
+                    TokenKind::Symbol(Symbol::RParen) => self.advance(),
+                    _ => panic!("Expected ')' after tuple pattern at {}", self.location()),
+                };
+
+                Pattern::Tuple(items)
+            }
+
+            _ => panic!("Expected identifier or tuple pattern after let/const at {}", self.location()),
+        }
+    }
+
+    /// class Dog(name, breed) extends Animal(name) { fn speak() -> string { "woof" } }
+    fn parse_class(&mut self) -> StmtKind {
+        self.advance(); // consume `class`
+
+        let name = self.expect_identifier("class name");
+        let type_params = self.parse_type_params();
+        let fields = if let TokenKind::Symbol(Symbol::LParen) = self.current().kind {
+            self.parse_param_list(true)
+        } else {
+            Vec::new()
+        };
+        let public_fields = fields.iter().filter(|(_, public)| *public).map(|(p, _)| p.name.clone()).collect();
+        let params = fields.into_iter().map(|(p, _)| p).collect();
+
+        let parent = if self.matches(&TokenKind::Keyword(Keyword::Extends)) {
+            let name = self.expect_identifier("parent class name");
+            let args = if self.matches(&TokenKind::Symbol(Symbol::LParen)) {
+                self.parse_args()
+            } else {
+                Vec::new()
+            };
+            Some(ParentClass { name, args })
+        } else {
+            None
+        };
+
+        // expect "{"
+        match self.current().kind {
+            TokenKind::Symbol(Symbol::LBrace) => self.advance(),
+            _ => panic!("Expected '{{' to start class body at {}", self.location()),
+        };
+
+        let mut methods = Vec::new();
+        while !self.matches(&TokenKind::Symbol(Symbol::RBrace)) {
+            let public = self.matches(&TokenKind::Keyword(Keyword::Pub));
+
+            // expect `fn`
+            match self.current().kind {
+                TokenKind::Keyword(Keyword::Fn) => self.advance(),
+                _ => panic!("Expected 'fn' method declaration in class {} at {}", name, self.location()),
+            };
+
+            let method = self.expect_identifier("method name");
+            let type_params = self.parse_type_params();
+            let params = self.parse_params();
+            let returns = self.parse_return_type();
+            let body = self.parse_block_expr();
+            methods.push(MethodDecl { name: method, public, type_params, params, returns, body });
+        }
+
+        StmtKind::Class(ClassDecl { name, type_params, params, public_fields, parent, methods })
+    }
+
+    /// `type Name = target;` or `newtype Name = target;`
+    fn parse_type_decl(&mut self, nominal: bool) -> StmtKind {
+        self.advance(); // consume `type` / `newtype`
+
+        let name = self.expect_identifier("type name");
+
+        // expect "="
+        match self.current().kind {
+            TokenKind::Operator(Operator::Assignment) => self.advance(),
+            _ => panic!("Expected '=' after type name at {}", self.location()),
+        };
+
+        let target = self.expect_identifier("type after '='");
+
+        // expect semicolon
+        match self.current().kind {
+            TokenKind::Symbol(Symbol::SemiColon) => self.advance(),
+            _ => panic!("Expected ';' after type declaration at {}", self.location()),
+        };
+
+        StmtKind::TypeDecl { name, target, nominal }
+    }
+
+    fn parse_import(&mut self) -> StmtKind {
+        self.advance(); // consume `import`
+
+        // optional `name from`
+        let alias = if let TokenKind::Identifier(n) = &self.current().kind {
+            let val = n.clone();
+            self.advance();
+
+            match self.current().kind {
+                TokenKind::Keyword(Keyword::From) => self.advance(),
+                _ => panic!("Expected 'from' after import name at {}", self.location()),
+            };
+
+            Some(val)
+        } else {
+            None
+        };
+
+        // expect path string
+        let path = if let TokenKind::Str(p) = &self.current().kind {
+            let val = p.clone();
+            self.advance();
+            val
+        } else {
+            panic!("Expected module path string in import at {}", self.location());
+        };
+
+        // expect semicolon
+        match self.current().kind {
+            TokenKind::Symbol(Symbol::SemiColon) => self.advance(),
+            _ => panic!("Expected ';' after import at {}", self.location()),
+        };
+
+        StmtKind::Import { path, alias }
+    }
+
+    fn parse_print(&mut self, newline: bool) -> StmtKind {
         self.advance(); // consume print or println
 
         // expect "("
         match self.current().kind {
             TokenKind::Symbol(Symbol::LParen) => self.advance(),
-            _ => panic!("Expected '(' after print"),
+            _ => panic!("Expected '(' after print at {}", self.location()),
         };
 
         let expr = self.parse_expression();
@@ -118,7 +771,7 @@ impl Parser {
         // expect ")"
         match self.current().kind {
             TokenKind::Symbol(Symbol::RParen) => self.advance(),
-            _ => panic!("Expected ')' after print expression"),
+            _ => panic!("Expected ')' after print expression at {}", self.location()),
         };
 
         // optional semicolon
@@ -126,100 +779,603 @@ impl Parser {
             self.advance();
         }
 
-        Stmt::Print { newline, expr }
+        StmtKind::Print { newline, expr }
+    }
+
+    fn parse_throw(&mut self) -> StmtKind {
+        self.advance(); // consume `throw`
+
+        let expr = self.parse_expression();
+
+        // expect semicolon
+        match self.current().kind {
+            TokenKind::Symbol(Symbol::SemiColon) => self.advance(),
+            _ => panic!("Expected ';' after throw expression at {}", self.location()),
+        };
+
+        StmtKind::Throw { expr }
+    }
+
+    fn parse_defer(&mut self) -> StmtKind {
+        self.advance(); // consume `defer`
+
+        let body = if let TokenKind::Symbol(Symbol::LBrace) = self.current().kind {
+            self.parse_block()
+        } else {
+            vec![self.parse_nested_statement()]
+        };
+
+        StmtKind::Defer { body }
+    }
+
+    fn parse_for(&mut self) -> StmtKind {
+        self.advance(); // consume `for`
+
+        let pattern = self.parse_pattern();
+
+        // expect `in`
+        if !self.matches(&TokenKind::Operator(Operator::In)) {
+            panic!("Expected 'in' after for-loop pattern at {}", self.location());
+        }
+
+        let iterable = self.parse_expression();
+        let body = self.parse_block();
+
+        StmtKind::For { pattern, iterable, body }
+    }
+
+    fn parse_try(&mut self) -> StmtKind {
+        let line = self.current().line;
+        self.advance(); // consume `try`
+
+        let body = self.parse_block();
+
+        let catch = if self.matches(&TokenKind::Keyword(Keyword::Catch)) {
+            // optional `(name)`
+            let name = if self.matches(&TokenKind::Symbol(Symbol::LParen)) {
+                let name = if let TokenKind::Identifier(n) = &self.current().kind {
+                    let val = n.clone();
+                    self.advance();
+                    val
+                } else {
+                    panic!("Expected identifier in catch clause at {}", self.location());
+                };
+
+                match self.current().kind {
+                    TokenKind::Symbol(Symbol::RParen) => self.advance(),
+                    _ => panic!("Expected ')' after catch variable at {}", self.location()),
+                };
+
+                Some(name)
+            } else {
+                None
+            };
+
+            Some(CatchClause { name, body: self.parse_block() })
+        } else {
+            None
+        };
+
+        let finally = if self.matches(&TokenKind::Keyword(Keyword::Finally)) {
+            Some(self.parse_block())
+        } else {
+            None
+        };
+
+        if catch.is_none() && finally.is_none() {
+            panic!("Expected 'catch' or 'finally' after try block at line {}", line);
+        }
+
+        StmtKind::Try { body, catch, finally }
+    }
+
+    /// A statement inside a block, where interfaces and impls are not allowed
+    fn parse_nested_statement(&mut self) -> StmtId {
+        if let TokenKind::Keyword(Keyword::Interface | Keyword::Impl) = self.current().kind {
+            panic!("Interfaces and impls must be declared at the top level, at {}", self.location());
+        }
+        if let TokenKind::Keyword(Keyword::Pub) = self.current().kind {
+            panic!("'pub' is only allowed on top-level declarations at {}", self.location());
+        }
+        self.parse_statement()
+    }
+
+    /// `if (cond) { ... } else if (cond) { ... } else { ... }`
+    fn parse_if(&mut self) -> ExprId {
+        let start = self.start_span();
+        self.advance(); // consume `if`
+
+        // expect "("
+        match self.current().kind {
+            TokenKind::Symbol(Symbol::LParen) => self.advance(),
+            _ => panic!("Expected '(' after if at {}", self.location()),
+        };
+
+        let condition = self.parse_expression();
+
+        // expect ")"
+        match self.current().kind {
+            TokenKind::Symbol(Symbol::RParen) => self.advance(),
+            _ => panic!("Expected ')' after if condition at {}", self.location()),
+        };
+
+        let then_branch = self.parse_block_expr();
+
+        let else_branch = if self.matches(&TokenKind::Keyword(Keyword::Else)) {
+            if let TokenKind::Keyword(Keyword::If) = self.current().kind {
+                Some(self.parse_if())
+            } else {
+                Some(self.parse_block_expr())
+            }
+        } else {
+            None
+        };
+
+        let kind = ExprKind::If {
+            condition,
+            then_branch,
+            else_branch,
+        };
+        self.expr(kind, self.span_from(start))
+    }
+
+    /// Parse `{ stmt* tail? }` where the optional tail expression is the block's value
+    fn parse_block_expr(&mut self) -> ExprId {
+        let start = self.start_span();
+        let (statements, value) = self.parse_body();
+        self.expr(ExprKind::Block { statements, value }, self.span_from(start))
+    }
+
+    /// Parse `{ stmt* }`
+    fn parse_block(&mut self) -> Vec<StmtId> {
+        // expect "{"
+        match self.current().kind {
+            TokenKind::Symbol(Symbol::LBrace) => self.advance(),
+            _ => panic!("Expected '{{' at {}", self.location()),
+        };
+
+        let mut statements = Vec::new();
+        while !self.is_end() && !matches!(self.current().kind, TokenKind::Symbol(Symbol::RBrace)) {
+            statements.push(self.parse_nested_statement());
+        }
+
+        // expect "}"
+        if !self.matches(&TokenKind::Symbol(Symbol::RBrace)) {
+            panic!("Expected '}}' to close block");
+        }
+
+        statements
     }
 
     // --------------------------
     // EXPRESSIONS
     // --------------------------
 
-    fn parse_expression(&mut self) -> Expr {
-        self.parse_term()
+    fn parse_expression(&mut self) -> ExprId {
+        self.parse_ternary()
+    }
+
+    /// `cond ? a : b`, right-associative
+    fn parse_ternary(&mut self) -> ExprId {
+        let condition = self.parse_comparison();
+
+        if !self.matches(&TokenKind::Symbol(Symbol::Question)) {
+            return condition;
+        }
+
+        let then_expr = self.parse_expression();
+
+        // expect ":"
+        match self.current().kind {
+            TokenKind::Symbol(Symbol::Colon) => self.advance(),
+            _ => panic!("Expected ':' in conditional expression at {}", self.location()),
+        };
+
+        let else_expr = self.parse_ternary();
+
+        let span = self.span_of(condition).to(self.span_of(else_expr));
+        let kind = ExprKind::Ternary {
+            condition,
+            then_expr,
+            else_expr,
+        };
+        self.expr(kind, span)
+    }
+
+    /// `x in collection`, `a == b`, `a < b`, ...
+    fn parse_comparison(&mut self) -> ExprId {
+        let mut expr = self.parse_range();
+
+        while let TokenKind::Operator(op @ (
+            Operator::In | Operator::InstanceOf | Operator::Equal | Operator::NotEqual |
+            Operator::Less | Operator::LessEqual | Operator::Greater | Operator::GreaterEqual
+        )) = &self.current().kind {
+            let op = op.clone();
+            self.advance();
+            let right = self.parse_range();
+
+            let span = self.span_of(expr).to(self.span_of(right));
+            let kind = ExprKind::Binary {
+                left: expr,
+                op,
+                right,
+            };
+            expr = self.expr(kind, span);
+        }
+
+        expr
+    }
+
+    /// `a..b`, `a..=b`, `a..b step s`
+    fn parse_range(&mut self) -> ExprId {
+        let start = self.parse_coalesce();
+
+        let inclusive = if self.matches(&TokenKind::Symbol(Symbol::DotDot)) {
+            false
+        } else if self.matches(&TokenKind::Symbol(Symbol::DotDotEq)) {
+            true
+        } else {
+            return start;
+        };
+
+        let end = self.parse_coalesce();
+
+        // `step` is only special right after a range
+        let step = if self.current().kind == TokenKind::Identifier("step".to_string()) {
+            self.advance();
+            Some(self.parse_coalesce())
+        } else {
+            None
+        };
+
+        let span = self.span_from(self.span_of(start));
+        let kind = ExprKind::Range {
+            start,
+            end,
+            inclusive,
+            step,
+        };
+        self.expr(kind, span)
+    }
+
+    /// `a ?? b` binds looser than arithmetic
+    fn parse_coalesce(&mut self) -> ExprId {
+        let mut expr = self.parse_term();
+
+        while self.matches(&TokenKind::Operator(Operator::Coalesce)) {
+            let right = self.parse_term();
+
+            let span = self.span_of(expr).to(self.span_of(right));
+            let kind = ExprKind::Binary {
+                left: expr,
+                op: Operator::Coalesce,
+                right,
+            };
+            expr = self.expr(kind, span);
+        }
+
+        expr
     }
 
-    fn parse_term(&mut self) -> Expr {
+    fn parse_term(&mut self) -> ExprId {
         let mut expr = self.parse_factor();
 
-        loop {
-            match &self.current().kind {
-                TokenKind::Operator(Operator::Plus) |
-                TokenKind::Operator(Operator::Minus) => {
-                    let op = if let TokenKind::Operator(op) = &self.current().kind {
-                        op.clone()
-                    } else { unreachable!() };
+        while let TokenKind::Operator(op @ (Operator::Plus | Operator::Minus)) = &self.current().kind {
+            let op = op.clone();
+            self.advance();
+            let right = self.parse_factor();
 
-                    self.advance();
-                    let right = self.parse_factor();
+            let span = self.span_of(expr).to(self.span_of(right));
+            let kind = ExprKind::Binary {
+                left: expr,
+                op,
+                right,
+            };
+            expr = self.expr(kind, span);
+        }
 
-                    expr = Expr::Binary {
-                        left: Box::new(expr),
-                        op,
-                        right: Box::new(right),
-                    };
-                }
-                _ => break,
-            }
+        expr
+    }
+
+    fn parse_factor(&mut self) -> ExprId {
+        let mut expr = self.parse_postfix();
+
+        while let TokenKind::Operator(op @ (Operator::Multiply | Operator::Division)) = &self.current().kind {
+            let op = op.clone();
+            self.advance();
+            let right = self.parse_postfix();
+
+            let span = self.span_of(expr).to(self.span_of(right));
+            let kind = ExprKind::Binary {
+                left: expr,
+                op,
+                right,
+            };
+            expr = self.expr(kind, span);
         }
 
         expr
     }
 
-    fn parse_factor(&mut self) -> Expr {
+    fn parse_postfix(&mut self) -> ExprId {
         let mut expr = self.parse_primary();
 
+        // t.0.1 / math.sqrt / maybe?.0 / max(a, b) / result? / s[1..3]
         loop {
-            match &self.current().kind {
-                TokenKind::Operator(Operator::Multiply) |
-                TokenKind::Operator(Operator::Division) => {
-                    let op = if let TokenKind::Operator(op) = &self.current().kind {
-                        op.clone()
-                    } else { unreachable!() };
+            if self.matches(&TokenKind::Symbol(Symbol::LParen)) {
+                let args = self.parse_args();
+                let span = self.span_from(self.span_of(expr));
+                expr = self.expr(ExprKind::Call { callee: expr, args }, span);
+                continue;
+            }
 
-                    self.advance();
-                    let right = self.parse_primary();
+            // `r?` unless it starts a conditional `r ? a : b`
+            if self.current().kind == TokenKind::Symbol(Symbol::Question) && !self.next_starts_expression() {
+                self.advance();
+                let span = self.span_from(self.span_of(expr));
+                expr = self.expr(ExprKind::Propagate(expr), span);
+                continue;
+            }
 
-                    expr = Expr::Binary {
-                        left: Box::new(expr),
-                        op,
-                        right: Box::new(right),
-                    };
-                }
-                _ => break,
+            if self.matches(&TokenKind::Symbol(Symbol::LParen)) {
+                let args = self.parse_args();
+                let span = self.span_from(self.span_of(expr));
+                expr = self.expr(ExprKind::Call { callee: expr, args }, span);
+                continue;
+            }
+
+            if self.matches(&TokenKind::Symbol(Symbol::LBracket)) {
+                let range = self.parse_expression();
+
+                // expect "]"
+                match self.current().kind {
+                    TokenKind::Symbol(Symbol::RBracket) => self.advance(),
+                    _ => panic!("Expected ']' after slice range at {}", self.location()),
+                };
+
+                let span = self.span_from(self.span_of(expr));
+                expr = self.expr(ExprKind::Slice { target: expr, range }, span);
+                continue;
             }
+
+            let optional = if self.matches(&TokenKind::Symbol(Symbol::Dot)) {
+                false
+            } else if self.matches(&TokenKind::Symbol(Symbol::SafeDot)) {
+                true
+            } else {
+                break;
+            };
+
+            let start = self.span_of(expr);
+            let kind = match &self.current().kind {
+                TokenKind::Number(n) => ExprKind::TupleIndex {
+                    tuple: expr,
+                    index: *n as usize,
+                    optional,
+                },
+                TokenKind::Identifier(name) => ExprKind::Member {
+                    object: expr,
+                    name: name.clone(),
+                    optional,
+                },
+                _ => panic!("Expected tuple index or member name after '.' at {}", self.location()),
+            };
+            self.advance();
+            expr = self.expr(kind, self.span_from(start));
         }
 
         expr
     }
 
-    fn parse_primary(&mut self) -> Expr {
+    fn parse_primary(&mut self) -> ExprId {
         let tok = self.current().clone();
+        let start = Span::of(&tok);
 
-        match tok.kind {
+        let kind = match tok.kind {
             TokenKind::Identifier(name) => {
                 self.advance();
-                Expr::Identifier(name)
+                ExprKind::Identifier(name)
             }
 
-            // "hello"
-            TokenKind::Symbol(Symbol::DblQuote) => {
-                self.advance(); // consume first "
+            TokenKind::Number(n) => {
+                self.advance();
+                ExprKind::IntLiteral(n)
+            }
 
-                let mut s = String::new();
-                while let TokenKind::Identifier(ch) = &self.current().kind {
-                    s.push_str(ch);
-                    self.advance();
+            TokenKind::Keyword(Keyword::None) => {
+                self.advance();
+                ExprKind::NoneLiteral
+            }
+
+            TokenKind::Keyword(Keyword::True) => {
+                self.advance();
+                ExprKind::BoolLiteral(true)
+            }
+
+            TokenKind::Keyword(Keyword::False) => {
+                self.advance();
+                ExprKind::BoolLiteral(false)
+            }
+
+            TokenKind::Keyword(Keyword::If) => return self.parse_if(),
+
+            // super.method
+            TokenKind::Keyword(Keyword::Super) => {
+                self.advance();
+
+                // expect "."
+                match self.current().kind {
+                    TokenKind::Symbol(Symbol::Dot) => self.advance(),
+                    _ => panic!("Expected '.' after super at {}", self.location()),
+                };
+
+                let method = self.expect_identifier("method name after 'super.'");
+                ExprKind::Super { method }
+            }
+
+            TokenKind::Symbol(Symbol::LBrace) => return self.parse_block_expr(),
+
+            // ok(x) / err(e) / some(x)
+            TokenKind::Keyword(kw @ (Keyword::Ok | Keyword::Err | Keyword::Some)) => {
+                self.advance();
+
+                // expect "("
+                match self.current().kind {
+                    TokenKind::Symbol(Symbol::LParen) => self.advance(),
+                    _ => panic!("Expected '(' after {:?} at {}", kw, self.location()),
+                };
+
+                let inner = self.parse_expression();
+
+                // expect ")"
+                match self.current().kind {
+                    TokenKind::Symbol(Symbol::RParen) => self.advance(),
+                    _ => panic!("Expected ')' after {:?} value at {}", kw, self.location()),
+                };
+
+                match kw {
+                    Keyword::Ok => ExprKind::Ok(inner),
+                    Keyword::Err => ExprKind::Err(inner),
+                    _ => ExprKind::Some(inner),
+                }
+            }
+
+            // (expr) or (a, b, ...)
+            TokenKind::Symbol(Symbol::LParen) => {
+                self.advance(); // consume "("
+
+                let mut items = Vec::new();
+                let mut trailing_comma = false;
+                while !matches!(self.current().kind, TokenKind::Symbol(Symbol::RParen)) {
+                    items.push(self.parse_expression());
+
+                    trailing_comma = self.matches(&TokenKind::Symbol(Symbol::Comma));
+                    if !trailing_comma {
+                        break;
+                    }
                 }
 
-                // expect closing "
+                // expect ")"
                 match self.current().kind {
-                    TokenKind::Symbol(Symbol::DblQuote) => self.advance(),
-                    _ => panic!("Unclosed string literal"),
+                    TokenKind::Symbol(Symbol::RParen) => self.advance(),
+                    _ => panic!("Expected ')' at {}", self.location()),
+                };
+
+                // `(x)` is just grouping; `(x,)` is a one-element tuple
+                if items.len() == 1 && !trailing_comma {
+                    return items.pop().unwrap();
                 }
+                ExprKind::Tuple(items)
+            }
 
-                Expr::StringLiteral(s)
+            // "hello"
+            TokenKind::Str(s) => {
+                self.advance();
+                ExprKind::StringLiteral(s)
             }
 
-            _ => panic!("Unexpected token {:?} in expression", tok.kind),
-        }
+            _ => panic!("Unexpected token {:?} in expression at {}", tok.kind, self.location()),
+        };
+
+        self.expr(kind, self.span_from(start))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lexer as lex;
+
+    fn parse(source: &str) -> Ast {
+        Parser::new(lex(source)).parse()
+    }
+
+    #[test]
+    fn functions_take_type_parameters_and_a_return_type() {
+        let ast = parse("fn max<T>(T a, T b) -> T { a }");
+        let StmtKind::Function(decl) = &ast[ast.body[0]].kind else {
+            panic!("expected a function, got {:?}", ast[ast.body[0]]);
+        };
+        let t = TypeExpr::Named("T".to_string());
+        assert_eq!(decl.type_params, vec![TypeParam { name: "T".to_string(), bound: None }]);
+        assert_eq!(decl.params, vec![
+            Param { name: "a".to_string(), ty: Some(t.clone()) },
+            Param { name: "b".to_string(), ty: Some(t.clone()) },
+        ]);
+        assert_eq!(decl.returns, Some(t));
+        assert!(decl.body.is_empty());
+        assert!(matches!(decl.value.map(|v| &ast[v].kind), Some(ExprKind::Identifier(n)) if n == "a"));
+    }
+
+    #[test]
+    fn parameter_types_can_be_generic_or_tuples() {
+        let ast = parse("fn f(Pair<int, T> p, (A, B) t, x) { f(p, t, x); }");
+        let StmtKind::Function(decl) = &ast[ast.body[0]].kind else {
+            panic!("expected a function, got {:?}", ast[ast.body[0]]);
+        };
+        let types: Vec<Option<String>> = decl.params.iter()
+            .map(|p| p.ty.as_ref().map(TypeExpr::to_string))
+            .collect();
+        assert_eq!(types, vec![Some("Pair<int, T>".to_string()), Some("(A, B)".to_string()), None]);
+        assert!(decl.value.is_none());
+        assert_eq!(decl.body.len(), 1);
+    }
+
+    #[test]
+    fn a_question_mark_makes_a_type_optional() {
+        let ast = parse("fn f(int? n, (A, B)? t) -> string? { n }");
+        let StmtKind::Function(decl) = &ast[ast.body[0]].kind else {
+            panic!("expected a function, got {:?}", ast[ast.body[0]]);
+        };
+        let int = TypeExpr::Named("int".to_string());
+        assert_eq!(decl.params[0].ty, Some(TypeExpr::Optional(Box::new(int))));
+        assert_eq!(decl.params[1].ty.as_ref().map(TypeExpr::to_string), Some("(A, B)?".to_string()));
+        assert_eq!(decl.returns.as_ref().map(TypeExpr::to_string), Some("string?".to_string()));
+    }
+
+    #[test]
+    fn declarations_may_name_a_type_before_the_pattern() {
+        let ast = parse("let int? x = none; const (int, string) (a, b) = (1, \"s\"); let (c, d) = (1, 2);");
+        let types: Vec<Option<String>> = ast.body.iter()
+            .map(|&s| match &ast[s].kind {
+                StmtKind::VarDeclaration { ty, .. } => ty.as_ref().map(TypeExpr::to_string),
+                other => panic!("expected a declaration, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(types, vec![Some("int?".to_string()), Some("(int, string)".to_string()), None]);
+    }
+
+    #[test]
+    fn spans_cover_a_node_from_its_first_to_its_last_token() {
+        let ast = parse("let x =\n  f(1) + 22;");
+        assert_eq!(ast[ast.body[0]].span.to_string(), "1:1-2:13");
+        let StmtKind::VarDeclaration { value, .. } = &ast[ast.body[0]].kind else {
+            panic!("expected a declaration, got {:?}", ast[ast.body[0]]);
+        };
+        assert_eq!(ast[*value].span.to_string(), "2:3-12");
+    }
+
+    #[test]
+    fn if_statements_take_an_optional_semicolon() {
+        assert_eq!(parse("if (true) { println(1); }").body.len(), 1);
+        assert_eq!(parse("let x = 5; if (x < 10) { print(\"small\"); } else { print(\"big\"); }").body.len(), 2);
+        assert_eq!(parse("if (true) { println(1); }; println(2);").body.len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Expected a declaration after attributes at line 1")]
+    fn attributes_need_a_declaration() {
+        parse("@deprecated defer println(1);");
+    }
+
+    #[test]
+    fn spans_from_a_macro_keep_to_its_definition() {
+        let ast = parse("macro inc!($x) {\n    $x + 1\n}\nlet b = inc!(a);\nlet c = a + 1;");
+        let value = |i: usize| match &ast[ast.body[i]].kind {
+            StmtKind::VarDeclaration { value, .. } => ast[*value].span,
+            other => panic!("expected a let, got {:?}", other),
+        };
+        assert_eq!(value(0).to_string(), "2:10-11, in macro expanded at line 4");
+        assert_eq!(value(1).to_string(), "5:9-14");
     }
 }
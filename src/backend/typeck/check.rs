@@ -0,0 +1,2072 @@
+//! Static type checking of the AST, with types inferred where they are
+//! not written.
+//!
+//! Every expression gets a [`Type`]. Where nothing says what a value is (an
+//! unannotated parameter or field, a function without a declared result,
+//! `none`) it starts as a type variable, and the way it is used solves it
+//! by unification: in `fn f(b) { b + 1 }` `b` is an `int`, so `f("a")` is
+//! an error. A program whose declarations disagree with their annotations,
+//! whose operators get operands they cannot take, or whose calls don't fit
+//! the callee's parameters is rejected before it runs.
+//!
+//! Operators whose operand types are not known yet are kept as pending
+//! constraints, decided once they are. Whatever a class's fields are still
+//! open to once its methods are checked, the class is generic over:
+//! `class Box(v) {}` gives `Box(1)` the type `Box<int>` and `Box("a")` the
+//! type `Box<string>`. A function or method is generic over its type
+//! parameters and the variables only its own signature has, and each call
+//! gets fresh ones.
+//!
+//! Inside its declaration a type parameter stands for whichever type the
+//! caller picks, so it only fits itself, its methods are its bound's and
+//! operators take it as unknown. Callers may pass anything that meets the
+//! bound. An interface named as a type, as in `Shape s`, takes any value
+//! whose type has an `impl` of it.
+//!
+//! An imported module is checked along with the program, so its exported
+//! members have the types it gives them and its classes, functions and
+//! impls work as the program's own do. The modules it imports in turn,
+//! and the like, are `unknown`, which is accepted anywhere and solves
+//! nothing.
+//!
+//! Scopes follow the interpreter's: a function or method body sees its
+//! parameters (and `self`) and the module's globals. Bodies are checked
+//! where they are declared, so a global they use that is declared further
+//! down gets a variable, solved by its declaration, and the impls further
+//! down count as well.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::ast::arena::SideTable;
+use crate::ast::{
+    attributes,
+    none_check, Ast, ClassDecl, Expr, ExprId, ExprKind, FunctionDecl, ImplDecl, InterfaceDecl, Param, Pattern, Span,
+    StmtId, StmtKind, TypeExpr, TypeParam,
+};
+use crate::lexer::Operator;
+use crate::typeck::types::{Named, Type};
+use crate::typeck::unify::Subst;
+
+/// The types inferred for a program.
+pub struct Typed {
+    /// The type of every expression
+    pub exprs: SideTable<Expr, Type>,
+    /// Every name bound by `let`, `const` or `for`, and every function,
+    /// class, field, method, interface, type and import, in the order they
+    /// were checked. Fields and methods are named `Class.name`, and methods
+    /// in an `impl` block `type.name`.
+    pub declarations: Vec<Declaration>,
+}
+
+pub struct Declaration {
+    pub name: String,
+    pub span: Span,
+    pub ty: Type,
+}
+
+/// The types in a program, or everything wrong with it, each with the span
+/// it points at. `load` gives the AST of an import path, if it can be read.
+pub fn check(ast: &Ast, load: &mut dyn FnMut(&str) -> Option<Ast>) -> Result<Typed, Vec<(Span, String)>> {
+    let mut checker = Checker::new(ast, load);
+    checker.stmts(&ast.body);
+    checker.solve();
+
+    // type names in bodies may be declared after them
+    for (name, span) in std::mem::take(&mut checker.later_types) {
+        match checker.scopes[0].get(&name) {
+            Some(Type::Class(_) | Type::Declared(_) | Type::Interface(_) | Type::Unknown) => {}
+            Some(_) => checker.error(span, format!("'{}' is not a type", name)),
+            None => checker.error(span, format!("Unknown type '{}'", name)),
+        }
+    }
+
+    if !checker.errors.is_empty() {
+        // pending operators are decided last; report in source order
+        checker.errors.sort_by_key(|(span, _)| (span.line, span.column));
+        return Err(checker.errors);
+    }
+    let Checker { subst, mut exprs, mut declarations, .. } = checker;
+    for id in ast.expr_ids() {
+        if let Some(ty) = exprs.get_mut(id) {
+            *ty = subst.resolve(ty);
+        }
+    }
+    for declaration in &mut declarations {
+        declaration.ty = subst.resolve(&declaration.ty);
+    }
+    Ok(Typed { exprs, declarations })
+}
+
+/// The parameters and result of the built-in function `name`, which a
+/// call means when nothing declares that name.
+fn builtin(name: &str) -> Option<(Vec<(String, Type)>, Type)> {
+    let param = |name: &str| (name.to_string(), Type::Str);
+    let result = |ok| Type::Result(Box::new(ok), Box::new(Type::Str));
+    match name {
+        "parseInt" => Some((vec![param("text")], result(Type::Int))),
+        "readFile" => Some((vec![param("path")], result(Type::Str))),
+        "writeFile" => Some((vec![param("path"), param("text")], result(Type::Int))),
+        _ => None,
+    }
+}
+
+/// Whether the module declares `name` at its top level.
+fn declares(ast: &Ast, name: &str) -> bool {
+    ast.body.iter().any(|&stmt| attributes::declared_names(ast, stmt).iter().any(|declared| declared == name))
+}
+
+/// Whether `stmt` is marked `pub`, under any attributes.
+fn exported(ast: &Ast, stmt: StmtId) -> bool {
+    match &ast[stmt].kind {
+        StmtKind::Public { .. } => true,
+        StmtKind::Attributed { stmt, .. } => exported(ast, *stmt),
+        _ => false,
+    }
+}
+
+/// The `impl` blocks at the top of the module, which bodies may rely on
+/// however far down they are: `(interface, target, methods)`.
+fn upcoming_impls(ast: &Ast) -> Vec<(String, String, Vec<String>)> {
+    let mut impls = Vec::new();
+    for &stmt in &ast.body {
+        if let StmtKind::Impl(decl) = &ast[attributes::unwrapped(ast, stmt)].kind {
+            let methods = decl.methods.iter().map(|m| m.name.clone()).collect();
+            impls.push((decl.interface.clone(), decl.target.clone(), methods));
+        }
+    }
+    impls
+}
+
+/// What is known about a declared class.
+struct ClassInfo {
+    named: Named,
+    /// Its declared type parameters
+    type_params: Vec<(String, Type)>,
+    /// What it is generic over, in the order of the arguments of its
+    /// `Instance`s: its type parameters, then variables. Empty until its
+    /// methods are checked.
+    params: Vec<Type>,
+    /// Constructor parameters, which become its own fields
+    fields: Vec<(String, Type)>,
+    /// `Instance` of the parent, or `Unknown` when it cannot be known here
+    parent: Option<Type>,
+    methods: HashMap<String, Scheme>,
+}
+
+/// A signature, without `self`. Each use gets fresh copies of `vars`, and
+/// of the `pending` operators on them from the body.
+#[derive(Clone)]
+struct Scheme {
+    /// Type parameters and variables the declaration is generic over
+    vars: Vec<Type>,
+    params: Vec<(String, Type)>,
+    returns: Type,
+    pending: Vec<Pending>,
+}
+
+impl Scheme {
+    fn new(params: Vec<(String, Type)>, returns: Type) -> Scheme {
+        Scheme { vars: Vec::new(), params, returns, pending: Vec::new() }
+    }
+
+    /// As shown by `--show-types`.
+    fn ty(&self) -> Type {
+        Type::Fn(self.params.iter().map(|(_, t)| t.clone()).collect(), Box::new(self.returns.clone()))
+    }
+}
+
+struct InterfaceInfo {
+    methods: HashMap<String, Scheme>,
+}
+
+/// The methods an `impl` block gives a type.
+struct ImplInfo {
+    interface: String,
+    /// The type's name as the interpreter reports it: `int`, `Point`
+    target: String,
+    /// The type of `self`, generic in each method as they are
+    this: Type,
+    methods: HashMap<String, Scheme>,
+}
+
+/// What a `type` or `newtype` declaration wraps.
+struct TypeInfo {
+    target: Type,
+    nominal: bool,
+}
+
+/// A declaration whose signature is being checked against its body.
+enum Owner {
+    Class(usize),
+    Function(usize),
+    Impl(usize),
+}
+
+/// An operator met before the types of its operands were known.
+#[derive(Clone)]
+struct Pending {
+    left: Type,
+    op: Operator,
+    right: Type,
+    result: Type,
+    span: Span,
+    /// The body it is in
+    origin: Option<usize>,
+}
+
+/// Type parameters in scope, or declared by one declaration, by name.
+type TypeParams = Vec<(String, Type)>;
+
+/// The variable a call gives a bounded type parameter, with the bound and
+/// the parameter's name.
+type Bound = (Type, Named, String);
+
+/// A function, method or constructor as one call sees it, with fresh
+/// variables for what it is generic over.
+struct Callee {
+    name: String,
+    params: Vec<(String, Type)>,
+    returns: Type,
+    bounds: Vec<Bound>,
+}
+
+/// The result of looking a member up along a class's ancestors.
+enum Lookup<T> {
+    Found(T),
+    Missing,
+    /// An ancestor is not known here, so it may have it
+    Unknown,
+}
+
+struct Checker<'a> {
+    ast: &'a Ast,
+    /// Names in scope and their types, globals first
+    scopes: Vec<HashMap<String, Type>>,
+    /// Globals used in a body before they are declared
+    forward: HashMap<String, Type>,
+    /// Type names used in a body before they are declared
+    later_types: Vec<(String, Span)>,
+    /// See [`upcoming_impls`]
+    upcoming: Vec<(String, String, Vec<String>)>,
+    /// Whether this is code in a body, which runs after the module
+    in_frame: bool,
+    /// Type parameters of the declarations being checked, innermost last
+    type_params: TypeParams,
+    classes: Vec<ClassInfo>,
+    functions: Vec<Scheme>,
+    interfaces: Vec<InterfaceInfo>,
+    impls: Vec<ImplInfo>,
+    types: Vec<TypeInfo>,
+    /// The exported members of each import, or `None` for one that could
+    /// not be read
+    modules: Vec<Option<HashMap<String, Type>>>,
+    load: &'a mut dyn FnMut(&str) -> Option<Ast>,
+    /// Declarations whose bodies are being checked
+    in_progress: Vec<Owner>,
+    subst: Subst,
+    pending: Vec<Pending>,
+    /// How many bodies have been checked, to number the next
+    bodies: usize,
+    /// The body being checked
+    body: Option<usize>,
+    /// Class and name of the method being checked, for `super`
+    current_method: Option<(usize, String)>,
+    /// An `if` used as a statement, whose branches need not agree
+    discarded: Option<ExprId>,
+    exprs: SideTable<Expr, Type>,
+    declarations: Vec<Declaration>,
+    errors: Vec<(Span, String)>,
+}
+
+impl<'a> Checker<'a> {
+    fn new(ast: &'a Ast, load: &'a mut dyn FnMut(&str) -> Option<Ast>) -> Self {
+        Checker {
+            ast,
+            scopes: vec![HashMap::new()],
+            forward: HashMap::new(),
+            later_types: Vec::new(),
+            upcoming: upcoming_impls(ast),
+            in_frame: false,
+            type_params: Vec::new(),
+            classes: Vec::new(),
+            functions: Vec::new(),
+            interfaces: Vec::new(),
+            impls: Vec::new(),
+            types: Vec::new(),
+            modules: Vec::new(),
+            load,
+            in_progress: Vec::new(),
+            subst: Subst::default(),
+            pending: Vec::new(),
+            bodies: 0,
+            body: None,
+            current_method: None,
+            discarded: None,
+            exprs: SideTable::new(),
+            declarations: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    fn error(&mut self, span: Span, message: String) {
+        self.errors.push((span, message));
+    }
+
+    /// `a` and `b` as far as they are solved, for a message.
+    fn show(&self, a: &Type, b: &Type) -> (String, String) {
+        let (a, b) = (self.subst.resolve(a), self.subst.resolve(b));
+        let mut shown = Type::show_all(&[&a, &b]);
+        let b = shown.pop().expect("two types");
+        (shown.pop().expect("two types"), b)
+    }
+
+    /// Make `actual` fit `expected`, or report `message` made from the two
+    /// of them.
+    fn expect(&mut self, expected: &Type, actual: &Type, span: Span, message: impl FnOnce(String, String) -> String) {
+        if !self.fits(expected, actual) {
+            let (expected, actual) = self.show(expected, actual);
+            self.error(span, message(expected, actual));
+        }
+    }
+
+    fn lookup(&mut self, name: &str) -> Option<Type> {
+        if let Some(ty) = self.scopes.iter().rev().find_map(|scope| scope.get(name)) {
+            return Some(ty.clone());
+        }
+        if !self.in_frame {
+            return None;
+        }
+        // a global declared further down, set by the time this runs
+        let subst = &mut self.subst;
+        Some(self.forward.entry(name.to_string()).or_insert_with(|| subst.fresh()).clone())
+    }
+
+    /// A name in scope right here, as a type or interface is looked up.
+    fn lookup_declared(&self, name: &str) -> Option<Type> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name)).cloned()
+    }
+
+    fn declare(&mut self, name: &str, ty: Type, span: Span) {
+        if !self.in_frame && self.scopes.len() == 1 {
+            if let Some(used) = self.forward.remove(name) {
+                if !self.subst.unify(&used, &ty) {
+                    let (used, ty) = self.show(&used, &ty);
+                    self.error(span, format!("'{}' is declared as '{}' but used as '{}' before", name, ty, used));
+                }
+            }
+        }
+        self.scopes.last_mut().expect("the global scope is never closed").insert(name.to_string(), ty);
+    }
+
+    fn record(&mut self, name: String, span: Span, ty: Type) {
+        self.declarations.push(Declaration { name, span, ty });
+    }
+
+    /// The types of what the module in `ast` exports. Its classes,
+    /// functions, interfaces, impls and types join this program's; its
+    /// errors are left for when it is loaded, and its own imports are not
+    /// followed.
+    fn module(&mut self, ast: &Ast) -> HashMap<String, Type> {
+        let mut unread = |_: &str| None;
+        let mut module = Checker::new(ast, &mut unread);
+        self.swap_tables(&mut module);
+        module.stmts(&ast.body);
+        module.solve();
+        self.swap_tables(&mut module);
+
+        let mut members = HashMap::new();
+        for &stmt in ast.body.iter().filter(|&&stmt| exported(ast, stmt)) {
+            for name in attributes::declared_names(ast, stmt) {
+                let ty = module.scopes[0].get(&name).map_or(Type::Unknown, |ty| self.subst.resolve(ty));
+                members.insert(name, ty);
+            }
+        }
+        members
+    }
+
+    /// Trade what is known about declarations with `other`.
+    fn swap_tables(&mut self, other: &mut Checker) {
+        std::mem::swap(&mut self.classes, &mut other.classes);
+        std::mem::swap(&mut self.functions, &mut other.functions);
+        std::mem::swap(&mut self.interfaces, &mut other.interfaces);
+        std::mem::swap(&mut self.impls, &mut other.impls);
+        std::mem::swap(&mut self.types, &mut other.types);
+        std::mem::swap(&mut self.subst, &mut other.subst);
+        std::mem::swap(&mut self.bodies, &mut other.bodies);
+    }
+
+    /// Run `f` in a new innermost scope.
+    fn scoped<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        self.scopes.push(HashMap::new());
+        let result = f(self);
+        self.scopes.pop();
+        result
+    }
+
+    /// Run `f` with `params` usable as types.
+    fn with_type_params<T>(&mut self, params: &[(String, Type)], f: impl FnOnce(&mut Self) -> T) -> T {
+        let depth = self.type_params.len();
+        self.type_params.extend(params.iter().cloned());
+        let result = f(self);
+        self.type_params.truncate(depth);
+        result
+    }
+
+    /// Check a body with `f`, in the frame it runs in: `frame`, then the
+    /// module's globals and nothing else. Returns the body's number and
+    /// what `f` returns.
+    fn in_body(
+        &mut self,
+        frame: HashMap<String, Type>,
+        type_params: &[(String, Type)],
+        method: Option<(usize, String)>,
+        f: impl FnOnce(&mut Self) -> Type,
+    ) -> (usize, Type) {
+        let body = self.bodies;
+        self.bodies += 1;
+        let outer = self.scopes.split_off(1);
+        let in_frame = std::mem::replace(&mut self.in_frame, true);
+        let outer_body = self.body.replace(body);
+        let outer_method = std::mem::replace(&mut self.current_method, method);
+        self.scopes.push(frame);
+
+        let ty = self.with_type_params(type_params, f);
+
+        self.scopes.pop();
+        self.current_method = outer_method;
+        self.body = outer_body;
+        self.in_frame = in_frame;
+        self.scopes.extend(outer);
+        (body, ty)
+    }
+
+    /// An option nothing has been put in.
+    fn none(&mut self) -> Type {
+        Type::Option(Box::new(self.subst.fresh()))
+    }
+
+    // --------------------------
+    // STATEMENTS
+    // --------------------------
+
+    fn stmts(&mut self, stmts: &'a [StmtId]) {
+        stmts.iter().for_each(|&s| self.stmt(s));
+    }
+
+    fn stmt(&mut self, id: StmtId) {
+        let ast = self.ast;
+        let span = ast[id].span;
+        match &ast[id].kind {
+            StmtKind::Attributed { stmt, .. } | StmtKind::Public { stmt } => self.stmt(*stmt),
+            StmtKind::VarDeclaration { ty, pattern, value, .. } => {
+                let actual = self.expr(*value);
+                let ty = match ty {
+                    Some(annotation) => {
+                        let declared = self.annotation(annotation, span);
+                        self.expect(&declared, &actual, ast[*value].span, |expected, found| {
+                            format!("Expected '{}', found '{}'", expected, found)
+                        });
+                        declared
+                    }
+                    None => actual,
+                };
+                self.bind(pattern, ty, span);
+            }
+            StmtKind::Function(decl) => self.function(decl, span),
+            StmtKind::Interface(decl) => self.interface(decl, span),
+            StmtKind::Impl(decl) => self.impl_block(decl, span),
+            StmtKind::Class(decl) => self.class(decl, span),
+            StmtKind::TypeDecl { name, target, nominal } => {
+                let target = self.annotation(&TypeExpr::Named(target.clone()), span);
+                let named = Named { id: self.types.len(), name: name.clone() };
+                self.types.push(TypeInfo { target, nominal: *nominal });
+                self.declare(name, Type::Declared(named.clone()), span);
+                self.record(name.clone(), span, Type::Declared(named));
+            }
+            StmtKind::Import { path, alias } => {
+                // `import "lib/math.sst"` is bound as `math`
+                let stem = || Path::new(path).file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+                let name = alias.clone().unwrap_or_else(stem);
+                let members = (self.load)(path).map(|module| self.module(&module));
+                let module = Type::Module(Named { id: self.modules.len(), name: name.clone() });
+                self.modules.push(members);
+                self.declare(&name, module.clone(), span);
+                self.record(name, span, module);
+            }
+            StmtKind::Expression { expr } => {
+                if matches!(ast[*expr].kind, ExprKind::If { .. }) {
+                    self.discarded = Some(*expr);
+                }
+                self.expr(*expr);
+            }
+            StmtKind::Print { expr, .. } | StmtKind::Throw { expr } => {
+                self.expr(*expr);
+            }
+            StmtKind::Defer { body } => self.scoped(|c| c.stmts(body)),
+            StmtKind::For { pattern, iterable, body } => {
+                let iterable_ty = self.expr(*iterable);
+                let item = match self.subst.shallow(&iterable_ty) {
+                    Type::Range => Type::Int,
+                    Type::Str => Type::Str,
+                    Type::Tuple(items) => items.iter().skip(1).fold(
+                        items.first().cloned().unwrap_or(Type::Unknown),
+                        // a mixed tuple's items are only known one at a time
+                        |all, item| self.join(&all, item).unwrap_or(Type::Unknown),
+                    ),
+                    // a variable could still be any of them
+                    Type::Unknown | Type::Var(_) => Type::Unknown,
+                    other => {
+                        let other = self.subst.resolve(&other).show();
+                        self.error(ast[*iterable].span, format!("Cannot iterate over '{}'", other));
+                        Type::Unknown
+                    }
+                };
+                self.scoped(|c| {
+                    c.bind(pattern, item, span);
+                    c.stmts(body);
+                });
+            }
+            StmtKind::Try { body, catch, finally } => {
+                self.scoped(|c| c.stmts(body));
+                if let Some(clause) = catch {
+                    self.scoped(|c| {
+                        if let Some(name) = &clause.name {
+                            c.declare(name, Type::Error, span);
+                        }
+                        c.stmts(&clause.body);
+                    });
+                }
+                if let Some(finally) = finally {
+                    self.scoped(|c| c.stmts(finally));
+                }
+            }
+        }
+    }
+
+    /// Declare the names in `pattern` with their part of `ty`.
+    fn bind(&mut self, pattern: &Pattern, ty: Type, span: Span) {
+        match (pattern, self.subst.shallow(&ty)) {
+            (Pattern::Identifier(name), _) => {
+                self.record(name.clone(), span, ty.clone());
+                self.declare(name, ty, span);
+            }
+            (Pattern::Tuple(patterns), Type::Tuple(items)) if patterns.len() == items.len() => {
+                for (p, item) in patterns.iter().zip(items) {
+                    self.bind(p, item, span);
+                }
+            }
+            // destructuring is what says it is a tuple
+            (Pattern::Tuple(patterns), Type::Var(_)) => {
+                let items: Vec<Type> = patterns.iter().map(|_| self.subst.fresh()).collect();
+                self.subst.unify(&ty, &Type::Tuple(items.clone()));
+                for (p, item) in patterns.iter().zip(items) {
+                    self.bind(p, item, span);
+                }
+            }
+            (Pattern::Tuple(patterns), ty) => {
+                match ty {
+                    Type::Tuple(items) => self.error(span, format!(
+                        "Cannot destructure a tuple of {} elements into {} names", items.len(), patterns.len()
+                    )),
+                    Type::Unknown => {}
+                    other => {
+                        let other = self.subst.resolve(&other).show();
+                        self.error(span, format!("Cannot destructure non-tuple type '{}'", other));
+                    }
+                }
+                for p in patterns {
+                    self.bind(p, Type::Unknown, span);
+                }
+            }
+        }
+    }
+
+    /// The type an annotation stands for.
+    fn annotation(&mut self, ty: &TypeExpr, span: Span) -> Type {
+        match ty {
+            TypeExpr::Named(name) => self.named_type(name, Vec::new(), span),
+            TypeExpr::Generic(name, args) => {
+                let args = args.iter().map(|t| self.annotation(t, span)).collect();
+                self.named_type(name, args, span)
+            }
+            TypeExpr::Tuple(items) => Type::Tuple(items.iter().map(|t| self.annotation(t, span)).collect()),
+            TypeExpr::Optional(inner) => Type::Option(Box::new(self.annotation(inner, span))),
+        }
+    }
+
+    /// The type `name<args>` stands for. Type parameters come first, then
+    /// built-in names, as they do at runtime, then declarations in scope.
+    /// Type arguments left out are inferred.
+    fn named_type(&mut self, name: &str, args: Vec<Type>, span: Span) -> Type {
+        if let Some((_, param)) = self.type_params.iter().rev().find(|(param, _)| param == name) {
+            return param.clone();
+        }
+        let declared = self.lookup_declared(name);
+        let expected = match (name, &declared) {
+            ("int" | "string" | "bool", _) => 0,
+            ("Option", _) => 1,
+            ("Result", _) => 2,
+            (_, Some(Type::Class(class))) => self.classes[class.id].type_params.len(),
+            _ => 0,
+        };
+        if args.len() != expected && !(expected > 0 && args.is_empty()) {
+            self.error(span, format!("'{}' takes {} type argument(s) but {} were given", name, expected, args.len()));
+        }
+        let mut arg = |i: usize| Box::new(args.get(i).cloned().unwrap_or_else(|| self.subst.fresh()));
+
+        match name {
+            "int" => return Type::Int,
+            "string" => return Type::Str,
+            "bool" => return Type::Bool,
+            "Option" => return Type::Option(arg(0)),
+            "Result" => return Type::Result(arg(0), arg(1)),
+            _ => {}
+        }
+        match declared {
+            Some(Type::Class(class)) => {
+                let (instance, _, _) = self.instance_of(&class);
+                if let Type::Instance(_, params) = &instance {
+                    for (param, arg) in params.iter().zip(&args) {
+                        self.subst.unify(param, arg);
+                    }
+                }
+                instance
+            }
+            Some(Type::Declared(def)) if self.types[def.id].nominal => Type::Newtype(def),
+            Some(Type::Declared(def)) => Type::Alias(def.clone(), Box::new(self.types[def.id].target.clone())),
+            Some(Type::Interface(interface)) => Type::Dyn(interface),
+            Some(Type::Unknown) => Type::Unknown,
+            Some(_) => {
+                self.error(span, format!("'{}' is not a type", name));
+                Type::Unknown
+            }
+            None if self.in_frame => {
+                self.later_types.push((name.to_string(), span));
+                Type::Unknown
+            }
+            None => {
+                self.error(span, format!("Unknown type '{}'", name));
+                Type::Unknown
+            }
+        }
+    }
+
+    /// The type of something with an optional annotation: what it says, or
+    /// a variable for inference to solve.
+    fn declared(&mut self, ty: &Option<TypeExpr>, span: Span) -> Type {
+        match ty {
+            Some(ty) => self.annotation(ty, span),
+            None => self.subst.fresh(),
+        }
+    }
+
+    fn signature(&mut self, params: &[Param], returns: &Option<TypeExpr>, span: Span) -> Scheme {
+        let params = params.iter().map(|p| (p.name.clone(), self.declared(&p.ty, span))).collect();
+        let returns = self.declared(returns, span);
+        Scheme::new(params, returns)
+    }
+
+    /// `<T, U: Shape>` as types for the declaration's own annotations.
+    fn type_param_types(&mut self, params: &[TypeParam], span: Span) -> Vec<(String, Type)> {
+        params.iter()
+            .map(|p| {
+                let bound = p.bound.as_ref().and_then(|bound| self.interface_named(bound, span));
+                (p.name.clone(), Type::Param(p.name.clone(), bound))
+            })
+            .collect()
+    }
+
+    /// The interface a bound or `impl` names.
+    fn interface_named(&mut self, name: &str, span: Span) -> Option<Named> {
+        match self.lookup_declared(name) {
+            Some(Type::Interface(interface)) => Some(interface),
+            Some(Type::Unknown) => None,
+            Some(_) => {
+                self.error(span, format!("'{}' is not an interface", name));
+                None
+            }
+            None => {
+                self.error(span, format!("Unknown interface '{}'", name));
+                None
+            }
+        }
+    }
+
+    /// Check a function where it is declared, then work out what it is
+    /// generic over.
+    fn function(&mut self, decl: &'a FunctionDecl, span: Span) {
+        let id = self.functions.len();
+        let named = Named { id, name: decl.name.clone() };
+        let type_params = self.type_param_types(&decl.type_params, span);
+        let signature = self.with_type_params(&type_params, |c| c.signature(&decl.params, &decl.returns, span));
+        let frame = signature.params.iter().cloned().collect();
+        let returns = signature.returns.clone();
+        self.functions.push(signature);
+        // declared first, so it can call itself
+        self.declare(&decl.name, Type::Function(named), span);
+
+        self.in_progress.push(Owner::Function(id));
+        let (body, actual) = self.in_body(frame, &type_params, None, |c| {
+            c.stmts(&decl.body);
+            match decl.value {
+                Some(value) => c.expr(value),
+                None => c.none(),
+            }
+        });
+        let value_span = decl.value.map_or(span, |value| self.ast[value].span);
+        self.expect(&returns, &actual, value_span, |expected, found| {
+            format!("'{}' should return '{}', found '{}'", decl.name, expected, found)
+        });
+        self.in_progress.pop();
+
+        self.solve();
+        let outside = self.outside();
+        let signature = std::mem::replace(&mut self.functions[id], Scheme::new(Vec::new(), Type::Unknown));
+        let scheme = self.generalize(signature, None, &type_params, body, &outside, None);
+        self.record(decl.name.clone(), span, scheme.ty());
+        self.functions[id] = scheme;
+    }
+
+    fn interface(&mut self, decl: &InterfaceDecl, span: Span) {
+        let named = Named { id: self.interfaces.len(), name: decl.name.clone() };
+        // what a signature leaves out is up to each impl
+        let or_unknown = |c: &mut Self, ty: &Option<TypeExpr>| ty.as_ref().map_or(Type::Unknown, |t| c.annotation(t, span));
+        let mut methods = HashMap::new();
+        for method in &decl.methods {
+            let params = method.params[1..].iter().map(|p| (p.name.clone(), or_unknown(self, &p.ty))).collect();
+            let returns = or_unknown(self, &method.returns);
+            methods.insert(method.name.clone(), Scheme::new(params, returns));
+        }
+        self.interfaces.push(InterfaceInfo { methods });
+        self.declare(&decl.name, Type::Interface(named.clone()), span);
+        self.record(decl.name.clone(), span, Type::Interface(named));
+    }
+
+    /// Check the methods an `impl` block adds to its type, with `self` as a
+    /// value of that type.
+    fn impl_block(&mut self, decl: &'a ImplDecl, span: Span) {
+        self.interface_named(&decl.interface, span);
+        let this = match decl.target.as_str() {
+            "int" => Type::Int,
+            "string" => Type::Str,
+            "bool" => Type::Bool,
+            "range" => Type::Range,
+            "error" => Type::Error,
+            name => match self.lookup_declared(name) {
+                Some(Type::Class(class)) => self.instance_of(&class).0,
+                Some(Type::Declared(def)) if self.types[def.id].nominal => Type::Newtype(def),
+                _ => Type::Unknown,
+            },
+        };
+
+        let id = self.impls.len();
+        let mut methods = HashMap::new();
+        let mut type_params = Vec::new();
+        for method in &decl.methods {
+            let params = self.type_param_types(&method.type_params, span);
+            let signature = self.with_type_params(&params, |c| c.signature(&method.params[1..], &method.returns, span));
+            methods.insert(method.name.clone(), signature);
+            type_params.push(params);
+        }
+        self.impls.push(ImplInfo { interface: decl.interface.clone(), target: decl.target.clone(), this, methods });
+
+        self.in_progress.push(Owner::Impl(id));
+        let mut bodies = Vec::new();
+        for (method, type_params) in decl.methods.iter().zip(&type_params) {
+            let info = &self.impls[id];
+            let signature = &info.methods[&method.name];
+            let mut frame: HashMap<String, Type> = signature.params.iter().cloned().collect();
+            frame.insert("self".to_string(), info.this.clone());
+            let returns = signature.returns.clone();
+
+            let (body, actual) = self.in_body(frame, type_params, None, |c| {
+                c.stmts(&method.body);
+                match method.value {
+                    Some(value) => c.expr(value),
+                    None => c.none(),
+                }
+            });
+            let value_span = method.value.map_or(span, |value| self.ast[value].span);
+            self.expect(&returns, &actual, value_span, |expected, found| {
+                format!("'{}.{}' should return '{}', found '{}'", decl.target, method.name, expected, found)
+            });
+            bodies.push(body);
+        }
+        self.in_progress.pop();
+
+        self.solve();
+        let outside = self.outside();
+        let this = self.subst.resolve(&self.impls[id].this);
+        for ((method, type_params), body) in decl.methods.iter().zip(&type_params).zip(bodies) {
+            let signature = self.impls[id].methods.remove(&method.name).expect("registered above");
+            let scheme = self.generalize(signature, Some(&this), type_params, body, &outside, None);
+            self.record(format!("{}.{}", decl.target, method.name), span, scheme.ty());
+            self.impls[id].methods.insert(method.name.clone(), scheme);
+        }
+        self.impls[id].this = this;
+    }
+
+    /// Check a class where it is declared, then work out what it and its
+    /// methods are generic over.
+    fn class(&mut self, decl: &'a ClassDecl, span: Span) {
+        let id = self.classes.len();
+        let named = Named { id, name: decl.name.clone() };
+        let parent = match &decl.parent {
+            Some(parent) => match self.lookup(&parent.name) {
+                Some(Type::Class(class)) => Some(class),
+                None | Some(Type::Unknown | Type::Var(_)) => None,
+                Some(_) => {
+                    self.error(span, format!("Cannot extend '{}': not a class", parent.name));
+                    None
+                }
+            },
+            None => None,
+        };
+        let type_params = self.type_param_types(&decl.type_params, span);
+        // declared first, so its own methods and fields can name it
+        self.classes.push(ClassInfo {
+            named: named.clone(),
+            type_params: type_params.clone(),
+            params: Vec::new(),
+            fields: Vec::new(),
+            parent: None,
+            methods: HashMap::new(),
+        });
+        self.in_progress.push(Owner::Class(id));
+        self.declare(&decl.name, Type::Class(named.clone()), span);
+        self.record(decl.name.clone(), span, Type::Class(named.clone()));
+
+        let fields = self.with_type_params(&type_params, |c| c.signature(&decl.params, &None, span).params);
+        for (name, ty) in &fields {
+            self.record(format!("{}.{}", decl.name, name), span, ty.clone());
+        }
+        self.classes[id].fields = fields;
+        let mut method_params = Vec::new();
+        for method in &decl.methods {
+            let own = self.type_param_types(&method.type_params, span);
+            let all: Vec<(String, Type)> = type_params.iter().chain(&own).cloned().collect();
+            let signature = self.with_type_params(&all, |c| c.signature(&method.params, &method.returns, span));
+            self.classes[id].methods.insert(method.name.clone(), signature);
+            method_params.push((all, own));
+        }
+
+        // the code inside runs later, in frames that see the module's globals and nothing else
+        if let Some(extends) = &decl.parent {
+            let frame = self.classes[id].fields.iter().cloned().collect();
+            let (_, parent) = self.in_body(frame, &type_params, None, |c| match &parent {
+                Some(class) => {
+                    let (instance, map, bounds) = c.instance_of(class);
+                    let params = c.fields(class.id, &map);
+                    let callee = Callee { name: class.name.clone(), params, returns: instance, bounds };
+                    c.apply(callee, &extends.args, span)
+                }
+                None => {
+                    c.args_unchecked(&extends.args);
+                    Type::Unknown
+                }
+            });
+            self.classes[id].parent = Some(parent);
+        }
+        let mut bodies = Vec::new();
+        for (method, (all, _)) in decl.methods.iter().zip(&method_params) {
+            let signature = &self.classes[id].methods[&method.name];
+            let mut frame: HashMap<String, Type> = signature.params.iter().cloned().collect();
+            frame.insert("self".to_string(), Type::Instance(named.clone(), Vec::new()));
+            let returns = signature.returns.clone();
+
+            let (body, actual) = self.in_body(frame, all, Some((id, method.name.clone())), |c| c.expr(method.body));
+            let value_span = match &self.ast[method.body].kind {
+                ExprKind::Block { value: Some(value), .. } => self.ast[*value].span,
+                _ => self.ast[method.body].span,
+            };
+            self.expect(&returns, &actual, value_span, |expected, found| {
+                format!("'{}.{}' should return '{}', found '{}'", decl.name, method.name, expected, found)
+            });
+            bodies.push(body);
+        }
+        self.in_progress.pop();
+
+        self.generalize_class(id, decl, &method_params, &bodies);
+        for method in &decl.methods {
+            let ty = self.classes[id].methods[&method.name].ty();
+            self.record(format!("{}.{}", decl.name, method.name), span, ty);
+        }
+    }
+
+    /// Every variable that something outside the declarations just checked
+    /// can still solve.
+    fn outside(&self) -> Vec<u32> {
+        let mut outside = Vec::new();
+        let mut add = |ty: &Type| self.subst.resolve(ty).vars(&mut outside);
+        for ty in self.scopes.iter().flat_map(|scope| scope.values()).chain(self.forward.values()) {
+            add(ty);
+        }
+        let signatures = |scheme: &'_ Scheme| scheme.params.iter().map(|(_, t)| t.clone()).chain([scheme.returns.clone()]).collect::<Vec<_>>();
+        for owner in &self.in_progress {
+            let types: Vec<Type> = match owner {
+                Owner::Function(id) => signatures(&self.functions[*id]),
+                Owner::Impl(id) => {
+                    let info = &self.impls[*id];
+                    info.methods.values().flat_map(signatures).chain([info.this.clone()]).collect()
+                }
+                Owner::Class(id) => {
+                    let info = &self.classes[*id];
+                    info.fields.iter().map(|(_, t)| t.clone())
+                        .chain(info.parent.clone())
+                        .chain(info.methods.values().flat_map(signatures))
+                        .collect()
+                }
+            };
+            types.iter().for_each(&mut add);
+        }
+        outside
+    }
+
+    /// `signature`, checked in `body`, made generic over the type
+    /// parameters it declares and the variables in it (and in `this`) that
+    /// nothing outside can solve. Operators in the body on those, or on
+    /// the `class_params` of the class it is in, are checked again for
+    /// each use. `own` is a class and the type of its `self`.
+    fn generalize(
+        &mut self,
+        signature: Scheme,
+        this: Option<&Type>,
+        type_params: &[(String, Type)],
+        body: usize,
+        outside: &[u32],
+        own: Option<(usize, &Type, &[u32])>,
+    ) -> Scheme {
+        let class_params = own.map_or(&[][..], |(_, _, params)| params);
+        let params: Vec<(String, Type)> = signature.params.iter().map(|(n, t)| (n.clone(), self.owned(t, own))).collect();
+        let returns = self.owned(&signature.returns, own);
+        let (mine, rest): (Vec<Pending>, Vec<Pending>) =
+            std::mem::take(&mut self.pending).into_iter().partition(|p| p.origin == Some(body));
+        self.pending = rest;
+
+        let mut vars = Vec::new();
+        params.iter().map(|(_, t)| t).chain([&returns]).chain(this).for_each(|t| self.subst.resolve(t).vars(&mut vars));
+        for p in &mine {
+            [&p.left, &p.right, &p.result].iter().for_each(|t| self.subst.resolve(t).vars(&mut vars));
+        }
+        vars.retain(|v| !outside.contains(v) && !class_params.contains(v));
+
+        // operators on what each use solves afresh are checked for each
+        let mentions = |p: &Pending| [&p.left, &p.right, &p.result].iter().any(|t| {
+            let mut in_it = Vec::new();
+            self.subst.resolve(t).vars(&mut in_it);
+            in_it.iter().any(|v| vars.contains(v) || class_params.contains(v))
+        });
+        let (generic, mine): (Vec<Pending>, Vec<Pending>) = mine.into_iter().partition(mentions);
+        self.pending.extend(mine);
+        let pending = generic.into_iter()
+            .map(|p| Pending {
+                left: self.owned(&p.left, own),
+                right: self.owned(&p.right, own),
+                result: self.owned(&p.result, own),
+                ..p
+            })
+            .collect();
+
+        let vars = type_params.iter().map(|(_, t)| t.clone()).chain(vars.into_iter().map(Type::Var)).collect();
+        Scheme { vars, params, returns, pending }
+    }
+
+    /// `ty` solved as far as it is, with `self` and the instances built in
+    /// the class `own` names standing for its own parameters.
+    fn owned(&self, ty: &Type, own: Option<(usize, &Type, &[u32])>) -> Type {
+        let ty = self.subst.resolve(ty);
+        match own {
+            Some((class, this, _)) => ty.map(&|t| match t {
+                Type::Instance(c, args) if c.id == class && args.is_empty() => Some(this.clone()),
+                _ => None,
+            }),
+            None => ty,
+        }
+    }
+
+    /// Work out what a checked class and each of its methods are generic
+    /// over: its type parameters, and the variables in it still unsolved
+    /// that nothing outside can solve any more.
+    fn generalize_class(
+        &mut self,
+        class: usize,
+        decl: &ClassDecl,
+        method_params: &[(TypeParams, TypeParams)],
+        bodies: &[usize],
+    ) {
+        self.solve();
+        let outside = self.outside();
+        let info = &self.classes[class];
+        let mut vars = Vec::new();
+        for ty in info.fields.iter().map(|(_, t)| t).chain(info.parent.as_ref()) {
+            self.subst.resolve(ty).vars(&mut vars);
+        }
+        vars.retain(|v| !outside.contains(v));
+        let params: Vec<Type> = info.type_params.iter().map(|(_, t)| t.clone())
+            .chain(vars.iter().map(|&v| Type::Var(v)))
+            .collect();
+
+        // `self` and instances built inside the class stand for its own parameters
+        let this = Type::Instance(info.named.clone(), params.clone());
+        let own = Some((class, &this, &vars[..]));
+        let fields = info.fields.iter().map(|(name, ty)| (name.clone(), self.owned(ty, own))).collect();
+        let parent = info.parent.as_ref().map(|ty| self.owned(ty, own));
+        for (method, ((_, type_params), &body)) in decl.methods.iter().zip(method_params.iter().zip(bodies)) {
+            let signature = self.classes[class].methods.remove(&method.name).expect("registered when declared");
+            let scheme = self.generalize(signature, None, type_params, body, &outside, own);
+            self.classes[class].methods.insert(method.name.clone(), scheme);
+        }
+
+        let info = &mut self.classes[class];
+        info.params = params;
+        info.fields = fields;
+        info.parent = parent;
+    }
+
+    // --------------------------
+    // TYPES
+    // --------------------------
+
+    /// Whether a value of type `actual` may be used where `expected` is,
+    /// solving what it takes for it to be.
+    fn fits(&mut self, expected: &Type, actual: &Type) -> bool {
+        let (expected, actual) = (self.subst.shallow(expected), self.subst.shallow(actual));
+        match (&expected, &actual) {
+            // nothing says which types a variable takes yet
+            (Type::Dyn(_), Type::Var(_)) => true,
+            (Type::Dyn(interface), actual) => self.implements(actual, interface),
+            (Type::Tuple(a), Type::Tuple(b)) if a.len() == b.len() => a.iter().zip(b).all(|(a, b)| self.fits(a, b)),
+            (Type::Option(a), Type::Option(b)) => self.fits(a, b),
+            // a value that is there fits where it may be missing
+            (Type::Option(a), b) if !matches!(b, Type::Var(_) | Type::Unknown) => self.fits(a, b),
+            (Type::Result(a, e), Type::Result(b, f)) => self.fits(a, b) && self.fits(e, f),
+            (a, b) => self.subst.unify(a, b),
+        }
+    }
+
+    /// Whether values of type `ty` have an `impl` of `interface`.
+    /// Instances also have their ancestors' impls.
+    fn implements(&self, ty: &Type, interface: &Named) -> bool {
+        let has = |target: &str| {
+            self.impls.iter().any(|i| i.target == target && i.interface == interface.name)
+                || self.in_frame && self.upcoming.iter().any(|(i, t, _)| t == target && *i == interface.name)
+        };
+        match self.subst.shallow(ty) {
+            Type::Unknown | Type::Var(_) => true,
+            Type::Dyn(bound) | Type::Param(_, Some(bound)) => bound == *interface,
+            Type::Instance(class, _) => self.lineage(class.id).iter().any(|name| name.as_ref().is_none_or(|n| has(n))),
+            // `some(x)` is an "option" and `none` a "none", so either may do
+            Type::Option(_) => has("option") || has("none"),
+            other => other.impl_name().is_some_and(has),
+        }
+    }
+
+    /// The names of `class` and its ancestors, ending in `None` if one of
+    /// them is not known here.
+    fn lineage(&self, class: usize) -> Vec<Option<&str>> {
+        let mut names = Vec::new();
+        let mut info = &self.classes[class];
+        loop {
+            names.push(Some(info.named.name.as_str()));
+            info = match &info.parent {
+                None => return names,
+                Some(Type::Instance(parent, _)) => &self.classes[parent.id],
+                Some(_) => {
+                    names.push(None);
+                    return names;
+                }
+            };
+        }
+    }
+
+    /// The type of an `if` or `?:` whose branches are `then` and `otherwise`,
+    /// or an error if they disagree.
+    fn branches(&mut self, then: &Type, otherwise: &Type, span: Span) -> Type {
+        let shown = self.show(then, otherwise);
+        self.join(then, otherwise).unwrap_or_else(|| {
+            self.error(span, format!("Branches have different types '{}' and '{}'", shown.0, shown.1));
+            Type::Unknown
+        })
+    }
+
+    /// The type of a value that is one of `a` and `b`: the two unified,
+    /// with `unknown` in either side taking the other's part. None if they
+    /// differ.
+    fn join(&mut self, a: &Type, b: &Type) -> Option<Type> {
+        let (a, b) = (self.subst.shallow(a), self.subst.shallow(b));
+        match (&a, &b) {
+            (Type::Var(_), _) | (_, Type::Var(_)) => self.subst.unify(&a, &b).then_some(a),
+            (Type::Unknown, other) | (other, Type::Unknown) => Some(other.clone()),
+            (Type::Tuple(x), Type::Tuple(y)) if x.len() == y.len() => {
+                x.iter().zip(y).map(|(x, y)| self.join(x, y)).collect::<Option<_>>().map(Type::Tuple)
+            }
+            (Type::Option(x), Type::Option(y)) => Some(Type::Option(Box::new(self.join(x, y)?))),
+            // a value on one side and maybe none on the other
+            (Type::Option(x), other) | (other, Type::Option(x)) => Some(Type::Option(Box::new(self.join(x, other)?))),
+            (Type::Result(x, e), Type::Result(y, f)) => {
+                Some(Type::Result(Box::new(self.join(x, y)?), Box::new(self.join(e, f)?)))
+            }
+            (Type::Instance(c, _), Type::Instance(d, _)) if c == d => self.subst.unify(&a, &b).then_some(a),
+            _ => (a == b).then_some(a),
+        }
+    }
+
+    /// For `?.`: the type inside an option, and whether the access may give
+    /// `none` instead. `some(none)` is looked through to a `none`, which
+    /// `?.` stops at too.
+    fn look_through(&self, target: Type, optional: bool) -> (Type, bool) {
+        match (self.subst.shallow(&target), optional) {
+            (Type::Option(inner), true) => match self.subst.shallow(&inner) {
+                Type::Option(none) if matches!(self.subst.shallow(&none), Type::Var(_)) => (Type::Unknown, true),
+                inner => (inner, true),
+            },
+            (Type::Unknown, true) => (Type::Unknown, true),
+            (target, _) => (target, false),
+        }
+    }
+
+    // --------------------------
+    // GENERICS
+    // --------------------------
+
+    /// Fresh variables for `vars` in `map`, each bounded type parameter's
+    /// with its bound in `bounds`.
+    fn instantiate(&mut self, vars: &[Type], map: &mut Vec<(Type, Type)>, bounds: &mut Vec<Bound>) {
+        for var in vars {
+            let fresh = self.subst.fresh();
+            if let Type::Param(name, Some(bound)) = var {
+                bounds.push((fresh.clone(), bound.clone(), name.clone()));
+            }
+            map.push((var.clone(), fresh));
+        }
+    }
+
+    /// `ty` with the type parameters and variables `map` has replaced.
+    fn substitute(&self, ty: &Type, map: &[(Type, Type)]) -> Type {
+        if map.is_empty() {
+            return ty.clone();
+        }
+        self.subst.resolve(ty).map(&|t| match t {
+            Type::Var(_) | Type::Param(..) => map.iter().rev().find(|(from, _)| from == t).map(|(_, to)| to.clone()),
+            _ => None,
+        })
+    }
+
+    /// A new instance of `class` with fresh variables for what it is
+    /// generic over, what those stand in for, and the bounds on them.
+    fn instance_of(&mut self, class: &Named) -> (Type, Vec<(Type, Type)>, Vec<Bound>) {
+        let params = self.classes[class.id].params.clone();
+        let (mut map, mut bounds) = (Vec::new(), Vec::new());
+        self.instantiate(&params, &mut map, &mut bounds);
+        let args = map.iter().map(|(_, arg)| arg.clone()).collect();
+        (Type::Instance(class.clone(), args), map, bounds)
+    }
+
+    /// The fields of `class` for an instance whose parameters `map` gives.
+    fn fields(&self, class: usize, map: &[(Type, Type)]) -> Vec<(String, Type)> {
+        self.classes[class].fields.iter().map(|(name, ty)| (name.clone(), self.substitute(ty, map))).collect()
+    }
+
+    /// Look something up on an instance of `class` with `args`, then on its
+    /// ancestors. `find` gets what the class's parameters stand for there.
+    /// Inside the class itself, where `args` is empty, they stand for
+    /// themselves.
+    fn find<T>(&self, class: usize, args: &[Type], find: impl Fn(&ClassInfo, &[(Type, Type)]) -> Option<T>) -> Lookup<T> {
+        let (mut class, mut args) = (class, args.to_vec());
+        loop {
+            let info = &self.classes[class];
+            let map: Vec<(Type, Type)> = info.params.iter().cloned().zip(args.iter().cloned()).collect();
+            if let Some(found) = find(info, &map) {
+                return Lookup::Found(found);
+            }
+            match info.parent.as_ref().map(|parent| self.substitute(parent, &map)) {
+                None => return Lookup::Missing,
+                Some(Type::Instance(parent, parent_args)) => (class, args) = (parent.id, parent_args),
+                Some(_) => return Lookup::Unknown,
+            }
+        }
+    }
+
+    /// `scheme` as a call to `name` sees it, with fresh variables for what
+    /// it is generic over besides what `map` already gives. The operators
+    /// left pending on those are checked for this call.
+    fn callee(&mut self, name: String, scheme: &Scheme, mut map: Vec<(Type, Type)>, span: Span) -> Callee {
+        let mut bounds = Vec::new();
+        self.instantiate(&scheme.vars, &mut map, &mut bounds);
+        let params = scheme.params.iter().map(|(n, t)| (n.clone(), self.substitute(t, &map))).collect();
+        let returns = self.substitute(&scheme.returns, &map);
+        for p in &scheme.pending {
+            let p = Pending {
+                left: self.substitute(&p.left, &map),
+                op: p.op.clone(),
+                right: self.substitute(&p.right, &map),
+                result: self.substitute(&p.result, &map),
+                span,
+                origin: self.body,
+            };
+            self.operator(p);
+        }
+        Callee { name, params, returns, bounds }
+    }
+
+    /// The method `name` of an `impl` for the type `target` names, called
+    /// on `receiver`.
+    fn impl_method(&mut self, target: &str, receiver: &Type, name: &str, span: Span) -> Option<Callee> {
+        let i = self.impls.iter().rposition(|i| i.target == target && i.methods.contains_key(name))?;
+        let scheme = self.impls[i].methods[name].clone();
+        let mut map = Vec::new();
+        let mut bounds = Vec::new();
+        // `self` is generic along with the method
+        self.instantiate(&scheme.vars, &mut map, &mut bounds);
+        let this = self.substitute(&self.impls[i].this, &map);
+        self.subst.unify(&this, receiver);
+        let mut callee = self.callee(format!("{}.{}", target, name), &Scheme { vars: Vec::new(), ..scheme }, map, span);
+        callee.bounds.extend(bounds);
+        Some(callee)
+    }
+
+    /// Whether an `impl` further down gives the type `target` names the
+    /// method `name`, which counts in bodies that run later.
+    fn upcoming_method(&self, target: &str, name: &str) -> bool {
+        self.in_frame && self.upcoming.iter().any(|(_, t, methods)| t == target && methods.iter().any(|m| m == name))
+    }
+
+    /// What `target.name(...)` calls. As at runtime, a class's own methods
+    /// come before impls for it.
+    fn method(&mut self, target: &Type, name: &str, span: Span) -> Lookup<Callee> {
+        match self.subst.shallow(target) {
+            Type::Instance(class, args) => {
+                let found = self.find(class.id, &args, |info, map| {
+                    info.methods.get(name).map(|scheme| (scheme.clone(), map.to_vec()))
+                });
+                match found {
+                    Lookup::Found((scheme, map)) => {
+                        return Lookup::Found(self.callee(format!("{}.{}", class.name, name), &scheme, map, span));
+                    }
+                    Lookup::Unknown => return Lookup::Unknown,
+                    Lookup::Missing => {}
+                }
+                // an impl for the class or an ancestor, with `self` as that class
+                let found = self.find(class.id, &args, |info, map| {
+                    let this = Type::Instance(info.named.clone(), info.params.iter().map(|p| self.substitute(p, map)).collect());
+                    self.impls.iter().any(|i| i.target == info.named.name && i.methods.contains_key(name))
+                        .then(|| (info.named.name.clone(), this))
+                });
+                match found {
+                    Lookup::Found((owner, this)) => match self.impl_method(&owner, &this, name, span) {
+                        Some(callee) => Lookup::Found(callee),
+                        None => Lookup::Missing,
+                    },
+                    Lookup::Unknown => Lookup::Unknown,
+                    Lookup::Missing => {
+                        let later = self.lineage(class.id).iter().flatten().any(|owner| self.upcoming_method(owner, name));
+                        if later { Lookup::Unknown } else { Lookup::Missing }
+                    }
+                }
+            }
+            Type::Dyn(interface) | Type::Param(_, Some(interface)) => {
+                match self.interfaces[interface.id].methods.get(name).cloned() {
+                    Some(scheme) => Lookup::Found(self.callee(format!("{}.{}", interface.name, name), &scheme, Vec::new(), span)),
+                    None => Lookup::Missing,
+                }
+            }
+            // a module's functions are called as its members
+            Type::Module(module) if self.modules[module.id].is_some() => Lookup::Missing,
+            Type::Option(_) | Type::Module(_) | Type::Var(_) | Type::Unknown => Lookup::Unknown,
+            other => {
+                let Some(owner) = other.impl_name().map(str::to_string) else { return Lookup::Missing };
+                match self.impl_method(&owner, &other, name, span) {
+                    Some(callee) => Lookup::Found(callee),
+                    None if self.upcoming_method(&owner, name) => Lookup::Unknown,
+                    None => Lookup::Missing,
+                }
+            }
+        }
+    }
+
+    // --------------------------
+    // EXPRESSIONS
+    // --------------------------
+
+    fn expr(&mut self, id: ExprId) -> Type {
+        let ty = self.expr_kind(id);
+        self.exprs.insert(id, ty.clone());
+        ty
+    }
+
+    fn expr_kind(&mut self, id: ExprId) -> Type {
+        let ast = self.ast;
+        let span = ast[id].span;
+        match &ast[id].kind {
+            ExprKind::Identifier(name) => self.lookup(name).unwrap_or(Type::Unknown),
+            ExprKind::StringLiteral(_) => Type::Str,
+            ExprKind::IntLiteral(_) => Type::Int,
+            ExprKind::BoolLiteral(_) => Type::Bool,
+            ExprKind::NoneLiteral => self.none(),
+            ExprKind::Ok(inner) => Type::Result(Box::new(self.expr(*inner)), Box::new(self.subst.fresh())),
+            ExprKind::Err(inner) => Type::Result(Box::new(self.subst.fresh()), Box::new(self.expr(*inner))),
+            ExprKind::Some(inner) => Type::Option(Box::new(self.expr(*inner))),
+            ExprKind::Propagate(inner) => {
+                let inner = self.expr(*inner);
+                match self.subst.shallow(&inner) {
+                    Type::Result(ok, _) => *ok,
+                    Type::Option(value) => *value,
+                    Type::Unknown | Type::Var(_) => Type::Unknown,
+                    other => {
+                        let other = self.subst.resolve(&other).show();
+                        self.error(span, format!("'?' requires a Result or Option, got '{}'", other));
+                        Type::Unknown
+                    }
+                }
+            }
+            ExprKind::Tuple(items) => Type::Tuple(items.iter().map(|&e| self.expr(e)).collect()),
+            ExprKind::TupleIndex { tuple, index, optional } => {
+                let target = self.expr(*tuple);
+                let (target, maybe_none) = self.look_through(target, *optional);
+                let item = match target {
+                    Type::Tuple(items) if *index < items.len() => items[*index].clone(),
+                    Type::Tuple(_) => {
+                        let target = self.subst.resolve(&target).show();
+                        self.error(span, format!("Tuple index {} out of range for '{}'", index, target));
+                        Type::Unknown
+                    }
+                    Type::Unknown | Type::Var(_) => Type::Unknown,
+                    other => {
+                        let other = self.subst.resolve(&other).show();
+                        self.error(span, format!("Cannot index non-tuple type '{}'", other));
+                        Type::Unknown
+                    }
+                };
+                if maybe_none { Type::Unknown } else { item }
+            }
+            ExprKind::Member { object, name, optional } => {
+                let target = self.expr(*object);
+                let (target, maybe_none) = self.look_through(target, *optional);
+                let member = self.member(target, name, span);
+                if maybe_none { Type::Unknown } else { member }
+            }
+            ExprKind::Range { start, end, step, .. } => {
+                self.integer(*start, "Range start");
+                self.integer(*end, "Range end");
+                if let Some(step) = step {
+                    self.integer(*step, "Range step");
+                }
+                Type::Range
+            }
+            ExprKind::Slice { target, range } => {
+                let target = self.expr(*target);
+                let range_ty = self.expr(*range);
+                self.expect(&Type::Range, &range_ty, ast[*range].span, |_, found| {
+                    format!("Cannot slice with '{}'; expected a range", found)
+                });
+                match self.subst.shallow(&target) {
+                    Type::Str => Type::Str,
+                    Type::Tuple(_) | Type::Unknown | Type::Var(_) => Type::Unknown,
+                    other => {
+                        let other = self.subst.resolve(&other).show();
+                        self.error(span, format!("Cannot slice '{}'", other));
+                        Type::Unknown
+                    }
+                }
+            }
+            ExprKind::Block { statements, value } => self.scoped(|c| {
+                c.stmts(statements);
+                match value {
+                    Some(value) => c.expr(*value),
+                    None => c.none(),
+                }
+            }),
+            ExprKind::If { condition, then_branch, else_branch } => {
+                let discarded = self.discarded.take() == Some(id);
+                self.condition(*condition);
+                let then = self.branch(*condition, true, *then_branch);
+                let otherwise = match else_branch {
+                    Some(e) => {
+                        if discarded {
+                            self.discarded = Some(*e);
+                        }
+                        self.branch(*condition, false, *e)
+                    }
+                    None => self.none(),
+                };
+                match discarded {
+                    true => self.join(&then, &otherwise).unwrap_or(Type::Unknown),
+                    false => self.branches(&then, &otherwise, span),
+                }
+            }
+            ExprKind::Ternary { condition, then_expr, else_expr } => {
+                self.condition(*condition);
+                let then = self.branch(*condition, true, *then_expr);
+                let otherwise = self.branch(*condition, false, *else_expr);
+                self.branches(&then, &otherwise, span)
+            }
+            ExprKind::Call { callee, args } => self.call(*callee, args, span),
+            ExprKind::Super { method } => {
+                self.error(span, format!("'super.{}' must be called", method));
+                Type::Unknown
+            }
+            ExprKind::Binary { left, op, right } => {
+                let l = self.expr(*left);
+                let r = self.expr(*right);
+                self.binary(&l, op, &r, span)
+            }
+        }
+    }
+
+    fn condition(&mut self, id: ExprId) {
+        let ty = self.expr(id);
+        self.expect(&Type::Bool, &ty, self.ast[id].span, |_, found| {
+            format!("Condition must be a bool, found '{}'", found)
+        });
+    }
+
+    /// The type of the branch of an `if` or `?:` taken when `condition` is
+    /// `taken`. A variable the condition found not to be `none` has the
+    /// type inside its option there.
+    fn branch(&mut self, condition: ExprId, taken: bool, branch: ExprId) -> Type {
+        let narrowed = match none_check(self.ast, condition) {
+            Some((name, present)) if present == taken => self.lookup_declared(name).and_then(|ty| {
+                match self.subst.shallow(&ty) {
+                    Type::Option(inner) => Some((name.to_string(), *inner)),
+                    _ => None,
+                }
+            }),
+            _ => None,
+        };
+        match narrowed {
+            Some((name, inner)) => self.scoped(|c| {
+                c.scopes.last_mut().expect("scoped opens a scope").insert(name, inner);
+                c.expr(branch)
+            }),
+            None => self.expr(branch),
+        }
+    }
+
+    fn integer(&mut self, id: ExprId, what: &str) {
+        let ty = self.expr(id);
+        self.expect(&Type::Int, &ty, self.ast[id].span, |_, found| {
+            format!("{} must be an integer, found '{}'", what, found)
+        });
+    }
+
+    /// `target.name` outside of a call.
+    fn member(&mut self, target: Type, name: &str, span: Span) -> Type {
+        match self.subst.shallow(&target) {
+            Type::Instance(class, args) => match self.find(class.id, &args, |info, map| {
+                info.fields.iter().find(|(field, _)| field == name).map(|(_, ty)| self.substitute(ty, map))
+            }) {
+                Lookup::Found(ty) => ty,
+                Lookup::Unknown => Type::Unknown,
+                Lookup::Missing => {
+                    let message = match self.find(class.id, &args, |info, _| info.methods.get(name).map(|_| ())) {
+                        Lookup::Found(()) => format!("Method '{}.{}' must be called", class.name, name),
+                        _ => format!("'{}' has no field '{}'", class.name, name),
+                    };
+                    self.error(span, message);
+                    Type::Unknown
+                }
+            },
+            Type::Newtype(def) => match name {
+                "value" => self.types[def.id].target.clone(),
+                _ => {
+                    self.error(span, format!("'{}' has no member '{}'", def.name, name));
+                    Type::Unknown
+                }
+            },
+            Type::Error => match name {
+                "kind" | "message" => Type::Str,
+                "trace" | "location" => Type::Unknown,
+                _ => {
+                    self.error(span, format!("Error values have no member '{}'", name));
+                    Type::Unknown
+                }
+            },
+            // a member that is missing or private is a name error
+            Type::Module(module) => self.modules[module.id].as_ref()
+                .and_then(|members| members.get(name).cloned())
+                .unwrap_or(Type::Unknown),
+            // whatever the value turns out to be may have fields
+            Type::Dyn(_) | Type::Param(..) | Type::Unknown | Type::Var(_) => Type::Unknown,
+            other => {
+                let other = self.subst.resolve(&other).show();
+                self.error(span, format!("Cannot access member '{}' on '{}'", name, other));
+                Type::Unknown
+            }
+        }
+    }
+
+    fn call(&mut self, callee: ExprId, args: &'a [ExprId], span: Span) -> Type {
+        let ast = self.ast;
+        match &ast[callee].kind {
+            // value.method(...) dispatches on the receiver's methods first
+            ExprKind::Member { object, name, optional: false } => {
+                let target = self.expr(*object);
+                match self.method(&target, name, span) {
+                    Lookup::Found(found) => {
+                        self.exprs.insert(callee, Type::Unknown);
+                        return self.apply(found, args, span);
+                    }
+                    Lookup::Unknown => {
+                        self.exprs.insert(callee, Type::Unknown);
+                        self.args_unchecked(args);
+                        return Type::Unknown;
+                    }
+                    Lookup::Missing => {}
+                }
+                // a field or module member can hold something callable
+                if !matches!(self.subst.shallow(&target), Type::Instance(..) | Type::Module(_)) {
+                    self.exprs.insert(callee, Type::Unknown);
+                    let target = self.subst.resolve(&target).show();
+                    self.error(ast[callee].span, format!("'{}' has no method '{}'", target, name));
+                    self.args_unchecked(args);
+                    return Type::Unknown;
+                }
+                let callee_ty = self.member(target, name, ast[callee].span);
+                self.exprs.insert(callee, callee_ty.clone());
+                self.call_value(callee_ty, args, span)
+            }
+
+            // super.method(...) looks in the parent of the class being defined
+            ExprKind::Super { method } => {
+                self.exprs.insert(callee, Type::Unknown);
+                let Some((class, current)) = self.current_method.clone() else {
+                    self.args_unchecked(args);
+                    return Type::Unknown;
+                };
+                let found = match self.classes[class].parent.clone() {
+                    Some(parent @ Type::Instance(..)) => self.method(&parent, method, span),
+                    Some(_) => Lookup::Unknown,
+                    None => Lookup::Missing,
+                };
+                match found {
+                    Lookup::Found(found) => self.apply(Callee { name: format!("super.{}", method), ..found }, args, span),
+                    Lookup::Unknown => {
+                        self.args_unchecked(args);
+                        Type::Unknown
+                    }
+                    Lookup::Missing => {
+                        let message = format!(
+                            "No parent method '{}' for '{}.{}'", method, self.classes[class].named.name, current
+                        );
+                        self.error(ast[callee].span, message);
+                        self.args_unchecked(args);
+                        Type::Unknown
+                    }
+                }
+            }
+
+            ExprKind::Identifier(name) if self.lookup_declared(name).is_none() && !declares(ast, name) => {
+                match builtin(name) {
+                    Some((params, returns)) => {
+                        self.exprs.insert(callee, Type::Unknown);
+                        self.apply(Callee { name: name.clone(), params, returns, bounds: Vec::new() }, args, span)
+                    }
+                    None => {
+                        let callee = self.expr(callee);
+                        self.call_value(callee, args, span)
+                    }
+                }
+            }
+
+            _ => {
+                let callee = self.expr(callee);
+                self.call_value(callee, args, span)
+            }
+        }
+    }
+
+    /// Calling a value: functions run, classes construct, declared types convert.
+    fn call_value(&mut self, callee: Type, args: &'a [ExprId], span: Span) -> Type {
+        match self.subst.shallow(&callee) {
+            Type::Function(function) => {
+                let scheme = self.functions[function.id].clone();
+                let callee = self.callee(function.name, &scheme, Vec::new(), span);
+                self.apply(callee, args, span)
+            }
+            Type::Class(class) => {
+                let (instance, map, bounds) = self.instance_of(&class);
+                let params = self.fields(class.id, &map);
+                self.apply(Callee { name: class.name, params, returns: instance, bounds }, args, span)
+            }
+            Type::Declared(def) => {
+                let target = self.types[def.id].target.clone();
+                let nominal = self.types[def.id].nominal;
+                if args.len() != 1 {
+                    self.error(span, format!("{} takes 1 argument but {} were given", def.name, args.len()));
+                }
+                for &arg in args {
+                    let actual = self.expr(arg);
+                    self.expect(&target, &actual, self.ast[arg].span, |expected, found| {
+                        format!("Expected '{}' for {}, found '{}'", expected, def.name, found)
+                    });
+                }
+                if nominal { Type::Newtype(def) } else { Type::Alias(def, Box::new(target)) }
+            }
+            Type::Unknown | Type::Var(_) => {
+                self.args_unchecked(args);
+                Type::Unknown
+            }
+            other => {
+                let other = self.subst.resolve(&other).show();
+                self.error(span, format!("'{}' is not callable", other));
+                self.args_unchecked(args);
+                Type::Unknown
+            }
+        }
+    }
+
+    /// Check a call's `args` against the callee, then the types its type
+    /// parameters were given against their bounds.
+    fn apply(&mut self, callee: Callee, args: &'a [ExprId], span: Span) -> Type {
+        self.args(&callee.name, &callee.params, args, span);
+        for (var, bound, param) in &callee.bounds {
+            let ty = self.subst.resolve(var);
+            if !self.implements(&ty, bound) {
+                self.error(span, format!(
+                    "Type parameter '{}' of {} must implement {}, found '{}'", param, callee.name, bound.name, ty.show()
+                ));
+            }
+        }
+        callee.returns
+    }
+
+    /// Check `args` against the parameters of `callee`.
+    fn args(&mut self, callee: &str, params: &[(String, Type)], args: &'a [ExprId], span: Span) {
+        if args.len() != params.len() {
+            self.error(span, format!(
+                "{} takes {} argument(s) but {} were given", callee, params.len(), args.len()
+            ));
+        }
+        for (i, &arg) in args.iter().enumerate() {
+            let actual = self.expr(arg);
+            if let Some((name, expected)) = params.get(i) {
+                self.expect(expected, &actual, self.ast[arg].span, |expected, found| {
+                    format!("Expected '{}' for parameter '{}' of {}, found '{}'", expected, name, callee, found)
+                });
+            }
+        }
+    }
+
+    /// Arguments to something whose parameters are not known.
+    fn args_unchecked(&mut self, args: &'a [ExprId]) {
+        for &arg in args {
+            self.expr(arg);
+        }
+    }
+
+    fn binary(&mut self, l: &Type, op: &Operator, r: &Type, span: Span) -> Type {
+        use Operator::*;
+        let (l, r) = (self.subst.shallow(l), self.subst.shallow(r));
+        let result = match op {
+            Plus | Minus | Multiply | Division | Less | LessEqual | Greater | GreaterEqual => {
+                let result = self.subst.fresh();
+                let origin = self.body;
+                self.operator(Pending { left: l, op: op.clone(), right: r, result: result.clone(), span, origin });
+                return result;
+            }
+            Equal | NotEqual => match (&l, &r) {
+                (Type::Newtype(a), Type::Newtype(b)) if a != b => None,
+                _ => Some(Type::Bool),
+            },
+            In => match &r {
+                Type::Range | Type::Tuple(_) | Type::Unknown | Type::Var(_) => Some(Type::Bool),
+                Type::Str if self.subst.unify(&Type::Str, &l) => Some(Type::Bool),
+                _ => None,
+            },
+            InstanceOf => match &r {
+                Type::Class(_) | Type::Declared(_) | Type::Unknown | Type::Var(_) => Some(Type::Bool),
+                other => {
+                    let other = self.subst.resolve(other).show();
+                    self.error(span, format!("'instanceof' requires a class or type, got '{}'", other));
+                    return Type::Bool;
+                }
+            },
+            // the value inside the option, or the default
+            Coalesce => match &l {
+                Type::Option(inner) => self.join(inner, &r),
+                _ => self.join(&l, &r),
+            },
+            Assignment => Some(Type::Unknown),
+        };
+        result.unwrap_or_else(|| {
+            let (l, r) = self.show(&l, &r);
+            self.error(span, format!("Cannot apply '{}' to '{}' and '{}'", op.as_str(), l, r));
+            Type::Unknown
+        })
+    }
+
+    /// Decide the result of an arithmetic or ordering operator, or keep it
+    /// pending until its operands are known well enough to.
+    fn operator(&mut self, p: Pending) {
+        use Operator::*;
+        // which type a parameter or interface value has is only known at runtime
+        let opaque = |t: Type| match t {
+            Type::Param(..) | Type::Dyn(_) => Type::Unknown,
+            t => t,
+        };
+        let (l, r) = (opaque(self.subst.shallow(&p.left)), opaque(self.subst.shallow(&p.right)));
+        let fail = |c: &mut Self| {
+            let (l, r) = c.show(&l, &r);
+            c.error(p.span, format!("Cannot apply '{}' to '{}' and '{}'", p.op.as_str(), l, r));
+        };
+        if !matches!(l, Type::Var(_)) && !matches!(r, Type::Var(_)) {
+            match arithmetic(&l, &p.op, &r) {
+                Some(ty) => {
+                    self.subst.unify(&p.result, &ty);
+                }
+                None => fail(self),
+            }
+            return;
+        }
+        match (&p.op, &l, &r) {
+            // both sides of these always have the same type
+            (Plus | Minus | Less | LessEqual | Greater | GreaterEqual, _, _) => {
+                if !self.subst.unify(&l, &r) {
+                    return fail(self);
+                }
+                let same = self.subst.shallow(&l);
+                match &p.op {
+                    Plus | Minus => self.subst.unify(&p.result, &same),
+                    _ => self.subst.unify(&p.result, &Type::Bool),
+                };
+                // kept until it is known whether that type takes the operator
+                if matches!(same, Type::Var(_)) { self.pending.push(p) } else { self.operator(p) }
+            }
+            // newtypes only scale by ints
+            (Multiply, Type::Var(_), Type::Newtype(_)) | (Multiply | Division, Type::Newtype(_), Type::Var(_)) => {
+                let var = if matches!(l, Type::Var(_)) { &l } else { &r };
+                self.subst.unify(var, &Type::Int);
+                self.operator(p);
+            }
+            // an int times something may be an int or scale a newtype
+            (_, Type::Var(_), Type::Var(_) | Type::Int | Type::Unknown) | (_, Type::Int | Type::Unknown, Type::Var(_)) => {
+                self.pending.push(p)
+            }
+            _ => fail(self),
+        }
+    }
+
+    /// Decide the pending operators whose operands are known by now, until
+    /// no more can be.
+    fn solve(&mut self) {
+        loop {
+            let before = self.pending.len();
+            for p in std::mem::take(&mut self.pending) {
+                self.operator(p);
+            }
+            if self.pending.len() >= before {
+                break;
+            }
+        }
+    }
+}
+
+/// The type of an arithmetic or ordering operator, or `None` if it does
+/// not apply. Newtypes follow the runtime: `Meters + Meters` and
+/// `Meters * int` stay `Meters`, and they mix with nothing else. An
+/// `unknown` side is taken to be whatever makes the other side work.
+fn arithmetic(l: &Type, op: &Operator, r: &Type) -> Option<Type> {
+    use Operator::*;
+    let ordering = matches!(op, Less | LessEqual | Greater | GreaterEqual);
+    match (l, r) {
+        (Type::Unknown, Type::Unknown) => Some(if ordering { Type::Bool } else { Type::Unknown }),
+        (Type::Unknown, known) | (known, Type::Unknown) => match (op, known) {
+            (_, Type::Int | Type::Newtype(_)) | (Plus | Less | LessEqual | Greater | GreaterEqual, Type::Str) => {
+                Some(match op {
+                    _ if ordering => Type::Bool,
+                    // `unknown * int` may be an int or scale a newtype
+                    Multiply | Division if *known == Type::Int => Type::Unknown,
+                    _ => known.clone(),
+                })
+            }
+            _ => None,
+        },
+        (Type::Int, Type::Int) => Some(if ordering { Type::Bool } else { Type::Int }),
+        (Type::Str, Type::Str) => match op {
+            Plus => Some(Type::Str),
+            _ if ordering => Some(Type::Bool),
+            _ => None,
+        },
+        (Type::Newtype(a), Type::Newtype(b)) if a == b => match op {
+            Plus | Minus => Some(l.clone()),
+            _ if ordering => Some(Type::Bool),
+            _ => None,
+        },
+        (Type::Newtype(_), Type::Int) if matches!(op, Multiply | Division) => Some(l.clone()),
+        (Type::Int, Type::Newtype(_)) if matches!(op, Multiply) => Some(r.clone()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lexer as lex;
+    use crate::parser::Parser;
+
+    fn errors(source: &str) -> Vec<String> {
+        let ast = Parser::new(lex(source)).parse();
+        match check(&ast, &mut |_| None) {
+            Ok(_) => Vec::new(),
+            Err(errors) => errors.into_iter().map(|(_, message)| message).collect(),
+        }
+    }
+
+    /// The type of the value of the last `let` in `source`.
+    fn type_of_last(source: &str) -> String {
+        let ast = Parser::new(lex(source)).parse();
+        let typed = check(&ast, &mut |_| None).expect("should type check");
+        let value = ast.body.iter().rev()
+            .find_map(|&s| match &ast[s].kind {
+                StmtKind::VarDeclaration { value, .. } => Some(*value),
+                _ => None,
+            })
+            .expect("a let");
+        typed.exprs.get(value).expect("every expression has a type").show()
+    }
+
+    /// What `--show-types` prints for `source`'s declarations.
+    fn declarations(source: &str) -> Vec<String> {
+        let ast = Parser::new(lex(source)).parse();
+        let typed = check(&ast, &mut |_| None).expect("should type check");
+        typed.declarations.iter().map(|d| format!("{}: {}", d.name, d.ty.show())).collect()
+    }
+
+    #[test]
+    fn expressions_get_types() {
+        assert_eq!(type_of_last("let x = (1, \"a\", 1 < 2);"), "(int, string, bool)");
+        assert_eq!(type_of_last("let t = (1, (\"a\", true)); let x = t.1.0 + \"b\";"), "string");
+        assert_eq!(type_of_last("let x = if (true) { some(1) } else { none };"), "Option<int>");
+        assert_eq!(type_of_last("let x = none ?? \"d\";"), "string");
+        assert_eq!(type_of_last("newtype M = int; let x = M(2) * 3 + M(1);"), "M");
+        assert_eq!(type_of_last("type Id = int; let x = Id(2);"), "Id");
+        assert_eq!(type_of_last("type Id = int; let x = Id(2) + 1;"), "int");
+        assert_eq!(type_of_last("class P(int x) { fn twice() -> int { self.x * 2 } } let x = P(1).twice();"), "int");
+        assert_eq!(type_of_last("class P(x) {} let x = P(1).x;"), "int");
+        assert_eq!(type_of_last("fn f(int a) -> Result<int, string> { ok(a) } let x = f(1);"), "Result<int, string>");
+        assert_eq!(type_of_last("let x = { for c in \"ab\" { println(c); } };"), "Option<'a>");
+    }
+
+    #[test]
+    fn builtins_that_can_fail_return_results() {
+        assert_eq!(type_of_last("let x = parseInt(\"1\");"), "Result<int, string>");
+        assert_eq!(type_of_last("fn f(p) { let t = readFile(p)?; writeFile(p, t) } let x = f(\"a\");"), "Result<int, string>");
+        assert_eq!(errors("parseInt(1);"), ["Expected 'string' for parameter 'text' of parseInt, found 'int'"]);
+        // a declaration of the same name, even further down, is called instead
+        assert_eq!(type_of_last("fn parseInt(x) { (x, x) } let y = parseInt(1);"), "(int, int)");
+        assert!(errors("fn f() { parseInt(1) } fn parseInt(x) { x }").is_empty());
+    }
+
+    #[test]
+    fn operators_need_matching_operands() {
+        assert_eq!(errors("let x = 3; let y = \"a\" + x;"), ["Cannot apply '+' to 'string' and 'int'"]);
+        assert_eq!(errors("let y = true - 1;"), ["Cannot apply '-' to 'bool' and 'int'"]);
+        assert_eq!(errors("let y = \"a\" < 1;"), ["Cannot apply '<' to 'string' and 'int'"]);
+        assert_eq!(errors("newtype A = int; newtype B = int; let y = A(1) + B(1);"), ["Cannot apply '+' to 'A' and 'B'"]);
+        assert_eq!(errors("let y = 1 in \"abc\";"), ["Cannot apply 'in' to 'int' and 'string'"]);
+        assert_eq!(errors("let y = 1 instanceof 2;"), ["'instanceof' requires a class or type, got 'int'"]);
+        assert_eq!(errors("let y = if (1) { 2 } else { 3 };"), ["Condition must be a bool, found 'int'"]);
+        assert_eq!(errors("let r = \"a\"..3;"), ["Range start must be an integer, found 'string'"]);
+        assert_eq!(errors("for i in 5 { println(i); }"), ["Cannot iterate over 'int'"]);
+        assert_eq!(errors("let y = (1, 2).2;"), ["Tuple index 2 out of range for '(int, int)'"]);
+        assert_eq!(errors("let y = 3?;"), ["'?' requires a Result or Option, got 'int'"]);
+        assert_eq!(errors("let x = if (true) { 1 } else { \"a\" };"), ["Branches have different types 'int' and 'string'"]);
+        assert_eq!(errors("let x = true ? (1, some(2)) : (3, some(\"b\"));"), [
+            "Branches have different types '(int, Option<int>)' and '(int, Option<string>)'",
+        ]);
+        assert_eq!(errors("let x = some(1) ?? \"a\";"), ["Cannot apply '??' to 'Option<int>' and 'string'"]);
+        assert!(errors("if (true) { 1 } else if (false) { \"a\" } else { println(2); }").is_empty());
+        assert!(errors("class C(a) { fn f(b) { self.a + b + 1 } } let y = 1 + C(1).f(2);").is_empty());
+    }
+
+    #[test]
+    fn declarations_and_calls_follow_annotations() {
+        assert_eq!(errors("let int x = \"a\";"), ["Expected 'int', found 'string'"]);
+        assert_eq!(errors("const (int, string) (a, b) = (1, 2);"), ["Expected '(int, string)', found '(int, int)'"]);
+        assert_eq!(errors("let int? n = some(\"a\");"), ["Expected 'Option<int>', found 'Option<string>'"]);
+        assert_eq!(errors("let (a, b) = (1, 2, 3);"), ["Cannot destructure a tuple of 3 elements into 2 names"]);
+        assert_eq!(errors("let Nope x = 1;"), ["Unknown type 'Nope'"]);
+        assert_eq!(errors("type UserId = int; let UserId u = \"s\";"), ["Expected 'UserId', found 'string'"]);
+        assert_eq!(declarations("type UserId = int; let UserId u = 1; let int v = u;"), ["UserId: type UserId", "u: UserId", "v: int"]);
+        assert_eq!(errors("let Option<int, int> x = none;"), ["'Option' takes 1 type argument(s) but 2 were given"]);
+        assert_eq!(errors(r#"
+            class Animal(pub string name) {
+                fn greet(string other) -> string { "hi " + other }
+            }
+            class Dog(name, int age) extends Animal(age) {
+                fn greet(other) -> int { super.greet(1) }
+            }
+            let d = Dog("Rex", "old");
+            let g = d.greet();
+            let n = d.nickname;
+            let a = Animal("a", "b");
+            fn label(int id) -> string { id }
+            let l = label("x");
+        "#), [
+            "Expected 'string' for parameter 'name' of Animal, found 'int'",
+            "'Dog.greet' should return 'int', found 'string'",
+            "Expected 'string' for parameter 'other' of super.greet, found 'int'",
+            "Expected 'int' for parameter 'age' of Dog, found 'string'",
+            "Dog.greet takes 1 argument(s) but 0 were given",
+            "'Dog' has no field 'nickname'",
+            "Animal takes 1 argument(s) but 2 were given",
+            "'label' should return 'string', found 'int'",
+            "Expected 'int' for parameter 'id' of label, found 'string'",
+        ]);
+    }
+
+    #[test]
+    fn type_parameters_and_interfaces_are_checked_against_impls() {
+        let shapes = "
+            interface Shape { fn area(self) -> int; }
+            impl Shape for int { fn area(self) -> int { self * self } }
+            class Square(int side) {}
+            impl Shape for Square { fn area(self) -> int { self.side * self.side } }
+        ";
+        assert_eq!(type_of_last(&format!("{} fn total<T: Shape>(T a, Shape b) -> int {{ a.area() + b.area() }} let t = total(2, Square(3));", shapes)), "int");
+        assert_eq!(type_of_last(&format!("{} let t = 4.area();", shapes)), "int");
+        assert_eq!(type_of_last("fn first<T>(T a, T b) -> T { a } let x = first(1, 2);"), "int");
+        assert_eq!(errors(&format!("{} fn f<T: Shape>(T a) -> T {{ a.perimeter() }} f(\"s\"); \"s\".area(); let Shape s = true;", shapes)), [
+            "'T' has no method 'perimeter'",
+            "Type parameter 'T' of f must implement Shape, found 'string'",
+            "'string' has no method 'area'",
+            "Expected 'Shape', found 'bool'",
+        ]);
+        assert_eq!(errors("fn f<T>(T a) -> T { 1 } fn g<T: Nope>(T a) { a } let x = f(1) + f(\"a\"); fn h<T>(T a, T b) { a } h(1, \"b\");"), [
+            "'f' should return 'T', found 'int'",
+            "Unknown interface 'Nope'",
+            "Cannot apply '+' to 'int' and 'string'",
+            "Expected 'int' for parameter 'b' of h, found 'string'",
+        ]);
+        // an impl further down counts in bodies, which run later
+        assert!(errors("fn show(int n) -> int { n.area() } interface Shape { fn area(self) -> int; } impl Shape for int { fn area(self) -> int { self } }").is_empty());
+    }
+
+    #[test]
+    fn optional_values_must_be_checked_for_none_before_use() {
+        assert_eq!(errors("let int? x = 3; let y = x + 1;"), ["Cannot apply '+' to 'Option<int>' and 'int'"]);
+        assert_eq!(errors("let int x = none;"), ["Expected 'int', found 'Option<'a>'"]);
+        assert_eq!(errors("fn f(int? n) -> int { n }"), ["'f' should return 'int', found 'Option<int>'"]);
+        assert!(errors("let int? x = none; let y = x == none ? 0 : x * 2;").is_empty());
+        assert!(errors("fn f(int? n) -> int { if (none != n) { n + 1 } else { 0 } }").is_empty());
+        assert_eq!(type_of_last("let int? x = some(3); let y = x ?? 0;"), "int");
+    }
+
+    #[test]
+    fn an_if_without_else_may_give_none() {
+        assert_eq!(type_of_last("let x = { if (true) { 1 } };"), "Option<int>");
+        assert_eq!(type_of_last("let int? x = 2; let y = if (x != none) { x } else { x };"), "Option<int>");
+        assert_eq!(declarations("class C(int n) { fn f() { if (self.n > 0) { self.n } } } let y = C(1).f();"), [
+            "C: class C", "C.n: int", "C.f: fn() -> Option<int>", "y: Option<int>",
+        ]);
+        assert!(errors("class C(int n) { fn f() -> int? { if (self.n > 0) { self.n } } }").is_empty());
+        assert_eq!(errors("fn f(bool b) -> int { if (b) { 1 } }"), ["'f' should return 'int', found 'Option<int>'"]);
+    }
+
+    #[test]
+    fn unannotated_code_is_inferred_from_use() {
+        assert_eq!(declarations(r#"
+            let x = 3;
+            class Counter(start) {
+                fn next(step) { self.start + step }
+                fn twice(step) { self.next(step) * 2 }
+            }
+            fn add(a, b) { a - b + 1 }
+            let (a, b) = (Counter(x).twice(1), none);
+        "#), [
+            "x: int",
+            "Counter: class Counter",
+            "Counter.start: 'a",
+            "Counter.next: fn('a) -> 'a",
+            "Counter.twice: fn('a) -> 'b",
+            "add: fn(int, int) -> int",
+            "a: int",
+            "b: Option<'a>",
+        ]);
+        assert_eq!(errors("class C(a) { fn f(b) { self.a + b + 1 } } let y = \"s\" + C(1).f(2);"), [
+            "Cannot apply '+' to 'string' and 'int'",
+        ]);
+        assert_eq!(errors("class C(a) { fn f() { if (self.a) { 1 } else { 2 } } } let c = C(3);"), [
+            "Expected 'bool' for parameter 'a' of C, found 'int'",
+        ]);
+        assert_eq!(errors("fn f() { limit + 1 } let limit = \"ten\";"), [
+            "'limit' is declared as 'string' but used as 'int' before",
+        ]);
+    }
+
+    #[test]
+    fn classes_and_functions_are_generic_over_what_is_left_open() {
+        assert_eq!(declarations(r#"
+            class Box(v) {
+                fn get() { self.v }
+                fn with(w) { (self.v, w) }
+                fn same() { Box(self.v) }
+            }
+            let i = Box(1).get();
+            let s = Box("a").with(true);
+            let t = Box(1).with("b").1;
+        "#), [
+            "Box: class Box",
+            "Box.v: 'a",
+            "Box.get: fn() -> 'a",
+            "Box.with: fn('a) -> ('b, 'a)",
+            "Box.same: fn() -> Box<'a>",
+            "i: int",
+            "s: (string, bool)",
+            "t: string",
+        ]);
+        assert_eq!(declarations("class Pair(a, b) {} let p = Pair(1, \"x\");").last().unwrap(), "p: Pair<int, string>");
+        assert_eq!(type_of_last("class Pair<A>(A a, b) {} let Pair<string> p = Pair(\"a\", 2);"), "Pair<string, int>");
+        assert_eq!(type_of_last("fn pair(a, b) { (b, a) } let p = (pair(1, \"a\"), pair(true, 2));"), "((string, int), (int, bool))");
+
+        // each call checks the operators on what it passes
+        let add = "fn add(a, b) { a + b } let x = add(1, 2); let y = add(\"a\", \"b\");";
+        assert!(errors(add).is_empty());
+        assert_eq!(errors("class Adder() { fn add(a, b) { a + b } } let y = Adder().add(true, false);"), [
+            "Cannot apply '+' to 'bool' and 'bool'",
+        ]);
+    }
+
+    #[test]
+    fn members_of_imports_have_their_types() {
+        let util = r#"
+            pub fn double(int x) -> int { x * 2 }
+            pub fn id(x) { x }
+            pub const name = "util";
+            pub class Point(pub int x, y) { pub fn sum() { self.x + self.y } }
+            fn helper() { 1 }
+        "#;
+        let check = |source: &str| {
+            let ast = Parser::new(lex(source)).parse();
+            let mut load = |path: &str| (path == "util.sst").then(|| Parser::new(lex(util)).parse());
+            match check(&ast, &mut load) {
+                Ok(typed) => Ok(typed.declarations.iter().map(|d| format!("{}: {}", d.name, d.ty.show())).collect::<Vec<_>>()),
+                Err(errors) => Err(errors.into_iter().map(|(_, message)| message).collect::<Vec<_>>()),
+            }
+        };
+
+        assert_eq!(check(r#"
+            import "util.sst";
+            let a = util.double(2);
+            let b = (util.id("s"), util.id(true));
+            let c = util.Point(1, 2).sum() + util.Point(3, 4).x;
+            let d = util.name;
+        "#).unwrap(), [
+            "util: module util",
+            "a: int",
+            "b: (string, bool)",
+            "c: int",
+            "d: string",
+        ]);
+        assert_eq!(check("import \"util.sst\"; let int n = util.name; util.double(\"a\");").unwrap_err(), [
+            "Expected 'int', found 'string'",
+            "Expected 'int' for parameter 'x' of double, found 'string'",
+        ]);
+        // a module that cannot be read is not known until it runs
+        assert_eq!(check("import other from \"other.sst\"; let x = other.f(1);").unwrap(), [
+            "other: module other",
+            "x: unknown",
+        ]);
+    }
+}
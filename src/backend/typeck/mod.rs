@@ -0,0 +1,5 @@
+pub mod types;
+pub mod unify;
+pub mod check;
+
+pub use check::check;
@@ -0,0 +1,175 @@
+use std::fmt;
+
+/// A class, interface, function or declared type, told apart from others
+/// of the same name by `id`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Named {
+    pub id: usize,
+    pub name: String,
+}
+
+/// The static type of an expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    Int,
+    Str,
+    Bool,
+    Tuple(Vec<Type>),
+    Range,
+    /// `some(x)` or `int?`, or `none` as an option of a fresh variable
+    Option(Box<Type>),
+    /// `ok(x)` / `err(e)`
+    Result(Box<Type>, Box<Type>),
+    /// The error value bound by `catch (e)`
+    Error,
+    /// An instance of a class, with a type for each of the class's type
+    /// parameters and then each field type its uses leave open:
+    /// `Box<int>` for `class Box(v)` built with `Box(1)`
+    Instance(Named, Vec<Type>),
+    /// A value wrapped by a `newtype`
+    Newtype(Named),
+    /// The target of a `type` alias, shown by the alias's name. It is
+    /// the same type as its target in every other way.
+    Alias(Named, Box<Type>),
+    /// A class itself, as called to construct instances
+    Class(Named),
+    /// A `type` or `newtype` declaration, as called to convert values
+    Declared(Named),
+    /// A free function
+    Function(Named),
+    /// An interface itself
+    Interface(Named),
+    /// A value of any type implementing an interface, as in `Shape s`.
+    /// Its methods are the interface's.
+    Dyn(Named),
+    /// A type parameter inside the declaration it belongs to, with its
+    /// bound. It stands for one type the caller picks, so it only fits
+    /// itself.
+    Param(String, Option<Named>),
+    /// A function's or method's signature. These are not values; this
+    /// only describes them.
+    Fn(Vec<Type>, Box<Type>),
+    /// An imported module
+    Module(Named),
+    /// A type inference has yet to work out, shown as `'a`
+    Var(u32),
+    /// Not known before running, e.g. a member of another module. It fits
+    /// anywhere, and anything fits it.
+    Unknown,
+}
+
+impl Type {
+    /// `self` with every part `f` returns a replacement for replaced,
+    /// looking inside the parts `f` leaves alone.
+    pub fn map(&self, f: &impl Fn(&Type) -> Option<Type>) -> Type {
+        if let Some(replaced) = f(self) {
+            return replaced;
+        }
+        let all = |types: &[Type]| types.iter().map(|t| t.map(f)).collect();
+        match self {
+            Type::Tuple(items) => Type::Tuple(all(items)),
+            Type::Option(inner) => Type::Option(Box::new(inner.map(f))),
+            Type::Result(ok, err) => Type::Result(Box::new(ok.map(f)), Box::new(err.map(f))),
+            Type::Instance(class, args) => Type::Instance(class.clone(), all(args)),
+            Type::Alias(def, target) => Type::Alias(def.clone(), Box::new(target.map(f))),
+            Type::Fn(params, returns) => Type::Fn(all(params), Box::new(returns.map(f))),
+            other => other.clone(),
+        }
+    }
+
+    /// Add the variables in `self` to `out`, each once, in order of appearance.
+    pub fn vars(&self, out: &mut Vec<u32>) {
+        match self {
+            Type::Var(v) if !out.contains(v) => out.push(*v),
+            Type::Tuple(items) | Type::Instance(_, items) => items.iter().for_each(|t| t.vars(out)),
+            Type::Option(inner) | Type::Alias(_, inner) => inner.vars(out),
+            Type::Result(ok, err) => {
+                ok.vars(out);
+                err.vars(out);
+            }
+            Type::Fn(params, returns) => {
+                params.iter().for_each(|t| t.vars(out));
+                returns.vars(out);
+            }
+            _ => {}
+        }
+    }
+
+    /// The name `impl` blocks use for values of this type, when there is
+    /// a single one.
+    pub fn impl_name(&self) -> Option<&str> {
+        Some(match self {
+            Type::Int => "int",
+            Type::Str => "string",
+            Type::Bool => "bool",
+            Type::Tuple(_) => "tuple",
+            Type::Range => "range",
+            Type::Result(..) => "result",
+            Type::Error => "error",
+            Type::Instance(named, _) | Type::Newtype(named) => &named.name,
+            Type::Class(_) => "class",
+            Type::Declared(_) => "type",
+            Type::Function(_) => "fn",
+            Type::Interface(_) => "interface",
+            Type::Module(_) => "module",
+            Type::Alias(_, target) => return target.impl_name(),
+            // `some(x)` is an "option" but `none` is a "none"
+            Type::Option(_) | Type::Dyn(_) | Type::Param(..) | Type::Fn(..) | Type::Var(_) | Type::Unknown => {
+                return None
+            }
+        })
+    }
+
+    /// Display `types` with their variables renamed `'a`, `'b`, ... in order
+    /// of appearance, so they read the same however many were made before.
+    pub fn show_all(types: &[&Type]) -> Vec<String> {
+        let mut vars = Vec::new();
+        types.iter().for_each(|t| t.vars(&mut vars));
+        let rename = |t: &Type| match t {
+            Type::Var(v) => vars.iter().position(|x| x == v).map(|i| Type::Var(i as u32)),
+            _ => None,
+        };
+        types.iter().map(|t| t.map(&rename).to_string()).collect()
+    }
+
+    /// `self` displayed with its variables renamed from `'a`.
+    pub fn show(&self) -> String {
+        Type::show_all(&[self]).remove(0)
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let list = |types: &[Type]| types.iter().map(Type::to_string).collect::<Vec<_>>().join(", ");
+        match self {
+            Type::Int => write!(f, "int"),
+            Type::Str => write!(f, "string"),
+            Type::Bool => write!(f, "bool"),
+            Type::Tuple(items) if items.len() == 1 => write!(f, "({},)", items[0]),
+            Type::Tuple(items) => write!(f, "({})", list(items)),
+            Type::Range => write!(f, "range"),
+            Type::Option(inner) => write!(f, "Option<{}>", inner),
+            Type::Result(ok, err) => write!(f, "Result<{}, {}>", ok, err),
+            Type::Error => write!(f, "error"),
+            Type::Instance(class, args) if args.is_empty() => write!(f, "{}", class.name),
+            Type::Instance(class, args) => write!(f, "{}<{}>", class.name, list(args)),
+            Type::Newtype(named) | Type::Alias(named, _) | Type::Dyn(named) => write!(f, "{}", named.name),
+            Type::Param(name, _) => write!(f, "{}", name),
+            Type::Class(class) => write!(f, "class {}", class.name),
+            Type::Declared(def) => write!(f, "type {}", def.name),
+            Type::Function(function) => write!(f, "fn {}", function.name),
+            Type::Interface(interface) => write!(f, "interface {}", interface.name),
+            Type::Fn(params, returns) => write!(f, "fn({}) -> {}", list(params), returns),
+            Type::Module(module) => write!(f, "module {}", module.name),
+            // 'a .. 'z, then 'a1 ..
+            Type::Var(v) => {
+                write!(f, "'{}", (b'a' + (v % 26) as u8) as char)?;
+                match v / 26 {
+                    0 => Ok(()),
+                    n => write!(f, "{}", n),
+                }
+            }
+            Type::Unknown => write!(f, "unknown"),
+        }
+    }
+}
@@ -0,0 +1,98 @@
+//! Type variables and what they have been solved to.
+
+use crate::typeck::types::Type;
+
+/// The solution found so far for each type variable.
+#[derive(Debug, Default)]
+pub struct Subst {
+    bindings: Vec<Option<Type>>,
+}
+
+impl Subst {
+    /// A new variable, not yet solved.
+    pub fn fresh(&mut self) -> Type {
+        self.bindings.push(None);
+        Type::Var(self.bindings.len() as u32 - 1)
+    }
+
+    /// `ty`, following solved variables and aliases at the top only.
+    pub fn shallow(&self, ty: &Type) -> Type {
+        match ty {
+            Type::Var(v) => match &self.bindings[*v as usize] {
+                Some(bound) => self.shallow(bound),
+                None => ty.clone(),
+            },
+            Type::Alias(_, target) => self.shallow(target),
+            other => other.clone(),
+        }
+    }
+
+    /// `ty` with every solved variable in it replaced by its solution.
+    pub fn resolve(&self, ty: &Type) -> Type {
+        ty.map(&|t| match t {
+            Type::Var(v) => self.bindings[*v as usize].as_ref().map(|bound| self.resolve(bound)),
+            _ => None,
+        })
+    }
+
+    /// Make `a` and `b` the same type by solving variables in either,
+    /// or return false if they cannot be. `Unknown` matches anything
+    /// without solving the other side, and a failed attempt may leave
+    /// some parts solved.
+    pub fn unify(&mut self, a: &Type, b: &Type) -> bool {
+        let (a, b) = (self.shallow(a), self.shallow(b));
+        match (&a, &b) {
+            (Type::Var(x), Type::Var(y)) if x == y => true,
+            (Type::Unknown, _) | (_, Type::Unknown) => true,
+            (Type::Var(v), other) | (other, Type::Var(v)) => {
+                if self.occurs(*v, other) {
+                    return false;
+                }
+                self.bindings[*v as usize] = Some(other.clone());
+                true
+            }
+            (Type::Tuple(x), Type::Tuple(y)) if x.len() == y.len() => self.unify_all(x, y),
+            (Type::Option(x), Type::Option(y)) => self.unify(x, y),
+            (Type::Result(x, e), Type::Result(y, f)) => self.unify(x, y) && self.unify(e, f),
+            (Type::Instance(c, x), Type::Instance(d, y)) if c == d => {
+                // an instance made inside its own class has no arguments yet
+                x.is_empty() || y.is_empty() || (x.len() == y.len() && self.unify_all(x, y))
+            }
+            (Type::Fn(x, r), Type::Fn(y, s)) if x.len() == y.len() => self.unify_all(x, y) && self.unify(r, s),
+            (a, b) => a == b,
+        }
+    }
+
+    fn unify_all(&mut self, a: &[Type], b: &[Type]) -> bool {
+        a.iter().zip(b).all(|(a, b)| self.unify(a, b))
+    }
+
+    /// Whether `var` appears in `ty`, which would make solving it to `ty`
+    /// an infinite type.
+    fn occurs(&self, var: u32, ty: &Type) -> bool {
+        let mut vars = Vec::new();
+        self.resolve(ty).vars(&mut vars);
+        vars.contains(&var)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unify_solves_variables_on_either_side() {
+        let mut subst = Subst::default();
+        let (a, b) = (subst.fresh(), subst.fresh());
+        let pair = Type::Tuple(vec![a.clone(), Type::Str]);
+        assert!(subst.unify(&pair, &Type::Tuple(vec![Type::Int, b.clone()])));
+        assert_eq!(subst.resolve(&pair), Type::Tuple(vec![Type::Int, Type::Str]));
+        assert_eq!(subst.resolve(&b), Type::Str);
+
+        let c = subst.fresh();
+        assert!(!subst.unify(&c, &Type::Option(Box::new(c.clone()))), "occurs check");
+        assert!(!subst.unify(&a, &Type::Str));
+        assert!(subst.unify(&Type::Unknown, &c));
+        assert_eq!(subst.resolve(&c), c);
+    }
+}
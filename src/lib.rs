@@ -1,4 +1,5 @@
-pub mod lexer;
-pub mod ast;
-pub mod parser;
+mod backend;
 pub mod runtime;
+
+pub use backend::lexer::lexer;
+pub use backend::{ast, ir, parser, typeck};
@@ -1,33 +1,192 @@
-mod lexer;
-mod ast;
-mod parser;
-mod runtime;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
 
-use crate::lexer::lexer as lex;
-use crate::parser::Parser;
-use crate::runtime::Interpreter;
+use stupid_script::ast::json::Json;
+use stupid_script::ast::{self, attributes, dump, Ast};
+use stupid_script::ir::{self, Program};
+use stupid_script::lexer::{tokenize, Token};
+use stupid_script::parser::Parser;
+use stupid_script::typeck;
+use stupid_script::runtime::{Interpreter, RuntimeError};
+
+const USAGE: &str = "Usage: stupid_script [--test | --fmt | --tokens[=json|sexp] | --ast[=json|sexp] | --ir | --show-types] <file.sst|file.json>";
+
+/// What to do with the input file
+enum Mode {
+    Run,
+    /// Run the file, then call each of its `@test` functions
+    Test,
+    /// Print the file back out in canonical form
+    Format,
+    /// Print the lexer's tokens; `true` for S-expressions instead of JSON
+    Tokens(bool),
+    /// Print the parsed AST; `true` for S-expressions instead of JSON
+    Ast(bool),
+    /// Print the core IR the interpreter runs, with variables resolved to slots
+    Ir,
+    /// Print the type inferred for each declaration
+    Types,
+}
 
 fn main() {
-    let source = r#"
-        let x = 3;
-        let y = 4;
-        let s = "hello";
-        print(s);
-        println(" world");
-        println(x + y);
-        println(s + " world");
-    "#;
-
-    // 1) Lex
-    let tokens = lex(source);
-
-    // 2) Parse
-    let mut parser = Parser::new(tokens);
-    let stmts = parser.parse();
-
-    // 3) Interpret
-    let mut interp = Interpreter::new();
-    if let Err(e) = interp.run(stmts) {
-        eprintln!("Runtime error: {:?}", e);
+    let mut args: Vec<String> = env::args().skip(1).collect();
+
+    let mode = match args.first().map(String::as_str) {
+        Some(flag) if flag.starts_with("--") => {
+            let mode = match flag {
+                "--test" => Mode::Test,
+                "--fmt" => Mode::Format,
+                "--tokens" | "--tokens=json" => Mode::Tokens(false),
+                "--tokens=sexp" => Mode::Tokens(true),
+                "--ast" | "--ast=json" => Mode::Ast(false),
+                "--ast=sexp" => Mode::Ast(true),
+                "--ir" => Mode::Ir,
+                "--show-types" => Mode::Types,
+                _ => {
+                    eprintln!("Unknown option '{}'\n{}", flag, USAGE);
+                    process::exit(2);
+                }
+            };
+            args.remove(0);
+            mode
+        }
+        _ => Mode::Run,
+    };
+
+    let path = match args.first() {
+        Some(p) => PathBuf::from(p),
+        None => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+
+    let render = |json: Json, sexp: bool| if sexp { json.to_sexp() } else { json.to_json() };
+    match mode {
+        // Lex, parse and run the entry file; imports are loaded on demand
+        Mode::Run => interpret(move |interp| run(interp, &path)),
+        Mode::Test => {
+            let tests = attributes::tests(&parse(&path));
+            interpret(move |interp| {
+                if !run(interp, &path) {
+                    return false;
+                }
+                let mut failed = 0;
+                for name in &tests {
+                    let function = interp.env.global(name).expect("a test is a global function");
+                    match interp.call_value(function, Vec::new()) {
+                        Ok(_) => println!("test {} ... ok", name),
+                        Err(e) => {
+                            failed += 1;
+                            println!("test {} ... FAILED: {}", name, e);
+                        }
+                    }
+                }
+                println!("{} passed, {} failed", tests.len() - failed, failed);
+                failed == 0
+            })
+        }
+        Mode::Format => print!("{}", ast::printer::print(&parse(&path))),
+        Mode::Tokens(sexp) => println!("{}", render(dump::tokens(&lex(&path, &read(&path))), sexp)),
+        Mode::Ast(sexp) => println!("{}", render(dump::program(&parse(&path)), sexp)),
+        Mode::Ir => print!("{}", ir::printer::print(&resolve(&path, &parse(&path)).body)),
+        Mode::Types => {
+            let ast = parse(&path);
+            resolve(&path, &ast);
+            match typeck::check(&ast, &mut |import| imported(&path, import)) {
+                Ok(typed) => {
+                    for declaration in &typed.declarations {
+                        println!("{}: {} ({})", declaration.name, declaration.ty.show(), declaration.span);
+                    }
+                }
+                Err(errors) => {
+                    for (span, message) in errors {
+                        eprintln!("error: {} ({}:{})", message, path.display(), span);
+                    }
+                    process::exit(1);
+                }
+            }
+        }
+    }
+}
+
+/// Run `f` with a new interpreter, and exit with status 1 if it returns
+/// false.
+fn interpret(f: impl FnOnce(&mut Interpreter) -> bool + Send + 'static) {
+    // deep recursion should end in a RecursionError, not a stack overflow
+    let runner = thread::Builder::new().stack_size(Interpreter::STACK_SIZE).spawn(move || {
+        let mut interp = Interpreter::new();
+        interp.on_warning(|warning| eprintln!("warning: {}", warning));
+        f(&mut interp)
+    }).expect("cannot start the interpreter thread");
+    match runner.join() {
+        Ok(true) => {}
+        Ok(false) => process::exit(1),
+        // a panic has already been reported by the panic hook
+        Err(_) => process::exit(101),
+    }
+}
+
+/// Run the file at `path`, reporting any error. Returns whether it ran
+/// to the end.
+fn run(interp: &mut Interpreter, path: &Path) -> bool {
+    let result = interp.run_file(path);
+    match &result {
+        // the checker's errors, found before the file ran
+        Err(RuntimeError::Raised(error)) if !error.diagnostics.is_empty() => {
+            for diagnostic in &error.diagnostics {
+                eprintln!("Type error: {}", diagnostic);
+            }
+            eprintln!("Type checking failed: {}", error.message);
+        }
+        Err(e) => eprintln!("Runtime error: {}", e),
+        Ok(()) => {}
     }
+    result.is_ok()
+}
+
+fn read(path: &Path) -> String {
+    fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("Cannot read '{}': {}", path.display(), e);
+        process::exit(1);
+    })
+}
+
+/// Parse source text, or load a `.json` AST dump.
+/// Lower and resolve the file at `path`, or exit with its name error.
+fn resolve(path: &Path, ast: &Ast) -> Program {
+    let mut load = |import: &str| imported(path, import).map(|ast| ir::lower(&ast));
+    ir::resolve(ir::lower(ast), &mut load).unwrap_or_else(|e| {
+        eprintln!("NameError: {} ({}:{})", e, path.display(), e.span);
+        process::exit(1);
+    })
+}
+
+/// The AST of the file `import` names from the file at `path`, if it exists.
+fn imported(path: &Path, import: &str) -> Option<Ast> {
+    let file = path.parent().unwrap_or(Path::new("")).join(import);
+    file.exists().then(|| parse(&file))
+}
+
+fn parse(path: &Path) -> Ast {
+    let source = read(path);
+    if path.extension().is_some_and(|ext| ext == "json") {
+        Json::parse(&source).and_then(|json| dump::load(&json)).unwrap_or_else(|e| {
+            eprintln!("Invalid AST in '{}': {}", path.display(), e);
+            process::exit(1);
+        })
+    } else {
+        Parser::new(lex(path, &source)).parse()
+    }
+}
+
+/// Split source text into tokens, or report where it can't be.
+fn lex(path: &Path, source: &str) -> Vec<Token> {
+    tokenize(source).unwrap_or_else(|e| {
+        eprintln!("SyntaxError: {} ({}:{}:{})", e.message, path.display(), e.line, e.column);
+        process::exit(1);
+    })
 }
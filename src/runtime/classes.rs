@@ -0,0 +1,144 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::PathBuf;
+use std::rc::{Rc, Weak};
+
+use crate::ir::{ClassDecl, Expr, MethodDecl};
+use crate::runtime::{Frame, Value};
+
+/// A class at runtime. Methods are looked up through `vtable`, which
+/// already holds inherited methods with overrides applied.
+#[derive(Debug)]
+pub struct Class {
+    pub name: String,
+    /// Constructor parameters, which become the instance's own fields
+    pub params: Vec<String>,
+    /// Constructor parameters whose argument must implement an interface
+    pub bounds: Vec<(usize, String)>,
+    /// Fields declared `pub`; the rest are private to `module`
+    pub public_fields: HashSet<String>,
+    /// File the class was declared in
+    pub module: Option<PathBuf>,
+    /// Globals of `module`, which the `extends` arguments see
+    pub globals: Weak<RefCell<Frame>>,
+    /// Parent class and the constructor arguments passed to it
+    pub parent: Option<(Rc<Class>, Vec<Expr>)>,
+    pub vtable: HashMap<String, Rc<Method>>,
+}
+
+/// A method body together with the class that defined it.
+#[derive(Debug)]
+pub struct Method {
+    pub name: String,
+    /// Name of the defining class, for traces
+    pub owner: String,
+    /// File of the defining class; private methods are only callable from there
+    pub module: Option<PathBuf>,
+    /// Globals of `module`, which the body sees
+    pub globals: Weak<RefCell<Frame>>,
+    pub public: bool,
+    /// Parent of the defining class: where `super.x` resolves
+    pub parent: Option<Rc<Class>>,
+    pub params: Vec<String>,
+    pub bounds: Vec<(usize, String)>,
+    pub body: Rc<Expr>,
+}
+
+/// An object: its class plus field values, parent fields first.
+#[derive(Debug)]
+pub struct Instance {
+    pub class: Rc<Class>,
+    pub fields: Vec<(String, Value)>,
+}
+
+impl Class {
+    /// Build a class declared in `module` from its declaration, inheriting `parent`'s vtable.
+    pub fn new(decl: &ClassDecl, parent: Option<Rc<Class>>, module: Option<PathBuf>, globals: Weak<RefCell<Frame>>) -> Self {
+        let mut vtable = parent.as_ref().map(|p| p.vtable.clone()).unwrap_or_default();
+
+        for MethodDecl { name, public, params, bounds, body } in &decl.methods {
+            let method = Method {
+                name: name.clone(),
+                owner: decl.name.clone(),
+                module: module.clone(),
+                globals: globals.clone(),
+                public: *public,
+                parent: parent.clone(),
+                params: params.clone(),
+                bounds: bounds.clone(),
+                body: body.clone(),
+            };
+            // overriding simply replaces the inherited slot
+            vtable.insert(name.clone(), Rc::new(method));
+        }
+
+        let parent = parent.map(|p| (p, decl.parent.as_ref().map(|(_, args)| args.clone()).unwrap_or_default()));
+        Self {
+            name: decl.name.clone(),
+            params: decl.params.clone(),
+            bounds: decl.bounds.clone(),
+            public_fields: decl.public_fields.iter().cloned().collect(),
+            module,
+            globals,
+            parent,
+            vtable,
+        }
+    }
+
+    /// This class's name followed by its ancestors', nearest first.
+    pub fn lineage(&self) -> Vec<&str> {
+        let mut names = vec![self.name.as_str()];
+        if let Some((parent, _)) = &self.parent {
+            names.extend(parent.lineage());
+        }
+        names
+    }
+
+    /// The class in the hierarchy that declares field `name`, nearest first.
+    pub fn field_owner(&self, name: &str) -> Option<&Class> {
+        if self.params.iter().any(|p| p == name) {
+            Some(self)
+        } else {
+            self.parent.as_ref().and_then(|(p, _)| p.field_owner(name))
+        }
+    }
+
+    /// Whether this class is `other` or inherits from it.
+    pub fn is_subclass_of(&self, other: &Class) -> bool {
+        std::ptr::eq(self, other)
+            || self.parent.as_ref().is_some_and(|(p, _)| p.is_subclass_of(other))
+    }
+}
+
+impl Instance {
+    pub fn field(&self, name: &str) -> Option<Value> {
+        self.fields.iter().find(|(n, _)| n == name).map(|(_, v)| v.clone())
+    }
+}
+
+// Classes and instances compare by identity.
+impl PartialEq for Class {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl PartialEq for Instance {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl fmt::Display for Instance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {{", self.class.name)?;
+        for (i, (name, value)) in self.fields.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, " {}: {}", name, value)?;
+        }
+        write!(f, " }}")
+    }
+}
@@ -1,45 +1,105 @@
+use std::cell::{Ref, RefCell};
 use std::collections::HashMap;
+use std::rc::{Rc, Weak};
+
+use crate::ir::Slot;
 use crate::runtime::Value;
 
-/// Environment holds variables and whether they are constant.
+/// One scope's variables, indexed by the slots the resolver handed out.
+/// A slot stays empty until its declaration runs.
+pub type Frame = Vec<Option<Value>>;
+
+/// Environment holds the variables of the running code.
 ///
-/// name -> (value, is_const)
-#[derive(Debug, Default)]
+/// The module's globals, then one frame per open block, innermost last.
+/// Variables are found by their resolved slot rather than by name.
+#[derive(Debug, Default, PartialEq)]
 pub struct Environment {
-    store: HashMap<String, (Value, bool)>,
+    /// Slot of each global, for `module.name` from other modules
+    names: HashMap<String, usize>,
+    /// Shared with the classes the module declares, so their methods see
+    /// these globals wherever they are called from.
+    globals: Rc<RefCell<Frame>>,
+    /// Only `get` borrows through the cell; everything else has `&mut self`
+    /// and goes through `get_mut`.
+    frames: RefCell<Vec<Frame>>,
+}
+
+/// What `enter_call` set aside, to hand back to `exit_call`.
+pub struct Caller {
+    globals: Rc<RefCell<Frame>>,
+    frames: Vec<Frame>,
 }
 
 impl Environment {
     pub fn new() -> Self {
-        Self { store: HashMap::new() }
+        Self::default()
+    }
+
+    /// Name the global slots of the module about to run.
+    pub fn declare_globals(&mut self, names: &[String]) {
+        self.names = names.iter().cloned().enumerate().map(|(slot, name)| (name, slot)).collect();
+    }
+
+    /// Open a new innermost scope (entering a block).
+    pub fn push_scope(&mut self) {
+        self.frames.get_mut().push(Frame::new());
+    }
+
+    /// Drop the innermost scope and everything defined in it.
+    pub fn pop_scope(&mut self) {
+        self.frames.get_mut().pop();
+    }
+
+    /// Handle to the globals, for the classes declared here.
+    pub fn globals(&self) -> Weak<RefCell<Frame>> {
+        Rc::downgrade(&self.globals)
     }
 
-    /// Define a new variable. Returns error if already exists and is const.
-    pub fn define(&mut self, name: String, value: Value, is_const: bool) -> Result<(), String> {
-        if let Some((_, existing_const)) = self.store.get(&name) {
-            if *existing_const {
-                return Err(format!("Cannot redefine constant '{}'", name));
-            }
+    /// Enter a method call: run under `globals` in a single `frame`, hiding
+    /// the caller's scopes. Returns what was hidden.
+    pub fn enter_call(&mut self, globals: &Weak<RefCell<Frame>>, frame: Frame) -> Caller {
+        let globals = globals.upgrade().expect("a module's globals outlive the classes it declares");
+        Caller {
+            globals: std::mem::replace(&mut self.globals, globals),
+            frames: std::mem::replace(self.frames.get_mut(), vec![frame]),
         }
-        self.store.insert(name, (value, is_const));
-        Ok(())
-    }
-
-    /// Assign to an existing variable. Error if it doesn't exist or is const.
-    pub fn assign(&mut self, name: &str, value: Value) -> Result<(), String> {
-        if let Some(entry) = self.store.get_mut(name) {
-            if entry.1 {
-                return Err(format!("Cannot assign to constant '{}'", name));
-            }
-            entry.0 = value;
-            Ok(())
-        } else {
-            Err(format!("Undefined variable '{}'", name))
+    }
+
+    /// Leave a method call, restoring the caller's scopes.
+    pub fn exit_call(&mut self, caller: Caller) {
+        self.globals = caller.globals;
+        *self.frames.get_mut() = caller.frames;
+    }
+
+    /// Set `slot` of the innermost scope.
+    pub fn define(&mut self, slot: usize, value: Value) {
+        match self.frames.get_mut().last_mut() {
+            Some(frame) => set(frame, slot, value),
+            None => set(&mut self.globals.borrow_mut(), slot, value),
         }
     }
 
-    /// Get a variable's value.
-    pub fn get(&self, name: &str) -> Option<Value> {
-        self.store.get(name).map(|(v, _)| v.clone())
+    /// A variable's value, or `None` if its declaration has not run yet.
+    /// Borrowed, so a caller that only inspects it does not copy it.
+    pub fn get(&self, slot: Slot) -> Option<Ref<'_, Value>> {
+        let frames = self.frames.borrow();
+        match frames.len().checked_sub(slot.depth + 1) {
+            Some(frame) => Ref::filter_map(frames, |frames| frames[frame].get(slot.index)?.as_ref()).ok(),
+            None => Ref::filter_map(self.globals.borrow(), |globals| globals.get(slot.index)?.as_ref()).ok(),
+        }
+    }
+
+    /// A global by name.
+    pub fn global(&self, name: &str) -> Option<Value> {
+        let slot = *self.names.get(name)?;
+        self.globals.borrow().get(slot).cloned().flatten()
+    }
+}
+
+fn set(frame: &mut Frame, slot: usize, value: Value) {
+    if frame.len() <= slot {
+        frame.resize(slot + 1, None);
     }
+    frame[slot] = Some(value);
 }
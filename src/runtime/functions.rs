@@ -0,0 +1,52 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::path::PathBuf;
+use std::rc::{Rc, Weak};
+
+use crate::ir::{Expr, FunctionDecl};
+use crate::runtime::Frame;
+
+/// A declared function. Type parameters and annotations are erased when
+/// lowering: the runtime is dynamically typed, so a generic function works
+/// for any argument it is given. Bounds are the exception, and are checked
+/// against each argument when the function is called.
+#[derive(Debug)]
+pub struct Function {
+    pub name: String,
+    pub params: Vec<String>,
+    /// Parameter index and the interface its argument must implement,
+    /// for each parameter typed as a bounded `T`
+    pub bounds: Vec<(usize, String)>,
+    pub body: Rc<Expr>,
+    /// File the function was declared in
+    pub module: Option<PathBuf>,
+    /// Globals of `module`, which the body sees
+    pub globals: Weak<RefCell<Frame>>,
+}
+
+impl Function {
+    /// A function declared by `decl` in `module`, whose globals are `globals`.
+    pub fn new(decl: &FunctionDecl, module: Option<PathBuf>, globals: Weak<RefCell<Frame>>) -> Self {
+        Self {
+            name: decl.name.clone(),
+            params: decl.params.clone(),
+            bounds: decl.bounds.clone(),
+            body: decl.body.clone(),
+            module,
+            globals,
+        }
+    }
+}
+
+/// Functions are compared by identity.
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<fn {}>", self.name)
+    }
+}
@@ -0,0 +1,133 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::PathBuf;
+use std::rc::{Rc, Weak};
+
+use crate::ir::{ExprKind, ImplDecl, InterfaceDecl, Stmt, StmtKind};
+use crate::runtime::{Frame, Function};
+
+/// An interface at runtime: the methods an implementing type must provide.
+#[derive(Debug, PartialEq)]
+pub struct Interface {
+    pub name: String,
+    /// Method name and parameter count, `self` included
+    pub methods: Vec<(String, usize)>,
+}
+
+impl Interface {
+    pub fn new(decl: &InterfaceDecl) -> Self {
+        Self {
+            name: decl.name.clone(),
+            methods: decl.methods.clone(),
+        }
+    }
+}
+
+impl fmt::Display for Interface {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<interface {}>", self.name)
+    }
+}
+
+/// Check every top-level `impl` in `program` against the interface it
+/// names, so a non-conforming impl stops the file before any of it runs.
+/// The interface must be declared in the same file.
+pub fn check_impls(program: &[Stmt]) -> Result<(), String> {
+    let interfaces: HashMap<&str, Interface> = program.iter()
+        .filter_map(|stmt| match &stmt.kind {
+            StmtKind::Let { value, .. } => match &value.kind {
+                ExprKind::Interface(decl) => Some((decl.name.as_str(), Interface::new(decl))),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+
+    for stmt in program {
+        let StmtKind::Impl(decl) = &stmt.kind else { continue };
+        let interface = interfaces.get(decl.interface.as_str()).ok_or_else(|| format!(
+            "Cannot implement unknown interface '{}' for {}", decl.interface, decl.target
+        ))?;
+        check_impl(interface, decl)?;
+    }
+    Ok(())
+}
+
+/// `decl` must define each of `interface`'s methods with the same number
+/// of parameters, and nothing else.
+fn check_impl(interface: &Interface, decl: &ImplDecl) -> Result<(), String> {
+    let header = format!("impl {} for {}", decl.interface, decl.target);
+
+    for (i, method) in decl.methods.iter().enumerate() {
+        if decl.methods[..i].iter().any(|m| m.name == method.name) {
+            return Err(format!("{} defines '{}' twice", header, method.name));
+        }
+        match interface.methods.iter().find(|(name, _)| *name == method.name) {
+            Some((_, arity)) if *arity != method.params.len() => return Err(format!(
+                "{}: '{}' takes {} parameter(s) but {} declares {}",
+                header, method.name, method.params.len(), interface.name, arity
+            )),
+            Some(_) => {}
+            None => return Err(format!(
+                "{}: '{}' is not a method of {}", header, method.name, interface.name
+            )),
+        }
+    }
+
+    if let Some((missing, _)) = interface.methods.iter()
+        .find(|(name, _)| !decl.methods.iter().any(|m| m.name == *name))
+    {
+        return Err(format!("{} is missing method '{}'", header, missing));
+    }
+    Ok(())
+}
+
+/// Every method registered through `impl`, looked up by the runtime type
+/// name of the receiver, so calls dispatch on the value rather than on
+/// how it was declared.
+#[derive(Debug, Default)]
+pub struct ImplTable {
+    /// (type name, method name) -> method
+    methods: HashMap<(String, String), Rc<Function>>,
+    /// (type name, interface name)
+    implemented: HashSet<(String, String)>,
+}
+
+impl ImplTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a checked impl's methods for its target type. `module` is the
+    /// file declaring it and `globals` its globals, which the methods see.
+    pub fn register(&mut self, decl: &ImplDecl, module: Option<PathBuf>, globals: Weak<RefCell<Frame>>) -> Result<(), String> {
+        let key = (decl.target.clone(), decl.interface.clone());
+        if self.implemented.contains(&key) {
+            return Err(format!("{} is already implemented for {}", decl.interface, decl.target));
+        }
+        if let Some(method) = decl.methods.iter()
+            .find(|m| self.methods.contains_key(&(decl.target.clone(), m.name.clone())))
+        {
+            return Err(format!("{} already has a method '{}'", decl.target, method.name));
+        }
+
+        for method in &decl.methods {
+            let name = method.name.clone();
+            let mut function = Function::new(method, module.clone(), globals.clone());
+            function.name = format!("{}.{}", decl.target, name);
+            self.methods.insert((decl.target.clone(), name), Rc::new(function));
+        }
+        self.implemented.insert(key);
+        Ok(())
+    }
+
+    /// The method `name` for values of type `ty`, if some impl defines it.
+    pub fn method(&self, ty: &str, name: &str) -> Option<Rc<Function>> {
+        self.methods.get(&(ty.to_string(), name.to_string())).cloned()
+    }
+
+    pub fn implements(&self, ty: &str, interface: &str) -> bool {
+        self.implemented.contains(&(ty.to_string(), interface.to_string()))
+    }
+}
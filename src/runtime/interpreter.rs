@@ -1,11 +1,84 @@
-use crate::ast::{Expr, Stmt};
-use crate::lexer::Operator;
-use crate::runtime::{Environment, Value};
+use std::cell::RefCell;
+use std::fmt;
+use std::fs;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::ast::json::Json;
+use crate::ast::{attributes, dump, Ast, Span};
+use crate::ir::{self, Catch, Expr, ExprKind, Literal, Pattern, Prim, Program, Slot, Stmt, StmtKind, Var};
+use crate::lexer::{tokenize, Operator};
+use crate::parser::Parser;
+use crate::typeck;
+use crate::runtime::modules::{display_path, module_name};
+use crate::runtime::{
+    check_impls, Class, Cursor, Diagnostic, Environment, ErrorValue, Function, ImplTable, Instance, Interface, Method,
+    Module, ModuleLoader, RangeValue, TypeDef, TypeRef, Value,
+};
+
+/// Function and method calls nested deeper than this raise a `RecursionError`. Each
+/// call takes several interpreter frames on the host stack, so running
+/// this deep needs more than a default thread's stack; see
+/// [`Interpreter::STACK_SIZE`].
+pub const MAX_CALL_DEPTH: usize = 1000;
 
 /// Errors that can happen while interpreting.
 #[derive(Debug)]
 pub enum RuntimeError {
     Message(String),
+    /// An error value in flight, catchable with `try`/`catch`.
+    Raised(ErrorValue),
+    /// Early exit from `expr?` carrying the `err`/`none` being returned.
+    /// Not an error value: `catch` lets it through and the enclosing function
+    /// returns it. At the top level of a file, which has no function to
+    /// return from, `?` raises an error instead.
+    Return(Value),
+}
+
+impl RuntimeError {
+    /// Raise an error of the given kind. The trace is filled in by the
+    /// statement that fails.
+    pub fn raise(kind: &str, message: impl Into<String>) -> Self {
+        RuntimeError::Raised(ErrorValue {
+            kind: kind.to_string(),
+            message: message.into(),
+            location: None,
+            trace: Vec::new(),
+            diagnostics: Vec::new(),
+        })
+    }
+
+    /// The script-level error value for this error.
+    pub fn into_error_value(self) -> ErrorValue {
+        match self {
+            RuntimeError::Message(message) => ErrorValue {
+                kind: "RuntimeError".to_string(),
+                message,
+                location: None,
+                trace: Vec::new(),
+                diagnostics: Vec::new(),
+            },
+            RuntimeError::Raised(e) => e,
+            RuntimeError::Return(v) => ErrorValue {
+                kind: "RuntimeError".to_string(),
+                message: format!("Unhandled {}", v),
+                location: None,
+                trace: Vec::new(),
+                diagnostics: Vec::new(),
+            },
+        }
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuntimeError::Message(m) => write!(f, "{}", m),
+            RuntimeError::Raised(e) => write!(f, "{}", e),
+            RuntimeError::Return(v) => write!(f, "Unhandled {}", v),
+        }
+    }
 }
 
 impl From<String> for RuntimeError {
@@ -18,84 +91,929 @@ impl From<&str> for RuntimeError {
 /// The interpreter. Keeps an environment and executes statements.
 pub struct Interpreter {
     pub env: Environment,
+    modules: ModuleLoader,
+    /// Methods from every `impl` run so far, in any module
+    impls: ImplTable,
+    /// Functions and methods currently executing, innermost last.
+    call_stack: Vec<Call>,
+    /// Names the running module has declared `pub`.
+    exports: HashSet<String>,
+    /// Called with each lint warning as its file is loaded, before any
+    /// of the file runs
+    on_warning: Box<dyn FnMut(Diagnostic)>,
+}
+
+/// A running call. Methods keep their definition so `super` can find the parent.
+enum Call {
+    Function { name: String, module: Option<PathBuf> },
+    Method(Rc<Method>),
+}
+
+impl Call {
+    fn name(&self) -> String {
+        match self {
+            Call::Function { name, .. } => name.clone(),
+            Call::Method(m) => format!("{}.{}", m.owner, m.name),
+        }
+    }
+
+    /// File the running code was declared in.
+    fn module(&self) -> &Option<PathBuf> {
+        match self {
+            Call::Function { module, .. } => module,
+            Call::Method(m) => &m.module,
+        }
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Interpreter {
+    /// Stack size for a thread that runs scripts, enough for
+    /// [`MAX_CALL_DEPTH`] calls in an unoptimized build.
+    pub const STACK_SIZE: usize = 256 * 1024 * 1024;
+
     pub fn new() -> Self {
-        Self { env: Environment::new() }
+        Self {
+            env: Environment::new(),
+            modules: ModuleLoader::new(),
+            impls: ImplTable::new(),
+            call_stack: Vec::new(),
+            exports: HashSet::new(),
+            on_warning: Box::new(|_| {}),
+        }
+    }
+
+    /// Load and run a source file as the entry module.
+    /// Imports inside it resolve relative to its directory.
+    pub fn run_file(&mut self, path: &Path) -> Result<(), RuntimeError> {
+        let path = path.canonicalize()
+            .map_err(|e| RuntimeError::raise("ImportError", format!("Cannot read '{}': {}", path.display(), e)))?;
+        self.modules.enter(path.clone()).map_err(|e| RuntimeError::raise("ImportError", e))?;
+        let result = self.parse_file(&path).and_then(|program| self.run(&program));
+        self.modules.exit();
+        result
     }
 
-    /// Run a sequence of statements.
-    pub fn run(&mut self, statements: Vec<Stmt>) -> Result<(), RuntimeError> {
+    /// Report each lint warning to `f` as its file is loaded, before the
+    /// file runs. Warnings are dropped until this is called.
+    pub fn on_warning(&mut self, f: impl FnMut(Diagnostic) + 'static) {
+        self.on_warning = Box::new(f);
+    }
+
+    /// Run a resolved program's top-level statements, after checking its
+    /// impls against their interfaces.
+    pub fn run(&mut self, program: &Program) -> Result<(), RuntimeError> {
+        check_impls(&program.body).map_err(|e| RuntimeError::raise("TypeError", e))?;
+        self.env.declare_globals(&program.globals);
+        self.exec_stmts(&program.body, None).map(|_| ())
+    }
+
+    /// Run statements, then evaluate the optional tail expression.
+    /// Returns the tail's value (`none` without one).
+    fn exec_stmts(&mut self, statements: &[Stmt], tail: Option<&Expr>) -> Result<Value, RuntimeError> {
         for stmt in statements {
-            self.exec_stmt(stmt)?;
+            self.exec_stmt(stmt).map_err(|e| self.with_trace(e))?;
         }
-        Ok(())
+        match tail {
+            Some(tail) => self.eval_expr(tail).map_err(|e| self.with_trace(e)),
+            None => Ok(Value::None),
+        }
+    }
+
+    /// Run statements in a fresh scope.
+    fn exec_block(&mut self, statements: &[Stmt], tail: Option<&Expr>) -> Result<Value, RuntimeError> {
+        self.env.push_scope();
+        let result = self.exec_stmts(statements, tail);
+        self.env.pop_scope();
+        result
     }
 
-    fn exec_stmt(&mut self, stmt: Stmt) -> Result<(), RuntimeError> {
+    /// Turn any error into a raised error value, recording where it happened.
+    fn with_trace(&self, err: RuntimeError) -> RuntimeError {
+        if let RuntimeError::Return(_) = err {
+            return err;
+        }
+        let mut error = err.into_error_value();
+        if error.trace.is_empty() {
+            error.trace = self.call_stack.iter().rev()
+                .map(Call::name)
+                .chain(self.modules.trace())
+                .collect();
+        }
+        RuntimeError::Raised(error)
+    }
+
+    /// Record `span` as where the error happened, unless a more deeply
+    /// nested node already did. Nodes loaded from JSON without a span have
+    /// no position, so the error is left for an enclosing node to place.
+    fn locate(&self, err: RuntimeError, span: Span) -> RuntimeError {
+        if matches!(err, RuntimeError::Return(_)) || span == Span::default() {
+            return err;
+        }
+        let mut error = err.into_error_value();
+        if error.location.is_none() {
+            error.location = Some(match self.current_module() {
+                Some(path) => format!("{}:{}", display_path(&path), span),
+                None => span.to_string(),
+            });
+        }
+        RuntimeError::Raised(error)
+    }
+
+    fn exec_stmt(&mut self, stmt: &Stmt) -> Result<(), RuntimeError> {
+        self.exec_stmt_kind(&stmt.kind).map_err(|e| self.locate(e, stmt.span))
+    }
+
+    fn exec_stmt_kind(&mut self, stmt: &StmtKind) -> Result<(), RuntimeError> {
         match stmt {
-            Stmt::VarDeclaration { constant, name, value } => {
+            StmtKind::Let { pattern, value, .. } => {
                 let val = self.eval_expr(value)?;
-                // if variable exists and is const, Environment::define handles it
-                self.env.define(name, val, constant)
-                    .map_err(RuntimeError::from)
+                self.bind_pattern(pattern, val)
             }
 
-            Stmt::Print { newline, expr } => {
-                let v = self.eval_expr(expr)?;
-                if newline {
-                    println!("{}", v);
-                } else {
-                    print!("{}", v);
+            StmtKind::Expr(expr) => {
+                self.eval_expr(expr)?;
+                Ok(())
+            }
+
+            StmtKind::While { condition, body } => {
+                while self.eval_condition(condition)? {
+                    self.exec_block(body, None)?;
+                }
+                Ok(())
+            }
+
+            StmtKind::Throw(expr) => {
+                match self.eval_expr(expr)? {
+                    // rethrow keeps the original kind and trace
+                    Value::Error(e) => Err(RuntimeError::Raised((*e).clone())),
+                    Value::Str(message) => Err(RuntimeError::raise("Error", message)),
+                    other => Err(RuntimeError::raise("Error", other.to_string_value())),
                 }
+            }
+
+            StmtKind::Return(expr) => {
+                let value = self.eval_expr(expr)?;
+                if self.call_stack.is_empty() {
+                    return Err(RuntimeError::raise("RuntimeError", format!("'?' got {} outside of a function", value)));
+                }
+                Err(RuntimeError::Return(value))
+            }
+
+            StmtKind::Export(names) => {
+                self.exports.extend(names.iter().cloned());
                 Ok(())
             }
+
+            StmtKind::Impl(decl) => self.impls.register(decl, self.current_module(), self.env.globals())
+                .map_err(|e| RuntimeError::raise("TypeError", e)),
+        }
+    }
+
+    /// Run a catch body with the caught error bound to its name.
+    fn exec_catch(&mut self, clause: &Catch, err: RuntimeError) -> Result<Value, RuntimeError> {
+        self.env.push_scope();
+        if let Some(name) = &clause.name {
+            let value = Value::Error(Rc::new(err.into_error_value()));
+            self.env.define(slot(name).index, value);
+        }
+        let result = self.exec_stmts(&clause.body, None);
+        self.env.pop_scope();
+        result
+    }
+
+    /// Load a module by its import path, running it at most once.
+    /// Each module executes in its own environment, which becomes its namespace.
+    fn load_module(&mut self, path: &str) -> Result<Rc<Module>, RuntimeError> {
+        let resolved = self.modules.resolve(path)
+            .map_err(|e| RuntimeError::raise("ImportError", e))?;
+        if let Some(module) = self.modules.cached(&resolved) {
+            return Ok(module);
+        }
+
+        self.modules.enter(resolved.clone())
+            .map_err(|e| RuntimeError::raise("ImportError", e))?;
+        let result = self.parse_file(&resolved).and_then(|program| {
+            let outer = std::mem::take(&mut self.env);
+            let outer_exports = std::mem::take(&mut self.exports);
+            let result = self.run(&program);
+            let env = std::mem::replace(&mut self.env, outer);
+            let exports = std::mem::replace(&mut self.exports, outer_exports);
+            result.map(|_| (env, exports))
+        });
+        self.modules.exit();
+
+        let (env, exports) = result?;
+        let module = Rc::new(Module {
+            name: module_name(&resolved),
+            path: resolved,
+            env,
+            exports,
+        });
+        self.modules.insert(module.clone());
+        Ok(module)
+    }
+
+    /// Read, parse, lower, resolve and type check a source file, or a
+    /// `.json` AST. Names that nothing declares and ill-typed code are
+    /// reported here, before anything runs.
+    fn parse_file(&mut self, path: &Path) -> Result<Program, RuntimeError> {
+        let ast = read_ast(path).map_err(|e| self.with_trace(e))?;
+
+        let warnings = attributes::lint(&ast, &mut |import| self.read_import(import));
+        for (span, message) in warnings {
+            (self.on_warning)(Diagnostic { message, location: format!("{}:{}", display_path(path), span) });
+        }
+
+        let program = ir::resolve(ir::lower(&ast), &mut |import| self.read_import(import).map(|ast| ir::lower(&ast)))
+            .map_err(|e| {
+            let mut error = RuntimeError::raise("NameError", e.message).into_error_value();
+            error.location = Some(format!("{}:{}", display_path(path), e.span));
+            self.with_trace(RuntimeError::Raised(error))
+        })?;
+
+        if let Err(errors) = typeck::check(&ast, &mut |import| self.read_import(import)) {
+            let mut error = RuntimeError::raise("TypeError", format!(
+                "'{}' has {} type error(s)", display_path(path), errors.len()
+            )).into_error_value();
+            error.diagnostics = errors.into_iter()
+                .map(|(span, message)| Diagnostic { message, location: format!("{}:{}", display_path(path), span) })
+                .collect();
+            return Err(self.with_trace(RuntimeError::Raised(error)));
+        }
+        Ok(program)
+    }
+
+    /// The AST of the file an import in the file being loaded names, if it
+    /// can be read. Imports resolve relative to that file.
+    fn read_import(&self, import: &str) -> Option<Ast> {
+        read_ast(&self.modules.resolve(import).ok()?).ok()
+    }
+
+    /// Bind every name in `pattern` to the matching part of `value`.
+    fn bind_pattern(&mut self, pattern: &Pattern, value: Value) -> Result<(), RuntimeError> {
+        match (pattern, value) {
+            (Pattern::Identifier(var), val) => {
+                self.env.define(slot(var).index, val);
+                Ok(())
+            }
+
+            (Pattern::Tuple(patterns), Value::Tuple(values)) => {
+                if patterns.len() != values.len() {
+                    return Err(RuntimeError::Message(format!(
+                        "Cannot destructure a tuple of {} elements into {} names",
+                        values.len(),
+                        patterns.len()
+                    )));
+                }
+                for (p, v) in patterns.iter().zip(values) {
+                    self.bind_pattern(p, v)?;
+                }
+                Ok(())
+            }
+
+            (Pattern::Tuple(_), other) => Err(RuntimeError::Message(format!(
+                "Cannot destructure non-tuple value '{}'", other
+            ))),
         }
     }
 
     /// Evaluate an expression to a Value.
-    fn eval_expr(&mut self, expr: Expr) -> Result<Value, RuntimeError> {
+    fn eval_expr(&mut self, expr: &Expr) -> Result<Value, RuntimeError> {
+        self.eval_expr_kind(&expr.kind).map_err(|e| self.locate(e, expr.span))
+    }
+
+    fn eval_expr_kind(&mut self, expr: &ExprKind) -> Result<Value, RuntimeError> {
         match expr {
-            Expr::IntLiteral(i) => Ok(Value::Int(i)),
-            Expr::StringLiteral(s) => Ok(Value::Str(s)),
-            Expr::Identifier(name) => {
-                self.env.get(&name)
-                    .ok_or_else(|| RuntimeError::Message(format!("Undefined identifier '{}'", name)))
+            ExprKind::Literal(literal) => Ok(match literal {
+                Literal::Int(i) => Value::Int(*i),
+                Literal::Str(s) => Value::Str(s.clone()),
+                Literal::Bool(b) => Value::Bool(*b),
+                Literal::None => Value::None,
+            }),
+            ExprKind::Var(var) => self.var(var),
+            ExprKind::Tuple(items) => Ok(Value::Tuple(self.eval_args(items)?)),
+            ExprKind::Index { tuple, index } => {
+                match self.eval_expr(tuple)? {
+                    Value::Tuple(mut items) if *index < items.len() => Ok(items.swap_remove(*index)),
+                    Value::Tuple(items) => Err(RuntimeError::Message(format!(
+                        "Tuple index {} out of range for tuple of {} elements", index, items.len()
+                    ))),
+                    other => Err(RuntimeError::Message(format!("Cannot index non-tuple value '{}'", other))),
+                }
+            }
+            ExprKind::Member { object, name } => {
+                let target = self.eval_expr(object)?;
+                self.member(target, name)
+            }
+            ExprKind::Call { callee, args } => self.eval_call(callee, args),
+            ExprKind::Super { method, .. } => Err(RuntimeError::raise(
+                "TypeError", format!("'super.{}' must be called", method)
+            )),
+            ExprKind::Prim { op, args } => {
+                let args = self.eval_args(args)?;
+                self.prim(op, args)
+            }
+            ExprKind::Block { body, value } => self.exec_block(body, value.as_deref()),
+            ExprKind::If { condition, then_branch, else_branch } => {
+                if self.eval_condition(condition)? {
+                    self.eval_expr(then_branch)
+                } else if let Some(else_branch) = else_branch {
+                    self.eval_expr(else_branch)
+                } else {
+                    Ok(Value::None)
+                }
+            }
+            ExprKind::Try { body, value, catch, finally } => {
+                let mut result = self.exec_stmts(body, value.as_deref());
+
+                if let Some(clause) = catch {
+                    match result {
+                        Err(RuntimeError::Return(_)) => {}
+                        Err(err) => result = self.exec_catch(clause, err),
+                        Ok(_) => {}
+                    }
+                }
+
+                // an error inside `finally` replaces any pending one
+                if let Some(finally) = finally {
+                    self.exec_stmts(finally, None)?;
+                }
+
+                result
+            }
+            ExprKind::Function(decl) => {
+                let function = Function::new(decl, self.current_module(), self.env.globals());
+                Ok(Value::Function(Rc::new(function)))
+            }
+            ExprKind::Interface(decl) => Ok(Value::Interface(Rc::new(Interface::new(decl)))),
+            ExprKind::Class(decl) => {
+                let parent = match &decl.parent {
+                    Some((parent, _)) => match self.var(parent) {
+                        Ok(Value::Class(class)) => Some(class),
+                        Ok(other) => return Err(RuntimeError::raise(
+                            "TypeError", format!("Cannot extend '{}': not a class", other)
+                        )),
+                        Err(_) => return Err(RuntimeError::raise(
+                            "NameError", format!("Undefined class '{}'", parent.name)
+                        )),
+                    },
+                    None => None,
+                };
+                let class = Class::new(decl, parent, self.current_module(), self.env.globals());
+                Ok(Value::Class(Rc::new(class)))
+            }
+            ExprKind::TypeDecl { name, target, nominal } => {
+                let target = match TypeRef::primitive(&target.name) {
+                    Some(primitive) => primitive,
+                    None => match target.slot.and_then(|slot| self.env.get(slot)).as_deref() {
+                        Some(Value::Type(def)) => TypeRef::Def(def.clone()),
+                        Some(_) => return Err(RuntimeError::raise(
+                            "TypeError", format!("'{}' is not a type", target.name)
+                        )),
+                        None => return Err(RuntimeError::raise(
+                            "NameError", format!("Unknown type '{}'", target.name)
+                        )),
+                    },
+                };
+                let def = TypeDef { name: name.clone(), target, nominal: *nominal };
+                Ok(Value::Type(Rc::new(def)))
+            }
+            ExprKind::Import { path } => Ok(Value::Module(self.load_module(path)?)),
+        }
+    }
+
+    /// A variable's value. Resolution guarantees it is declared somewhere in
+    /// scope, but the declaration may not have run yet: a `finally` can read
+    /// a name its `try` never reached, and a method a global declared after
+    /// the call.
+    fn var(&self, var: &Var) -> Result<Value, RuntimeError> {
+        self.env.get(slot(var))
+            .map(|value| value.clone())
+            .ok_or_else(|| RuntimeError::raise("NameError", format!("Undefined identifier '{}'", var.name)))
+    }
+
+    /// Apply a built-in operation to its evaluated arguments.
+    fn prim(&self, op: &Prim, args: Vec<Value>) -> Result<Value, RuntimeError> {
+        let count = args.len();
+        let mut args = args.into_iter();
+        let mut arg = || args.next().expect("lowering passes every primitive its arguments");
+
+        match op {
+            Prim::Binary(op) => {
+                let (l, r) = (arg(), arg());
+                self.apply_binary_op(&l, op, &r)
+            }
+            Prim::Ok => Ok(Value::Ok(Box::new(arg()))),
+            Prim::Err => Ok(Value::Err(Box::new(arg()))),
+            Prim::Some => Ok(Value::Some(Box::new(arg()))),
+            Prim::Failed => match arg() {
+                Value::Ok(_) | Value::Some(_) => Ok(Value::Bool(false)),
+                Value::Err(_) | Value::None => Ok(Value::Bool(true)),
+                other => Err(RuntimeError::raise("TypeError", format!("'?' requires a Result or Option, got '{}'", other))),
+            },
+            Prim::Unwrap => match arg() {
+                Value::Ok(v) | Value::Some(v) => Ok(*v),
+                other => Err(RuntimeError::raise("TypeError", format!("Cannot unwrap '{}'", other))),
+            },
+            Prim::IsNone => Ok(Value::Bool(arg() == Value::None)),
+            Prim::LookThrough => match arg() {
+                Value::Some(v) => Ok(*v),
+                v => Ok(v),
+            },
+            Prim::Range { inclusive } => {
+                let start = int(arg(), "Range start")?;
+                let end = int(arg(), "Range end")?;
+                let step = if count > 2 { int(arg(), "Range step")? } else { 1 };
+                if step == 0 {
+                    return Err(RuntimeError::raise("ValueError", "Range step cannot be zero"));
+                }
+                Ok(Value::Range(RangeValue { start, end, inclusive: *inclusive, step }))
+            }
+            Prim::Slice => {
+                let target = arg();
+                let range = match arg() {
+                    Value::Range(r) => r,
+                    other => return Err(RuntimeError::raise("TypeError", format!("Cannot slice with '{}'; expected a range", other))),
+                };
+                self.slice(target, range)
+            }
+            Prim::Iter => {
+                let cursor = match arg() {
+                    Value::Range(r) => Cursor::new(r),
+                    Value::Tuple(items) => Cursor::of_items(items),
+                    Value::Str(s) => Cursor::of_items(s.chars().map(|c| Value::Str(c.to_string())).collect()),
+                    other => return Err(RuntimeError::raise("TypeError", format!("Cannot iterate over '{}'", other))),
+                };
+                Ok(Value::Cursor(Rc::new(RefCell::new(cursor))))
+            }
+            Prim::HasNext | Prim::Next => {
+                let Value::Cursor(cursor) = arg() else {
+                    unreachable!("lowering only walks cursors it made");
+                };
+                let mut cursor = cursor.borrow_mut();
+                Ok(match op {
+                    Prim::HasNext => Value::Bool(cursor.has_next()),
+                    _ => cursor.next().unwrap_or(Value::None),
+                })
+            }
+            Prim::Print { newline } => {
+                let v = arg();
+                if *newline {
+                    println!("{}", v);
+                } else {
+                    print!("{}", v);
+                }
+                Ok(Value::None)
+            }
+            // failures a script can expect are `err` values, not errors
+            Prim::ParseInt => {
+                let text = string(arg(), "parseInt")?;
+                Ok(match text.trim().parse::<i64>() {
+                    Ok(n) => Value::Ok(Box::new(Value::Int(n))),
+                    Err(_) => Value::Err(Box::new(Value::Str(format!("'{}' is not an integer", text)))),
+                })
+            }
+            Prim::ReadFile => {
+                let path = string(arg(), "readFile")?;
+                Ok(match fs::read_to_string(&path) {
+                    Ok(text) => Value::Ok(Box::new(Value::Str(text))),
+                    Err(e) => Value::Err(Box::new(Value::Str(format!("Cannot read '{}': {}", path, e)))),
+                })
+            }
+            Prim::WriteFile => {
+                let path = string(arg(), "writeFile")?;
+                let text = string(arg(), "writeFile")?;
+                Ok(match fs::write(&path, &text) {
+                    Ok(()) => Value::Ok(Box::new(Value::Int(text.len() as i64))),
+                    Err(e) => Value::Err(Box::new(Value::Str(format!("Cannot write '{}': {}", path, e)))),
+                })
+            }
+        }
+    }
+
+    /// `target.name` for modules, error values and instances.
+    fn member(&self, target: Value, name: &str) -> Result<Value, RuntimeError> {
+        match target {
+            Value::Module(module) => match module.env.global(name) {
+                Some(_) if !module.exports.contains(name) => Err(RuntimeError::raise(
+                    "NameError", format!("`{}` is private to module `{}`", name, module.name)
+                )),
+                Some(value) => Ok(value),
+                None => Err(RuntimeError::raise(
+                    "NameError", format!("Module '{}' has no member '{}'", module.name, name)
+                )),
+            },
+            Value::Error(e) => match name {
+                "kind" => Ok(Value::Str(e.kind.clone())),
+                "message" => Ok(Value::Str(e.message.clone())),
+                "trace" => Ok(Value::Tuple(e.trace.iter().cloned().map(Value::Str).collect())),
+                "location" => Ok(e.location.clone().map(Value::Str).unwrap_or(Value::None)),
+                _ => Err(RuntimeError::raise("NameError", format!("Error values have no member '{}'", name))),
+            },
+            Value::Newtype(def, inner) => match name {
+                "value" => Ok(*inner),
+                _ => Err(RuntimeError::raise("NameError", format!("'{}' has no member '{}'", def.name, name))),
+            },
+            Value::Instance(instance) => {
+                if let Some(owner) = instance.class.field_owner(name) {
+                    self.check_visible(owner.public_fields.contains(name), &owner.module, name)?;
+                }
+                instance.field(name).ok_or_else(|| {
+                    if instance.class.vtable.contains_key(name) {
+                        RuntimeError::raise("TypeError", format!("Method '{}.{}' must be called", instance.class.name, name))
+                    } else {
+                        RuntimeError::raise("NameError", format!("'{}' has no field '{}'", instance.class.name, name))
+                    }
+                })
+            }
+            other => Err(RuntimeError::Message(format!("Cannot access member '{}' on '{}'", name, other))),
+        }
+    }
+
+    fn eval_call(&mut self, callee: &Expr, args: &[Expr]) -> Result<Value, RuntimeError> {
+        match &callee.kind {
+            // value.method(...) dispatches on the receiver: a class's own
+            // methods first, then impls for its type
+            ExprKind::Member { object, name } => {
+                let target = self.eval_expr(object)?;
+                if let Value::Instance(instance) = &target {
+                    if let Some(method) = instance.class.vtable.get(name).cloned() {
+                        self.check_visible(method.public, &method.module, name)?;
+                        let args = self.eval_args(args)?;
+                        return self.invoke_method(method, target, args);
+                    }
+                }
+                if let Some(method) = self.impl_method(&target, name) {
+                    let mut values = vec![target];
+                    values.extend(self.eval_args(args)?);
+                    return self.invoke(&method, values);
+                }
+                if matches!(target, Value::Module(_) | Value::Instance(_)) {
+                    let callee = self.member(target, name)?;
+                    let args = self.eval_args(args)?;
+                    return self.call_value(callee, args);
+                }
+                Err(RuntimeError::raise("NameError", format!("{} has no method '{}'", target.type_name(), name)))
+            }
+
+            // super.method(...) starts the lookup at the defining class's parent
+            ExprKind::Super { method: name, this } => {
+                let current = match self.call_stack.last() {
+                    Some(Call::Method(method)) => method.clone(),
+                    _ => return Err(RuntimeError::raise("TypeError", "'super' used outside of a method")),
+                };
+                let method = current.parent.as_ref()
+                    .and_then(|parent| parent.vtable.get(name).cloned())
+                    .ok_or_else(|| RuntimeError::raise(
+                        "NameError", format!("No parent method '{}' for '{}.{}'", name, current.owner, current.name)
+                    ))?;
+                self.check_visible(method.public, &method.module, name)?;
+                let this = self.var(this)?;
+                let args = self.eval_args(args)?;
+                self.invoke_method(method, this, args)
+            }
+
+            _ => {
+                let callee = self.eval_expr(callee)?;
+                let args = self.eval_args(args)?;
+                self.call_value(callee, args)
+            }
+        }
+    }
+
+    /// The impl method `name` for `target`; instances also see impls
+    /// written for their ancestors.
+    fn impl_method(&self, target: &Value, name: &str) -> Option<Rc<Function>> {
+        match target {
+            Value::Instance(instance) => instance.class.lineage().into_iter()
+                .find_map(|ty| self.impls.method(ty, name)),
+            other => self.impls.method(other.type_name(), name),
+        }
+    }
+
+    /// Whether `value` satisfies the bound `interface`, directly or, for
+    /// instances, through an ancestor.
+    fn implements(&self, value: &Value, interface: &str) -> bool {
+        match value {
+            Value::Instance(instance) => instance.class.lineage().into_iter()
+                .any(|ty| self.impls.implements(ty, interface)),
+            other => self.impls.implements(other.type_name(), interface),
+        }
+    }
+
+    /// Fail unless each bounded argument implements its interface.
+    fn check_bounds(&self, owner: &str, params: &[String], bounds: &[(usize, String)], args: &[Value]) -> Result<(), RuntimeError> {
+        for (i, bound) in bounds {
+            if !self.implements(&args[*i], bound) {
+                return Err(RuntimeError::raise("TypeError", format!(
+                    "{}: argument '{}' is {}, which does not implement {}",
+                    owner, params[*i], args[*i].type_name(), bound
+                )));
             }
-            Expr::Binary { left, op, right } => {
-                let l = self.eval_expr(*left)?;
-                let r = self.eval_expr(*right)?;
-                self.apply_binary_op(&l, &op, &r)
+        }
+        Ok(())
+    }
+
+    /// The file whose code is running: the defining file of the innermost
+    /// function or method, or else the module being executed.
+    fn current_module(&self) -> Option<PathBuf> {
+        match self.call_stack.last() {
+            Some(call) => call.module().clone(),
+            None => self.modules.current().map(Path::to_path_buf),
+        }
+    }
+
+    /// Non-`pub` fields and methods are only usable from their own module.
+    fn check_visible(&self, public: bool, module: &Option<PathBuf>, name: &str) -> Result<(), RuntimeError> {
+        if public || *module == self.current_module() {
+            return Ok(());
+        }
+        let owner = module.as_deref().map(module_name).unwrap_or_default();
+        Err(RuntimeError::raise("NameError", format!("`{}` is private to module `{}`", name, owner)))
+    }
+
+    fn eval_args(&mut self, args: &[Expr]) -> Result<Vec<Value>, RuntimeError> {
+        args.iter().map(|arg| self.eval_expr(arg)).collect()
+    }
+
+    /// Call a function, class or type with `args`, as `callee(args)` does.
+    pub fn call_value(&mut self, callee: Value, args: Vec<Value>) -> Result<Value, RuntimeError> {
+        match callee {
+            Value::Function(function) => self.invoke(&function, args),
+            Value::Class(class) => {
+                let fields = self.class_fields(&class, args)?;
+                Ok(Value::Instance(Rc::new(Instance { class, fields })))
+            }
+            Value::Type(def) => {
+                let value = match <[Value; 1]>::try_from(args) {
+                    Ok([value]) => value,
+                    Err(args) => return Err(RuntimeError::raise("TypeError", format!(
+                        "{} takes 1 argument but {} were given", def.name, args.len()
+                    ))),
+                };
+                if !def.target.accepts(&value) {
+                    let expected = if def.nominal { format!("{} for {}", def.target, def.name) } else { def.to_string() };
+                    return Err(RuntimeError::raise("TypeError", format!("Expected {}, got '{}'", expected, value)));
+                }
+                if def.nominal {
+                    Ok(Value::Newtype(def, Box::new(value)))
+                } else {
+                    Ok(value)
+                }
             }
+            other => Err(RuntimeError::raise("TypeError", format!("'{}' is not callable", other))),
         }
     }
 
+    /// Run a function with its parameters bound. Its body sees globals and
+    /// its own parameters; the trailing expression is the result, unless a
+    /// `?` returns early.
+    fn invoke(&mut self, function: &Function, args: Vec<Value>) -> Result<Value, RuntimeError> {
+        if args.len() != function.params.len() {
+            return Err(RuntimeError::raise("TypeError", format!(
+                "{} takes {} argument(s) but {} were given",
+                function.name, function.params.len(), args.len()
+            )));
+        }
+        self.check_bounds(&function.name, &function.params, &function.bounds, &args)?;
+        self.check_depth(&function.name)?;
+
+        let caller = self.env.enter_call(&function.globals, args.into_iter().map(Some).collect());
+        self.call_stack.push(Call::Function { name: function.name.clone(), module: function.module.clone() });
+        let result = self.eval_expr(&function.body)
+            .map_err(|e| self.with_trace(e))
+            .or_else(|e| match e {
+                RuntimeError::Return(value) => Ok(value),
+                e => Err(e),
+            });
+        self.call_stack.pop();
+        self.env.exit_call(caller);
+        result
+    }
+
+    /// Field values for a new instance of `class`: the parent's fields
+    /// (built from the `extends` arguments) followed by its own.
+    fn class_fields(&mut self, class: &Class, args: Vec<Value>) -> Result<Vec<(String, Value)>, RuntimeError> {
+        if args.len() != class.params.len() {
+            return Err(RuntimeError::raise("TypeError", format!(
+                "{} takes {} argument(s) but {} were given", class.name, class.params.len(), args.len()
+            )));
+        }
+        self.check_bounds(&class.name, &class.params, &class.bounds, &args)?;
+        let own: Vec<(String, Value)> = class.params.iter().cloned().zip(args).collect();
+
+        let mut fields = match &class.parent {
+            Some((parent, parent_args)) => {
+                let frame = own.iter().map(|(_, value)| Some(value.clone())).collect();
+                let caller = self.env.enter_call(&class.globals, frame);
+                let values = self.eval_args(parent_args);
+                self.env.exit_call(caller);
+                self.class_fields(parent, values?)?
+            }
+            None => Vec::new(),
+        };
+
+        for (name, value) in own {
+            match fields.iter_mut().find(|(field, _)| *field == name) {
+                Some(slot) => slot.1 = value,
+                None => fields.push((name, value)),
+            }
+        }
+        Ok(fields)
+    }
+
+    /// Run a method with `self` and its parameters bound. A `?` that exits
+    /// early inside the body becomes the method's result.
+    fn invoke_method(&mut self, method: Rc<Method>, this: Value, args: Vec<Value>) -> Result<Value, RuntimeError> {
+        let owner = format!("{}.{}", method.owner, method.name);
+        if args.len() != method.params.len() {
+            return Err(RuntimeError::raise("TypeError", format!(
+                "{} takes {} argument(s) but {} were given", owner, method.params.len(), args.len()
+            )));
+        }
+        self.check_bounds(&owner, &method.params, &method.bounds, &args)?;
+
+        self.check_depth(&owner)?;
+
+        // `self` then the parameters, in the slots the resolver gave them
+        let frame = std::iter::once(this).chain(args).map(Some).collect();
+        let caller = self.env.enter_call(&method.globals, frame);
+        self.call_stack.push(Call::Method(method.clone()));
+        let result = self.eval_expr(&method.body);
+        self.call_stack.pop();
+        self.env.exit_call(caller);
+
+        match result {
+            Err(RuntimeError::Return(value)) => Ok(value),
+            result => result,
+        }
+    }
+
+    /// A `RecursionError` if calling `name` would nest deeper than [`MAX_CALL_DEPTH`].
+    fn check_depth(&self, name: &str) -> Result<(), RuntimeError> {
+        if self.call_stack.len() >= MAX_CALL_DEPTH {
+            return Err(RuntimeError::raise("RecursionError", format!(
+                "Maximum call depth of {} exceeded calling {}", MAX_CALL_DEPTH, name
+            )));
+        }
+        Ok(())
+    }
+
+    /// Arithmetic and comparisons involving newtype values. `Meters + Meters`
+    /// stays `Meters`, scaling by an int is allowed, and anything that mixes
+    /// a newtype with another type is an error. `None` defers to the usual rules.
+    fn newtype_op(&self, left: &Value, op: &Operator, right: &Value) -> Option<Result<Value, RuntimeError>> {
+        use Operator::*;
+        let wrap = |def: &Rc<TypeDef>, result: Result<Value, RuntimeError>| {
+            result.map(|v| Value::Newtype(def.clone(), Box::new(v)))
+        };
+
+        match (left, right) {
+            (Value::Newtype(a, x), Value::Newtype(b, y)) if Rc::ptr_eq(a, b) => match op {
+                Plus | Minus => Some(wrap(a, self.apply_binary_op(x, op, y))),
+                Less | LessEqual | Greater | GreaterEqual => Some(self.apply_binary_op(x, op, y)),
+                _ => None,
+            },
+            (Value::Newtype(a, _), Value::Newtype(b, _)) => match op {
+                Plus | Minus | Multiply | Division | Less | LessEqual | Greater | GreaterEqual | Equal | NotEqual => {
+                    Some(Err(RuntimeError::raise("TypeError", format!("Cannot mix '{}' and '{}'", a.name, b.name))))
+                }
+                _ => None,
+            },
+            (Value::Newtype(def, x), Value::Int(_)) if matches!(op, Multiply | Division) => {
+                Some(wrap(def, self.apply_binary_op(x, op, right)))
+            }
+            (Value::Int(_), Value::Newtype(def, y)) if matches!(op, Multiply) => {
+                Some(wrap(def, self.apply_binary_op(left, op, y)))
+            }
+            (Value::Newtype(def, _), other) | (other, Value::Newtype(def, _)) => match (op, other) {
+                (Plus, Value::Str(_)) => None,
+                (Plus | Minus | Multiply | Division | Less | LessEqual | Greater | GreaterEqual, _) => {
+                    Some(Err(RuntimeError::raise("TypeError", format!(
+                        "Cannot mix '{}' with '{}'; convert with {}(...) or unwrap with .value",
+                        def.name, other, def.name
+                    ))))
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn eval_condition(&mut self, expr: &Expr) -> Result<bool, RuntimeError> {
+        match self.eval_expr(expr)? {
+            Value::Bool(b) => Ok(b),
+            other => Err(RuntimeError::raise("TypeError", format!("Condition must be a bool, got '{}'", other))),
+        }
+    }
+
+    /// `s[range]` for strings (by character) and tuples.
+    fn slice(&self, target: Value, range: RangeValue) -> Result<Value, RuntimeError> {
+        let len = match &target {
+            Value::Str(s) => s.chars().count(),
+            Value::Tuple(items) => items.len(),
+            other => return Err(RuntimeError::raise("TypeError", format!("Cannot slice '{}'", other))),
+        } as i64;
+
+        if range.step < 0 {
+            return Err(RuntimeError::raise("ValueError", "Slices require a positive step"));
+        }
+        let last = if range.inclusive { range.end } else { range.end.saturating_sub(1) };
+        if range.start < 0 || last >= len {
+            return Err(RuntimeError::raise("IndexError", format!("Slice {} out of range for length {}", range, len)));
+        }
+
+        let indices = range.iter().map(|i| i as usize);
+        Ok(match target {
+            Value::Str(s) => {
+                let chars: Vec<char> = s.chars().collect();
+                Value::Str(indices.map(|i| chars[i]).collect())
+            }
+            Value::Tuple(items) => Value::Tuple(indices.map(|i| items[i].clone()).collect()),
+            _ => unreachable!(),
+        })
+    }
+
     /// Apply binary operator to two values.
     fn apply_binary_op(&self, left: &Value, op: &Operator, right: &Value) -> Result<Value, RuntimeError> {
         use Operator::*;
+        if let Some(result) = self.newtype_op(left, op, right) {
+            return result;
+        }
         match op {
             Plus => match (left, right) {
-                (Value::Int(a), Value::Int(b)) => Ok(Value::Int(a + b)),
+                (Value::None, _) | (_, Value::None) => Err(RuntimeError::raise("TypeError", "Cannot use none with '+'; provide a default with '??'")),
+                (Value::Int(a), Value::Int(b)) => checked(a.checked_add(*b), "+"),
                 (Value::Str(a), Value::Str(b)) => Ok(Value::Str(format!("{}{}", a, b))),
                 // allow mixing via tostring
                 (a, b) => Ok(Value::Str(format!("{}{}", a.to_string_value(), b.to_string_value()))),
             },
 
             Minus => match (left, right) {
-                (Value::Int(a), Value::Int(b)) => Ok(Value::Int(a - b)),
-                _ => Err(RuntimeError::Message("'-' operator requires integer operands".into())),
+                (Value::Int(a), Value::Int(b)) => checked(a.checked_sub(*b), "-"),
+                _ => Err(RuntimeError::raise("TypeError", "'-' operator requires integer operands")),
             },
 
             Multiply => match (left, right) {
-                (Value::Int(a), Value::Int(b)) => Ok(Value::Int(a * b)),
-                _ => Err(RuntimeError::Message("'*' operator requires integer operands".into())),
+                (Value::Int(a), Value::Int(b)) => checked(a.checked_mul(*b), "*"),
+                _ => Err(RuntimeError::raise("TypeError", "'*' operator requires integer operands")),
             },
 
             Division => match (left, right) {
-                (Value::Int(_), Value::Int(0)) => Err(RuntimeError::Message("Division by zero".into())),
-                (Value::Int(a), Value::Int(b)) => Ok(Value::Int(a / b)),
-                _ => Err(RuntimeError::Message("'/' operator requires integer operands".into())),
+                (Value::Int(_), Value::Int(0)) => Err(RuntimeError::raise("ZeroDivisionError", "Division by zero")),
+                // i64::MIN / -1
+                (Value::Int(a), Value::Int(b)) => checked(a.checked_div(*b), "/"),
+                _ => Err(RuntimeError::raise("TypeError", "'/' operator requires integer operands")),
+            },
+
+            In => match right {
+                Value::Range(r) => match left {
+                    Value::Int(i) => Ok(Value::Bool(r.contains(*i))),
+                    _ => Ok(Value::Bool(false)),
+                },
+                Value::Tuple(items) => Ok(Value::Bool(items.contains(left))),
+                Value::Str(s) => match left {
+                    Value::Str(sub) => Ok(Value::Bool(s.contains(sub.as_str()))),
+                    _ => Err(RuntimeError::raise("TypeError", "'in' on a string requires a string")),
+                },
+                other => Err(RuntimeError::raise("TypeError", format!("'in' is not supported for '{}'", other))),
+            },
+
+            Equal => Ok(Value::Bool(left == right)),
+            NotEqual => Ok(Value::Bool(left != right)),
+
+            Less | LessEqual | Greater | GreaterEqual => {
+                let ordering = match (left, right) {
+                    (Value::Int(a), Value::Int(b)) => a.cmp(b),
+                    (Value::Str(a), Value::Str(b)) => a.cmp(b),
+                    _ => return Err(RuntimeError::raise(
+                        "TypeError", "Comparison requires two integers or two strings"
+                    )),
+                };
+                Ok(Value::Bool(match op {
+                    Less => ordering.is_lt(),
+                    LessEqual => ordering.is_le(),
+                    Greater => ordering.is_gt(),
+                    _ => ordering.is_ge(),
+                }))
+            }
+
+            InstanceOf => match (left, right) {
+                (Value::Instance(instance), Value::Class(class)) => Ok(Value::Bool(instance.class.is_subclass_of(class))),
+                (_, Value::Class(_)) => Ok(Value::Bool(false)),
+                (value, Value::Type(def)) => Ok(Value::Bool(TypeRef::Def(def.clone()).accepts(value))),
+                (_, other) => Err(RuntimeError::raise("TypeError", format!("'instanceof' requires a class, got '{}'", other))),
+            },
+
+            Coalesce => match left {
+                Value::None => Ok(right.clone()),
+                _ => Ok(left.clone()),
             },
 
             // Assignment operator shouldn't appear as binary expression in our design:
@@ -103,3 +1021,335 @@ impl Interpreter {
         }
     }
 }
+
+/// Read a source file, or a `.json` file holding an AST dumped with `--ast`
+/// or written by another tool.
+fn read_ast(path: &Path) -> Result<Ast, RuntimeError> {
+    let source = fs::read_to_string(path)
+        .map_err(|e| RuntimeError::raise("ImportError", format!("Cannot read '{}': {}", path.display(), e)))?;
+
+    if path.extension().is_some_and(|ext| ext == "json") {
+        return Json::parse(&source)
+            .and_then(|json| dump::load(&json))
+            .map_err(|e| RuntimeError::raise("ImportError", format!("Invalid AST in '{}': {}", path.display(), e)));
+    }
+    let tokens = tokenize(&source).map_err(|e| {
+        let mut error = RuntimeError::raise("SyntaxError", e.message).into_error_value();
+        error.location = Some(format!("{}:{}:{}", display_path(path), e.line, e.column));
+        RuntimeError::Raised(error)
+    })?;
+    Ok(Parser::new(tokens).parse())
+}
+
+/// Where a resolved variable lives.
+fn slot(var: &Var) -> Slot {
+    var.slot.unwrap_or_else(|| panic!("'{}' was never resolved", var.name))
+}
+
+/// An integer argument of a primitive, described as `what` in the error.
+fn int(value: Value, what: &str) -> Result<i64, RuntimeError> {
+    match value {
+        Value::Int(i) => Ok(i),
+        other => Err(RuntimeError::raise("TypeError", format!("{} must be an integer, got '{}'", what, other))),
+    }
+}
+
+/// A string argument of the built-in `function`.
+fn string(value: Value, function: &str) -> Result<String, RuntimeError> {
+    match value {
+        Value::Str(s) => Ok(s),
+        other => Err(RuntimeError::raise("TypeError", format!("{} takes strings, got '{}'", function, other))),
+    }
+}
+
+/// The result of a checked integer operation `op`, or an `OverflowError`
+/// if it did not fit in an `int`.
+fn checked(result: Option<i64>, op: &str) -> Result<Value, RuntimeError> {
+    result.map(Value::Int).ok_or_else(|| RuntimeError::raise("OverflowError", format!("Integer overflow in '{}'", op)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lexer as lex;
+
+    fn run(source: &str) -> Result<Interpreter, RuntimeError> {
+        let mut interp = Interpreter::new();
+        let program = ir::resolve(ir::lower(&Parser::new(lex(source)).parse()), &mut |_| None)
+            .map_err(|e| RuntimeError::raise("NameError", e.message))?;
+        interp.run(&program)?;
+        Ok(interp)
+    }
+
+    #[test]
+    fn a_generic_function_takes_any_argument_type() {
+        let interp = run("fn first<T>(T a, T b) -> T { a } let n = first(1, 2); let s = first(\"x\", \"y\");").unwrap();
+        assert_eq!(interp.env.global("n"), Some(Value::Int(1)));
+        assert_eq!(interp.env.global("s"), Some(Value::Str("x".to_string())));
+    }
+
+    #[test]
+    fn function_bodies_see_globals_but_not_the_callers_locals() {
+        let interp = run("let g = 1; fn inner() { g } fn outer(g) { inner() } let r = outer(2);").unwrap();
+        assert_eq!(interp.env.global("r"), Some(Value::Int(1)));
+
+        match run("fn f(a) { a } f(1, 2);") {
+            Err(RuntimeError::Raised(e)) => assert_eq!(e.message, "f takes 1 argument(s) but 2 were given"),
+            other => panic!("expected an arity error, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn tuples_index_and_destructure_in_let_and_for() {
+        let interp = run("
+            let (q, (r, s)) = (1, (2, \"three\"));
+            let t = ((1, 2), 3);
+            let i = (t.0.1, t.1);
+            for (a, b) in ((1, 2), (3, 4)) { let sum = a + b; }
+        ").unwrap();
+        assert_eq!(interp.env.global("r"), Some(Value::Int(2)));
+        assert_eq!(interp.env.global("s"), Some(Value::Str("three".to_string())));
+        assert_eq!(interp.env.global("i"), Some(Value::Tuple(vec![Value::Int(2), Value::Int(3)])));
+
+        assert_eq!(raised("let (a, b) = (1, 2, 3);").message, "Cannot destructure a tuple of 3 elements into 2 names");
+        assert_eq!(raised("let (a, b) = 1;").message, "Cannot destructure non-tuple value '1'");
+    }
+
+    const SHAPES: &str = "
+        interface Shape { fn area(self) -> int; }
+        impl Shape for int { fn area(self) -> int { self * self } }
+        impl Shape for tuple { fn area(self) -> int { self.0 * self.1 } }
+        fn total<T: Shape, U: Shape>(T a, U b) -> int { a.area() + b.area() }
+    ";
+
+    #[test]
+    fn methods_dispatch_on_the_runtime_type_and_bounds_are_checked() {
+        let interp = run(&format!("{} let t = total(3, (2, 5));", SHAPES)).unwrap();
+        assert_eq!(interp.env.global("t"), Some(Value::Int(19)));
+
+        match run(&format!("{} total(\"x\", 1);", SHAPES)) {
+            Err(RuntimeError::Raised(e)) => {
+                assert_eq!(e.kind, "TypeError");
+                assert_eq!(e.message, "total: argument 'a' is string, which does not implement Shape");
+            }
+            other => panic!("expected a bound error, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn a_non_conforming_impl_stops_the_file_before_it_runs() {
+        let mut interp = Interpreter::new();
+        let program = ir::resolve(ir::lower(&Parser::new(lex("
+            let ran = 1;
+            interface Shape { fn area(self) -> int; fn name(self) -> string; }
+            impl Shape for int { fn area(self) -> int { self } }
+        ")).parse()), &mut |_| None).unwrap();
+        let result = interp.run(&program);
+
+        match result {
+            Err(RuntimeError::Raised(e)) => assert_eq!(e.message, "impl Shape for int is missing method 'name'"),
+            other => panic!("expected a conformance error, got {:?}", other),
+        }
+        assert_eq!(interp.env.global("ran"), None);
+    }
+
+    fn raised(source: &str) -> ErrorValue {
+        match run(source) {
+            Err(RuntimeError::Raised(e)) => e,
+            other => panic!("expected a raised error, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn errors_carry_their_kind_message_and_function_trace() {
+        let e = raised("fn inner() { throw \"bad\"; } fn outer() { inner() } outer();");
+        assert_eq!((e.kind.as_str(), e.message.as_str()), ("Error", "bad"));
+        assert_eq!(e.trace, vec!["inner", "outer"]);
+
+        let e = raised("try { 1 / 0; } catch (e) { throw e.kind + \": \" + e.message; }");
+        assert_eq!(e.message, "ZeroDivisionError: Division by zero");
+    }
+
+    #[test]
+    fn runaway_recursion_raises_a_recursion_error() {
+        let deep = std::thread::Builder::new().stack_size(Interpreter::STACK_SIZE).spawn(|| {
+            let e = raised("fn down(n) { down(n + 1) } down(0);");
+            (e.kind, e.message)
+        });
+        let (kind, message) = deep.unwrap().join().unwrap();
+        assert_eq!(kind, "RecursionError");
+        assert_eq!(message, "Maximum call depth of 1000 exceeded calling down");
+    }
+
+    #[test]
+    fn finally_runs_whether_or_not_the_body_fails() {
+        assert_eq!(raised("try { let x = 1; } finally { throw \"ran\"; }").message, "ran");
+        assert_eq!(raised("try { throw \"body\"; } finally { throw \"finally\"; }").message, "finally");
+        assert_eq!(raised("try { throw \"body\"; } finally { let x = 1; }").message, "body");
+        assert!(run("try { throw \"body\"; } catch { let x = 1; } finally { let y = 2; }").is_ok());
+    }
+
+    #[test]
+    fn operator_and_builtin_errors_are_caught_like_thrown_ones() {
+        let rethrown = |body: &str| {
+            raised(&format!("try {{ {} }} catch (e) {{ throw e.kind + \": \" + e.message; }}", body)).message
+        };
+        assert_eq!(rethrown("parseInt(1);"), "TypeError: parseInt takes strings, got '1'");
+        assert_eq!(rethrown("let x = 9223372036854775807 + 1;"), "OverflowError: Integer overflow in '+'");
+        assert_eq!(rethrown("throw \"mine\";"), "Error: mine");
+
+        let e = raised("fn f() { 1 / 0 } try { f(); } catch (e) { throw e; }");
+        assert_eq!((e.kind.as_str(), e.trace.clone()), ("ZeroDivisionError", vec!["f".to_string()]));
+    }
+
+    #[test]
+    fn question_mark_returns_err_and_none_from_the_enclosing_function() {
+        let interp = run("
+            fn half(x) { x? }
+            let a = half(ok(4));
+            let b = half(err(\"bad\"));
+            let c = half(none);
+            let d = some((1, 2))?.1;
+        ").unwrap();
+        assert_eq!(interp.env.global("a"), Some(Value::Int(4)));
+        assert_eq!(interp.env.global("b"), Some(Value::Err(Box::new(Value::Str("bad".into())))));
+        assert_eq!(interp.env.global("c"), Some(Value::None));
+        assert_eq!(interp.env.global("d"), Some(Value::Int(2)));
+
+        let e = raised("println(1);\nlet x = err(\"bad\")?;");
+        assert_eq!(e.message, "'?' got err(bad) outside of a function");
+        assert_eq!(e.location.as_deref(), Some("2:9-20"));
+    }
+
+    #[test]
+    fn builtins_return_results_for_what_can_fail() {
+        let dir = std::env::temp_dir().join(format!("sst-builtins-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("out.txt");
+        let interp = run(&format!(r#"
+            fn sum(a, b) {{ ok(parseInt(a)? + parseInt(b)?) }}
+            let good = sum("1", " 2");
+            let bad = sum("1", "two");
+            let written = writeFile({file:?}, "hi");
+            let read = readFile({file:?});
+            let missing = readFile({missing:?});
+        "#, file = file.display().to_string(), missing = dir.join("missing.txt").display().to_string())).unwrap();
+        let ok = |v| Some(Value::Ok(Box::new(v)));
+        assert_eq!(interp.env.global("good"), ok(Value::Int(3)));
+        assert_eq!(interp.env.global("bad"), Some(Value::Err(Box::new(Value::Str("'two' is not an integer".into())))));
+        assert_eq!(interp.env.global("written"), ok(Value::Int(2)));
+        assert_eq!(interp.env.global("read"), ok(Value::Str("hi".into())));
+        assert!(matches!(interp.env.global("missing"), Some(Value::Err(_))));
+        assert_eq!(raised("parseInt(1);").message, "parseInt takes strings, got '1'");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn deferred_bodies_run_last_in_first_out_on_every_exit() {
+        // each deferred error replaces the pending one, so the first
+        // defer, which runs last, decides what escapes
+        let e = raised("defer throw \"first\"; defer { throw \"second\"; } let x = 1;");
+        assert_eq!(e.message, "first");
+        assert_eq!(raised("defer throw \"cleanup\"; throw \"body\";").message, "cleanup");
+
+        let e = raised("fn f() { defer throw \"in f\"; 1 } let x = f(); throw \"after\";");
+        assert_eq!(e.message, "in f");
+        assert!(run("fn f(r) { defer let y = 2; r? } let x = f(err(1));").is_ok());
+    }
+
+    #[test]
+    fn if_blocks_and_ternaries_produce_values() {
+        let interp = run("
+            fn max(a, b) { if (a > b) { a } else { b } }
+            fn sign(n) { n < 0 ? 0 - 1 : n == 0 ? 0 : 1 }
+            let m = max(3, 9);
+            let s = (sign(0 - 4), sign(0), sign(7));
+            let b = { let y = 2; y + 3 };
+            let e = ({ if (true) { 1 } }, { if (false) { 1 } });
+        ").unwrap();
+        assert_eq!(interp.env.global("m"), Some(Value::Int(9)));
+        assert_eq!(interp.env.global("s"), Some(Value::Tuple(vec![Value::Int(-1), Value::Int(0), Value::Int(1)])));
+        assert_eq!(interp.env.global("b"), Some(Value::Int(5)));
+        assert_eq!(interp.env.global("e"), Some(Value::Tuple(vec![Value::Int(1), Value::None])));
+    }
+
+    #[test]
+    fn none_checks_and_defaults_look_through_some() {
+        let interp = run("
+            fn inc(n) { n != none ? n + 1 : 0 }
+            let a = (inc(some(2)), inc(5), inc(none));
+            let b = some(3) ?? 0;
+        ").unwrap();
+        assert_eq!(interp.env.global("a"), Some(Value::Tuple(vec![Value::Int(3), Value::Int(6), Value::Int(0)])));
+        assert_eq!(interp.env.global("b"), Some(Value::Int(3)));
+    }
+
+    #[test]
+    fn subclasses_override_methods_call_super_and_share_impls() {
+        let interp = run(&format!("{}
+            class Animal(name) {{
+                fn speak() -> string {{ \"...\" }}
+                fn describe() -> string {{ self.name + \" says \" + self.speak() }}
+            }}
+            class Dog(name, breed) extends Animal(name) {{
+                fn speak() -> string {{ \"woof\" }}
+                fn describe() -> string {{ super.describe() + \"!\" }}
+            }}
+            impl Shape for Animal {{ fn area(self) -> int {{ 1 }} }}
+            let d = Dog(\"rex\", \"lab\");
+            let text = d.describe();
+            let kinds = (d instanceof Animal, Animal(\"x\") instanceof Dog);
+            let t = total(d, 2);
+        ", SHAPES)).unwrap();
+        assert_eq!(interp.env.global("text"), Some(Value::Str("rex says woof!".to_string())));
+        assert_eq!(interp.env.global("kinds"), Some(Value::Tuple(vec![Value::Bool(true), Value::Bool(false)])));
+        assert_eq!(interp.env.global("t"), Some(Value::Int(5)));
+    }
+
+    #[test]
+    fn newtypes_wrap_and_refuse_to_mix_while_aliases_pass_through() {
+        let interp = run("
+            type UserId = int;
+            newtype Meters = int;
+            let id = UserId(4);
+            let m = Meters(5) + Meters(2);
+            let raw = (m * 3).value;
+        ").unwrap();
+        assert_eq!(interp.env.global("id"), Some(Value::Int(4)));
+        assert_eq!(interp.env.global("raw"), Some(Value::Int(21)));
+
+        let e = raised("newtype Meters = int; newtype Seconds = int; let x = Meters(1) + Seconds(1);");
+        assert_eq!(e.message, "Cannot mix 'Meters' and 'Seconds'");
+        let e = raised("type UserId = int; let x = UserId(\"a\");");
+        assert_eq!(e.message, "Expected UserId (alias of int), got 'a'");
+    }
+
+    #[test]
+    fn only_pub_members_are_visible_from_other_modules() {
+        let dir = std::env::temp_dir().join(format!("sst-pub-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("util.sst"), "
+            pub fn double(x) { helper(x) * 2 }
+            fn helper(x) { x }
+            pub class Point(pub x, y) { pub fn sum() { self.x + self.y } fn secret() { 0 } }
+        ").unwrap();
+        let main = dir.join("main.sst");
+        let check = |source: &str| {
+            fs::write(&main, format!("import util from \"util.sst\"; {}", source)).unwrap();
+            let mut interp = Interpreter::new();
+            interp.run_file(&main).map(|_| interp)
+        };
+
+        let interp = check("let d = util.double(4); let p = util.Point(1, 2); let r = (p.x, p.sum());").unwrap();
+        assert_eq!(interp.env.global("d"), Some(Value::Int(8)));
+        assert_eq!(interp.env.global("r"), Some(Value::Tuple(vec![Value::Int(1), Value::Int(3)])));
+
+        for (source, name) in [("util.helper(1);", "helper"), ("util.Point(1, 2).y;", "y"), ("util.Point(1, 2).secret();", "secret")] {
+            match check(source) {
+                Err(RuntimeError::Raised(e)) => assert_eq!(e.message, format!("`{}` is private to module `util`", name)),
+                other => panic!("expected {} to be private, got {:?}", name, other.map(|_| ())),
+            }
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -2,7 +2,17 @@
 mod value;
 mod env;
 mod interpreter;
+mod modules;
+mod functions;
+mod interfaces;
+mod classes;
+mod types;
 
-pub use value::Value;
-pub use env::Environment;
+pub use value::{Cursor, Diagnostic, ErrorValue, RangeValue, Value};
+pub use env::{Environment, Frame};
 pub use interpreter::{Interpreter, RuntimeError};
+pub use modules::{Module, ModuleLoader};
+pub use functions::Function;
+pub use interfaces::{check_impls, ImplTable, Interface};
+pub use classes::{Class, Instance, Method};
+pub use types::{TypeDef, TypeRef};
@@ -0,0 +1,98 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::runtime::Environment;
+
+/// A loaded source file and the names it defined.
+#[derive(Debug, PartialEq)]
+pub struct Module {
+    pub name: String,
+    pub path: PathBuf,
+    pub env: Environment,
+    /// Names declared `pub`; everything else is private to the module
+    pub exports: HashSet<String>,
+}
+
+/// Tracks which files are loaded or currently loading.
+///
+/// `loading` is the chain of files being executed, innermost last, which is
+/// used both to resolve relative imports and to report import cycles.
+#[derive(Debug, Default)]
+pub struct ModuleLoader {
+    cache: HashMap<PathBuf, Rc<Module>>,
+    loading: Vec<PathBuf>,
+}
+
+impl ModuleLoader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Resolve `path` relative to the file currently being executed
+    /// (or the working directory for the entry file).
+    pub fn resolve(&self, path: &str) -> Result<PathBuf, String> {
+        let base = match self.loading.last().and_then(|p| p.parent()) {
+            Some(dir) => dir.join(path),
+            None => PathBuf::from(path),
+        };
+        base.canonicalize()
+            .map_err(|e| format!("Cannot load module '{}' ({}): {}", path, base.display(), e))
+    }
+
+    /// Already-loaded module for this canonical path, if any.
+    pub fn cached(&self, path: &Path) -> Option<Rc<Module>> {
+        self.cache.get(path).cloned()
+    }
+
+    /// Mark `path` as being executed. Fails if it is already on the stack.
+    pub fn enter(&mut self, path: PathBuf) -> Result<(), String> {
+        if let Some(start) = self.loading.iter().position(|p| *p == path) {
+            let chain = self.loading[start..]
+                .iter()
+                .chain(std::iter::once(&path))
+                .map(|p| display_path(p))
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            return Err(format!("Import cycle detected: {}", chain));
+        }
+        self.loading.push(path);
+        Ok(())
+    }
+
+    /// The file currently being executed, if any.
+    pub fn current(&self) -> Option<&Path> {
+        self.loading.last().map(PathBuf::as_path)
+    }
+
+    /// Pop the file on top of the loading stack.
+    pub fn exit(&mut self) {
+        self.loading.pop();
+    }
+
+    /// Files currently executing, innermost first, for error traces.
+    pub fn trace(&self) -> Vec<String> {
+        self.loading.iter().rev().map(|p| display_path(p)).collect()
+    }
+
+    pub fn insert(&mut self, module: Rc<Module>) {
+        self.cache.insert(module.path.clone(), module);
+    }
+}
+
+/// Namespace name for `import "lib/math.sst";` -> `math`
+pub fn module_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Show paths relative to the working directory when possible.
+pub fn display_path(path: &Path) -> String {
+    std::env::current_dir()
+        .ok()
+        .and_then(|cwd| path.strip_prefix(cwd).ok().map(Path::to_path_buf))
+        .unwrap_or_else(|| path.to_path_buf())
+        .display()
+        .to_string()
+}
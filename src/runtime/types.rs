@@ -0,0 +1,74 @@
+use std::fmt;
+use std::rc::Rc;
+
+use crate::runtime::Value;
+
+/// A `type` alias or `newtype` declared in a script.
+#[derive(Debug)]
+pub struct TypeDef {
+    pub name: String,
+    pub target: TypeRef,
+    /// `newtype`: values are wrapped and never mix with the target type
+    pub nominal: bool,
+}
+
+/// What a declaration refers to on the right of `=`.
+#[derive(Debug)]
+pub enum TypeRef {
+    Int,
+    Str,
+    Bool,
+    Def(Rc<TypeDef>),
+}
+
+impl TypeRef {
+    /// Resolve a built-in type name.
+    pub fn primitive(name: &str) -> Option<Self> {
+        match name {
+            "int" => Some(TypeRef::Int),
+            "string" => Some(TypeRef::Str),
+            "bool" => Some(TypeRef::Bool),
+            _ => None,
+        }
+    }
+
+    /// Whether `value` belongs to this type. Aliases are looked through,
+    /// newtypes only accept values wrapped by that same newtype.
+    pub fn accepts(&self, value: &Value) -> bool {
+        match (self, value) {
+            (TypeRef::Int, Value::Int(_)) | (TypeRef::Str, Value::Str(_)) | (TypeRef::Bool, Value::Bool(_)) => true,
+            (TypeRef::Def(def), Value::Newtype(owner, _)) if def.nominal => Rc::ptr_eq(def, owner),
+            (TypeRef::Def(def), value) if !def.nominal => def.target.accepts(value),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for TypeRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeRef::Int => write!(f, "int"),
+            TypeRef::Str => write!(f, "string"),
+            TypeRef::Bool => write!(f, "bool"),
+            TypeRef::Def(def) => write!(f, "{}", def.name),
+        }
+    }
+}
+
+// Declared types compare by identity, like classes.
+impl PartialEq for TypeDef {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+/// `UserId (alias of int)` / `Meters`, for diagnostics.
+impl fmt::Display for TypeDef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.nominal {
+            write!(f, "{}", self.name)
+        } else {
+            write!(f, "{} (alias of {})", self.name, self.target)
+        }
+    }
+}
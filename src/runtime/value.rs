@@ -1,4 +1,8 @@
+use std::cell::RefCell;
 use std::fmt;
+use std::rc::Rc;
+
+use crate::runtime::{Class, Function, Instance, Interface, Module, TypeDef};
 
 /// Values handled by the runtime.
 #[derive(Clone, Debug, PartialEq)]
@@ -6,6 +10,26 @@ pub enum Value {
     Int(i64),
     Str(String),
     Bool(bool),
+    None,
+    /// `ok(v)` / `err(e)` — Result
+    Ok(Box<Value>),
+    Err(Box<Value>),
+    /// `some(v)` — Option, whose empty case is `None`
+    Some(Box<Value>),
+    Tuple(Vec<Value>),
+    Range(RangeValue),
+    Module(Rc<Module>),
+    Function(Rc<Function>),
+    Interface(Rc<Interface>),
+    Class(Rc<Class>),
+    Instance(Rc<Instance>),
+    /// `type` / `newtype` declarations, callable to convert a value
+    Type(Rc<TypeDef>),
+    /// A value wrapped by a newtype, e.g. `Meters(5)`
+    Newtype(Rc<TypeDef>, Box<Value>),
+    Error(Rc<ErrorValue>),
+    /// How far a `for` loop has got; only lowering's temporaries hold one
+    Cursor(Rc<RefCell<Cursor>>),
     // extendable: Float(f64), Char(char), etc.
 }
 
@@ -15,17 +39,206 @@ impl fmt::Display for Value {
             Value::Int(i) => write!(f, "{}", i),
             Value::Str(s) => write!(f, "{}", s),
             Value::Bool(b) => write!(f, "{}", b),
+            Value::None => write!(f, "none"),
+            Value::Ok(v) => write!(f, "ok({})", v),
+            Value::Err(e) => write!(f, "err({})", e),
+            Value::Some(v) => write!(f, "some({})", v),
+            Value::Tuple(items) => {
+                write!(f, "(")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                if items.len() == 1 {
+                    write!(f, ",")?;
+                }
+                write!(f, ")")
+            }
+            Value::Range(r) => write!(f, "{}", r),
+            Value::Module(m) => write!(f, "<module {}>", m.name),
+            Value::Function(func) => write!(f, "{}", func),
+            Value::Interface(i) => write!(f, "{}", i),
+            Value::Class(c) => write!(f, "<class {}>", c.name),
+            Value::Instance(i) => write!(f, "{}", i),
+            Value::Type(t) => write!(f, "<type {}>", t.name),
+            Value::Newtype(t, v) => write!(f, "{}({})", t.name, v),
+            Value::Error(e) => write!(f, "{}: {}", e.kind, e.message),
+            Value::Cursor(_) => write!(f, "<cursor>"),
+        }
+    }
+}
+
+/// An integer range. Iterated lazily, so large ranges cost nothing up front.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RangeValue {
+    pub start: i64,
+    pub end: i64,
+    pub inclusive: bool,
+    /// Never zero; negative steps count down.
+    pub step: i64,
+}
+
+impl RangeValue {
+    /// Whether `i` lies between `start` and `end`, ignoring the step.
+    fn in_bounds(&self, i: i64) -> bool {
+        match (self.step > 0, self.inclusive) {
+            (true, true) => self.start <= i && i <= self.end,
+            (true, false) => self.start <= i && i < self.end,
+            (false, true) => self.end <= i && i <= self.start,
+            (false, false) => self.end < i && i <= self.start,
         }
     }
+
+    pub fn contains(&self, i: i64) -> bool {
+        // widened: `i - start` overflows an i64 for a range wider than half of it,
+        // and `% -1` does for i64::MIN
+        self.in_bounds(i) && (i as i128 - self.start as i128) % self.step as i128 == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = i64> {
+        let range = *self;
+        std::iter::successors(Some(range.start), move |&i| i.checked_add(range.step))
+            .take_while(move |&i| range.in_bounds(i))
+    }
+}
+
+/// The items a `for` loop has still to visit.
+#[derive(Debug, PartialEq)]
+pub enum Cursor {
+    /// The next number, if any, and the range it steps through
+    Range(Option<i64>, RangeValue),
+    /// The remaining items, last first so the next one pops off the end
+    Items(Vec<Value>),
+}
+
+impl Cursor {
+    pub fn new(range: RangeValue) -> Self {
+        let first = Some(range.start).filter(|&i| range.in_bounds(i));
+        Cursor::Range(first, range)
+    }
+
+    pub fn of_items(mut items: Vec<Value>) -> Self {
+        items.reverse();
+        Cursor::Items(items)
+    }
+
+    pub fn has_next(&self) -> bool {
+        match self {
+            Cursor::Range(next, _) => next.is_some(),
+            Cursor::Items(items) => !items.is_empty(),
+        }
+    }
+}
+
+impl Iterator for Cursor {
+    type Item = Value;
+
+    fn next(&mut self) -> Option<Value> {
+        match self {
+            Cursor::Range(next, range) => {
+                let i = (*next)?;
+                *next = i.checked_add(range.step).filter(|&n| range.in_bounds(n));
+                Some(Value::Int(i))
+            }
+            Cursor::Items(items) => items.pop(),
+        }
+    }
+}
+
+impl fmt::Display for RangeValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let dots = if self.inclusive { "..=" } else { ".." };
+        write!(f, "{}{}{}", self.start, dots, self.end)?;
+        if self.step != 1 {
+            write!(f, " step {}", self.step)?;
+        }
+        Ok(())
+    }
+}
+
+/// A script-level error, raised by `throw` or by a failing runtime operation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ErrorValue {
+    /// e.g. "Error", "TypeError", "ZeroDivisionError"
+    pub kind: String,
+    pub message: String,
+    /// `file:line:col-col` of the innermost node that failed
+    pub location: Option<String>,
+    /// Files being executed when the error was raised, innermost first.
+    pub trace: Vec<String>,
+    /// The type errors behind a file failing to load; empty for anything else
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// A problem found in a file before it runs: a type error or a lint warning.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub message: String,
+    /// `file:line:col-col` it points at
+    pub location: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.message, self.location)
+    }
+}
+
+impl fmt::Display for ErrorValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.kind, self.message)?;
+        if let Some(location) = &self.location {
+            write!(f, "\n  --> {}", location)?;
+        }
+        // runaway recursion would otherwise list the same method a thousand times
+        let mut frames = self.trace.iter().peekable();
+        while let Some(frame) = frames.next() {
+            write!(f, "\n    at {}", frame)?;
+            let mut repeats = 0;
+            while frames.next_if_eq(&frame).is_some() {
+                repeats += 1;
+            }
+            if repeats > 0 {
+                write!(f, " (and {} more time(s))", repeats)?;
+            }
+        }
+        Ok(())
+    }
 }
 
 impl Value {
+    /// The name `impl ... for` uses for this value's type.
+    pub fn type_name(&self) -> &str {
+        match self {
+            Value::Int(_) => "int",
+            Value::Str(_) => "string",
+            Value::Bool(_) => "bool",
+            Value::None => "none",
+            Value::Ok(_) | Value::Err(_) => "result",
+            Value::Some(_) => "option",
+            Value::Range(_) => "range",
+            Value::Tuple(_) => "tuple",
+            Value::Module(_) => "module",
+            Value::Function(_) => "fn",
+            Value::Interface(_) => "interface",
+            Value::Error(_) => "error",
+            Value::Class(_) => "class",
+            Value::Instance(instance) => &instance.class.name,
+            Value::Type(_) => "type",
+            Value::Newtype(def, _) => &def.name,
+            Value::Cursor(_) => "cursor",
+        }
+    }
+
     /// Convert to string (used for `print` and string concatenation)
     pub fn to_string_value(&self) -> String {
         match self {
             Value::Int(i) => i.to_string(),
             Value::Str(s) => s.clone(),
             Value::Bool(b) => b.to_string(),
+            _ => self.to_string(),
         }
     }
 }
@@ -0,0 +1,120 @@
+//! Runs the `stupid_script` binary on scripts written to a temporary
+//! directory, checking what it prints and how it exits.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// A fresh directory holding `files`, for one test.
+fn scripts(test: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("sst-run-{}-{}", test, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    for (name, source) in files {
+        fs::write(dir.join(name), source).unwrap();
+    }
+    dir
+}
+
+fn run(script: &Path) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_stupid_script")).arg(script).output().unwrap()
+}
+
+#[test]
+fn a_script_runs_with_its_imports() {
+    let dir = scripts("imports", &[
+        ("shapes.sst", "pub fn area(int w, int h) -> int { w * h }"),
+        ("main.sst", "import shapes from \"shapes.sst\";\nprintln(shapes.area(3, 4));"),
+    ]);
+    let output = run(&dir.join("main.sst"));
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "12\n");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn a_failing_script_exits_with_status_1() {
+    let dir = scripts("failing", &[
+        ("a.sst", "import b from \"b.sst\";"),
+        ("b.sst", "import a from \"a.sst\";"),
+        ("throws.sst", "println(1);\nthrow \"boom\";"),
+    ]);
+
+    let output = run(&dir.join("a.sst"));
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("Runtime error: ImportError: Import cycle detected"));
+
+    let output = run(&dir.join("throws.sst"));
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "1\n");
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("Runtime error: Error: boom"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn deferred_statements_run_in_reverse_on_every_block_exit() {
+    let dir = scripts("defer", &[(
+        "defer.sst",
+        "fn f(r) { defer println(\"f done\"); defer { println(\"f closing\"); } r? }\n\
+         f(ok(1));\n\
+         f(err(2));\n\
+         for i in 0..2 { defer println(\"next\"); println(i); }\n\
+         try { defer println(\"unwound\"); throw \"boom\"; } catch (e) { println(e.message); }",
+    )]);
+    let output = run(&dir.join("defer.sst"));
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "f closing\nf done\nf closing\nf done\n0\nnext\n1\nnext\nunwound\nboom\n"
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn warnings_print_before_the_script_runs_and_type_errors_stop_it() {
+    let dir = scripts("checked", &[
+        ("old.sst", "@deprecated let old = 1;\nprintln(old);"),
+        ("bad.sst", "println(1);\nlet int x = \"s\";"),
+    ]);
+
+    // both streams into one file, to see which came first
+    let log = dir.join("old.log");
+    let out = fs::File::create(&log).unwrap();
+    let status = Command::new(env!("CARGO_BIN_EXE_stupid_script"))
+        .arg(dir.join("old.sst"))
+        .stderr(out.try_clone().unwrap())
+        .stdout(out)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert_eq!(fs::read_to_string(&log).unwrap(), format!(
+        "warning: use of deprecated 'old' ({}:2:9-12)\n1\n", dir.join("old.sst").display()
+    ));
+
+    let output = run(&dir.join("bad.sst"));
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.starts_with("Type error: Expected 'int', found 'string'"));
+    assert!(stderr.contains("Type checking failed:"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_mode_calls_each_test_function() {
+    let dir = scripts("tests", &[(
+        "add.sst",
+        "fn add(int a, int b) -> int { a + b }\n\
+         @test fn adds() { if (add(1, 2) != 3) { throw \"1 + 2 is not 3\"; } }\n\
+         @test fn fails() { if (add(1, 1) != 3) { throw \"1 + 1 is not 3\"; } }",
+    )]);
+    let output = Command::new(env!("CARGO_BIN_EXE_stupid_script"))
+        .arg("--test")
+        .arg(dir.join("add.sst"))
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("test adds ... ok\ntest fails ... FAILED: Error: 1 + 1 is not 3"));
+    assert!(stdout.ends_with("1 passed, 1 failed\n"));
+    fs::remove_dir_all(&dir).unwrap();
+}